@@ -15,7 +15,7 @@ use tokio::time::sleep;
 use tower_http::cors::{Any, CorsLayer};
 use services::llmdb::LLMDB;
 use services::db::DB;
-use crate::routes::links::{add_link, delete_link, edit_link, new_remote_topic, register_topic, search_links, vote_link};
+use crate::routes::links::{add_link, delete_link, edit_link, browse_topics, flag_content, leave_topic, list_conversations, list_topics, new_remote_topic, read_conversation, register_topic, retract_content, search_links, send_direct, vote_link};
 use crate::services::p2p;
 use crate::services::p2p::P2PClient;
 use crate::utils::fetch_data;
@@ -122,8 +122,15 @@ fn app(state: AppState) -> Router {
         .route("/", get(|| async {"Home"}))
         .route("/link", post(add_link).put(edit_link).delete(delete_link))
         .route("/vote", post(vote_link))
+        .route("/content/retract", post(retract_content))
+        .route("/content/flag", post(flag_content))
         .route("/topic/register", post(register_topic))
         .route("/topic/new", post(new_remote_topic))
+        .route("/topic/leave", post(leave_topic))
+        .route("/topics", get(list_topics))
+        .route("/topics/browse", get(browse_topics))
+        .route("/direct", post(send_direct).get(list_conversations))
+        .route("/direct/{peer_id}", get(read_conversation))
         .route("/search", get(search_links))
         .route("/ws", get(ws_handler))
         .with_state(state)
@@ -15,6 +15,12 @@ pub struct Vote {
     pub id: String,
     pub topic: String,
     pub vote: bool,
+    #[serde(default)]
+    pub abstain: bool,
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    #[serde(default)]
+    pub justification: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -22,6 +28,37 @@ pub struct Vote {
 pub struct Topic {
     pub title: String,
     pub description: String,
+    #[serde(default)]
+    pub creator: String,
+    #[serde(default)]
+    pub subscribed: bool,
+}
+
+/* topics used to be posted as a bare name */
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum NewTopic {
+    Name(String),
+    Described {
+        title: String,
+        #[serde(default)]
+        description: String,
+    },
+}
+
+impl NewTopic {
+    pub fn title(&self) -> &str {
+        match self {
+            NewTopic::Name(title) | NewTopic::Described { title, .. } => title,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            NewTopic::Name(_) => "",
+            NewTopic::Described { description, .. } => description,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -37,6 +74,16 @@ pub struct Content {
     pub id_votation: String,
     pub content: String,
     pub approved: bool,
+    /* approved, rejected, retracted or removed */
+    #[serde(default)]
+    pub state: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone)]
+pub struct ContentAction {
+    pub id: String,
+    pub topic: String,
 }
  
 #[derive(Serialize, Deserialize, Debug)]
@@ -71,3 +118,27 @@ pub struct Votation {
     pub votes_id: Vec<(String, Option<f32>)>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewDirectMessage {
+    pub peer_id: String,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DirectMessage {
+    pub id: String,
+    pub peer_id: String,
+    pub outgoing: bool,
+    pub body: String,
+    pub sent_at: u64,
+    pub delivered: bool,
+    pub read: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Conversation {
+    pub peer_id: String,
+    pub messages: usize,
+    pub unread: usize,
+    pub last_at: u64,
+}
@@ -5,7 +5,7 @@ use axum::{
 };
 use serde::Deserialize;
 use crate::{AppState};
-use crate::model::{Link, Vote};
+use crate::model::{ContentAction, Conversation, DirectMessage, Link, NewDirectMessage, NewTopic, Topic, Vote};
 use crate::services::llmdb::LLMDBLink;
 use crate::services::queue;
 use crate::services::queue::Task;
@@ -75,26 +75,106 @@ pub async fn vote_link(
     Json(vote): Json<Vote>,
 ) -> Result<Json<()>, StatusCode>{
     if let Some(p2p) = state.p2p {
-        p2p.add_vote(vote.id, vote.topic, vote.vote).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        p2p.add_vote(vote).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
     Ok(Json(()))
 }
 
+pub async fn retract_content(
+    State(state): State<AppState>,
+    Json(action): Json<ContentAction>,
+) -> Result<Json<()>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        p2p.retract_content(action).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    Ok(Json(()))
+}
+
+pub async fn flag_content(
+    State(state): State<AppState>,
+    Json(action): Json<ContentAction>,
+) -> Result<Json<Option<String>>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        let key = p2p.flag_content(action).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return Ok(Json(Some(key)));
+    }
+    Ok(Json(None))
+}
+
 pub async fn register_topic(
+    State(state): State<AppState>,
+    Json(topic): Json<NewTopic>,
+) -> Result<Json<()>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        p2p.register_topic(topic.title(), topic.description()).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    Ok(Json(()))
+}
+pub async fn leave_topic(
     State(state): State<AppState>,
     Json(topic): Json<String>,
 ) -> Result<Json<()>, StatusCode>{
     if let Some(p2p) = state.p2p {
-        p2p.register_topic(topic.as_str(), topic.as_str()).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        p2p.leave_topic(topic.as_str()).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
     Ok(Json(()))
 }
+
+pub async fn list_topics(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Topic>>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        return Ok(Json(p2p.list_topics().await));
+    }
+    Ok(Json(vec![]))
+}
+
+pub async fn browse_topics(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Topic>>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        return Ok(Json(p2p.browse_topics().await));
+    }
+    Ok(Json(vec![]))
+}
+
+pub async fn send_direct(
+    State(state): State<AppState>,
+    Json(message): Json<NewDirectMessage>,
+) -> Result<Json<Option<String>>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        let id = p2p.send_direct(message).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return Ok(Json(Some(id)));
+    }
+    Ok(Json(None))
+}
+
+pub async fn list_conversations(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Conversation>>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        return Ok(Json(p2p.list_conversations()));
+    }
+    Ok(Json(vec![]))
+}
+
+pub async fn read_conversation(
+    State(state): State<AppState>,
+    Path(peer_id): Path<String>,
+) -> Result<Json<Vec<DirectMessage>>, StatusCode>{
+    if let Some(p2p) = state.p2p {
+        let messages = p2p.read_conversation(peer_id.as_str()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return Ok(Json(messages));
+    }
+    Ok(Json(vec![]))
+}
+
 pub async fn new_remote_topic(
     State(state): State<AppState>,
-    Json(topic): Json<String>,
+    Json(topic): Json<NewTopic>,
 ) -> Result<Json<()>, StatusCode>{
     if let Some(p2p) = state.p2p {
-        p2p.new_remote_topic(topic.as_str(), topic.as_str()).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        p2p.new_remote_topic(topic.title(), topic.description()).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
     Ok(Json(()))
 }
@@ -2,11 +2,13 @@ use std::time::Duration;
 use axum::serve::Serve;
 use futures_util::FutureExt;
 use mongodb::event::sdam::ServerClosedEvent;
-use messages_p2p::{DataContent, Keypair, PeerId, StateContent, Votation, Vote};
+use messages_p2p::{Ballot, DataContent, PeerId, StateContent, Votation, Vote};
 use messages_p2p::p2p::api::APIClient;
+use messages_p2p::p2p::config::IdentityConfig;
 use serde::Deserialize;
 use tokio::task::JoinHandle;
-use crate::model::{Content, ContentToValidate, WSContentData, Topic};
+use crate::model;
+use crate::model::{Content, ContentAction, ContentToValidate, Conversation, DirectMessage, NewDirectMessage, WSContentData, Topic};
 //use messages_p2p::p2p::api::APIClient;
 
 #[derive(Debug, Deserialize)]
@@ -23,6 +25,7 @@ pub struct ConnectionData {
 }
 
 pub const MAGIC_SERVER_LINK_ADDRESS: &str = "http://34.244.185.56:3000/tracker";
+const BACKEND_IDENTITY_PATH: &str = "backend_identity.key";
 pub async fn download_server_params_from_address() -> ConnectionData {
     let response = reqwest::get(MAGIC_SERVER_LINK_ADDRESS).await.unwrap();
     let tracker_info: TrackerInfo = response.json().await.unwrap();
@@ -43,7 +46,7 @@ pub struct P2PClient {
 
 impl P2PClient {
     pub fn new(server_peer_id: &str, server_address: &str) -> anyhow::Result<Self> {
-        let keypair = Keypair::generate_ed25519();
+        let keypair = IdentityConfig::from_env(BACKEND_IDENTITY_PATH).load_keypair()?;
         let peer_id = PeerId::from(keypair.public());
         let peer_id_str = peer_id.to_string();
         
@@ -67,25 +70,54 @@ impl P2PClient {
     }
 
     pub async fn new_remote_topic(&self, topic: &str, description: &str) -> anyhow::Result<()> {
-        let topic = messages_p2p::Topic {
-            name: topic.to_string(),
-            description: description.to_string(),
-        };
+        let topic = messages_p2p::Topic::new(topic, description);
         self.client.remote_new_topic(&topic).await
     }
 
     pub async fn register_topic(&self, title: &str, description: &str) -> anyhow::Result<()> {
-        let topic = messages_p2p::Topic {
-            name: title.to_string(),
-            description: description.to_string(),
-        };
+        let topic = messages_p2p::Topic::new(title, description);
         self.client.register_topic(&topic).await
     }
 
     pub async fn get_my_topics(&self) -> Vec<Topic> {
-        self.client.get_my_topics().await.iter().map(
-            |t| (Topic {title: t.name.clone(),description: t.description.clone()}
-                )).collect()
+        self.client.get_my_topics().await.iter().map(to_topic).collect()
+    }
+
+    pub async fn list_topics(&self) -> Vec<Topic> {
+        self.client.list_topics().await.iter().map(to_topic).collect()
+    }
+
+    pub async fn browse_topics(&self) -> Vec<Topic> {
+        self.client.browse_topics().await.iter().map(to_topic).collect()
+    }
+
+    pub async fn leave_topic(&self, title: &str) -> anyhow::Result<()> {
+        self.client.leave_topic(title).await
+    }
+
+    pub async fn send_direct(&self, message: NewDirectMessage) -> anyhow::Result<String> {
+        self.client.send_direct(&message.peer_id, &message.body).await
+    }
+
+    pub fn list_conversations(&self) -> Vec<Conversation> {
+        self.client.list_conversations().into_iter().map(
+            |conversation| Conversation {
+                peer_id: conversation.peer,
+                messages: conversation.messages,
+                unread: conversation.unread,
+                last_at: conversation.last_at }).collect()
+    }
+
+    pub fn read_conversation(&self, peer_id: &str) -> anyhow::Result<Vec<DirectMessage>> {
+        Ok(self.client.read_conversation(peer_id)?.into_iter().map(
+            |message| DirectMessage {
+                id: message.id,
+                peer_id: message.peer,
+                outgoing: message.outgoing,
+                body: message.body,
+                sent_at: message.sent_at,
+                delivered: message.delivered,
+                read: message.read }).collect())
     }
 
     pub fn get_reputations(&self, topic: String) -> anyhow::Result<Vec<(String,f32)>> {
@@ -104,15 +136,22 @@ impl P2PClient {
         let values: Vec<ContentToValidate> = elems.iter().map(|e| {
             {
                 let elem = e.clone();
-                ContentToValidate { id_votation: elem.0, topic: elem.1, content: elem.2, duration: elem.3 }
+                ContentToValidate { id_votation: elem.0, topic: elem.1, content: elem.2.to_string(), duration: elem.3 }
             }
         }).collect();
         values
     }
 
-    pub async fn add_vote(&self, id_votation: String, topic: String, vote: bool) -> anyhow::Result<()> {
-        let parsed_vote =  if vote {Vote::Yes} else {Vote::No};
-        let res = self.client.add_vote(id_votation.as_str(), topic.as_str(), parsed_vote).await;
+    pub async fn add_vote(&self, vote: model::Vote) -> anyhow::Result<()> {
+        let parsed_vote = if vote.abstain {
+            Vote::Abstain
+        } else if vote.vote {
+            Vote::Yes
+        } else {
+            Vote::No
+        };
+        let ballot = Ballot::new(parsed_vote, vote.confidence.unwrap_or(1.0), vote.justification);
+        let res = self.client.add_vote(vote.id.as_str(), vote.topic.as_str(), ballot).await;
         res
     }
 
@@ -120,23 +159,33 @@ impl P2PClient {
         self.client.all_content().iter().map(
             |dc| Content{
                 id_votation: dc.id_votation.clone(),
-                content: dc.content.clone(),
-                approved: dc.approved == StateContent::Approved } ).collect()
+                content: dc.content.to_string(),
+                approved: dc.approved == StateContent::Approved,
+                state: dc.approved.to_string().to_lowercase() } ).collect()
     }
 
     pub async fn get_my_pending_content(&self) -> Vec<Content> {
         self.client.get_my_pending_to_contents_to_validate().await.iter().map(
             |dc| Content{
                 id_votation: dc.id_votation.clone(),
-                content: dc.content.clone(),
-                approved: dc.approved == StateContent::Approved } ).collect()
+                content: dc.content.to_string(),
+                approved: dc.approved == StateContent::Approved,
+                state: dc.approved.to_string().to_lowercase() } ).collect()
+    }
+
+    pub async fn retract_content(&self, action: ContentAction) -> anyhow::Result<()> {
+        self.client.retract_content(&action.topic, &action.id).await
+    }
+
+    pub async fn flag_content(&self, action: ContentAction) -> anyhow::Result<String> {
+        self.client.flag_content(&action.topic, &action.id).await
     }
 
     pub async fn voters(&self, key: String, topic: String) -> anyhow::Result<Vec<String>> {
         self.client.voters(&key, &topic).await
     }
     pub async fn validate_content(&self, key: String, topic: String, content: String) -> anyhow::Result<()> {
-        self.client.validate_content(&key, &topic, &content).await
+        self.client.validate_content(&key, &topic, content.as_str()).await
     }
     pub fn get_status_votes(&self, key: String) -> Option<Votation> {
         self.client.get_status_vote(key.as_str())
@@ -146,6 +195,15 @@ impl P2PClient {
     }
 }
 
+fn to_topic(topic: &messages_p2p::Topic) -> Topic {
+    Topic {
+        title: topic.name.clone(),
+        description: topic.description.clone(),
+        creator: topic.creator.clone(),
+        subscribed: topic.subscribed,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tokio::time;
@@ -78,7 +78,7 @@ pub async fn fetch_data(client: Arc<P2PClient>) -> WSContentData {
             let ws_votation = Votation{
                 id_votation: votation.id_votation,
                 timestamp: votation.timestamp.timestamp(),
-                content: votation.content,
+                content: votation.content.to_string(),
                 status: votation.status,
                 leader_id: votation.leader_id,
                 my_role: votation.my_role,
@@ -100,8 +100,8 @@ pub async fn fetch_data(client: Arc<P2PClient>) -> WSContentData {
 
 pub fn fake_ws_content_data() -> WSContentData {
     let my_topics = vec![
-        Topic { title: "topic-1".into(), description: "1".to_string() },
-        Topic { title: "topic-2".into(), description: "2".to_string() },
+        Topic { title: "topic-1".into(), description: "1".to_string(), creator: "peer-1".into(), subscribed: true },
+        Topic { title: "topic-2".into(), description: "2".to_string(), creator: "peer-2".into(), subscribed: true },
     ];
 
     let content = vec![
@@ -109,11 +109,13 @@ pub fn fake_ws_content_data() -> WSContentData {
             id_votation: "content-1".into(),
             content: "hello world".into(),
             approved: false,
+            state: "rejected".into(),
         },
         Content {
             id_votation: "content-2".into(),
             content: "another payload".into(),
             approved: true,
+            state: "approved".into(),
         },
     ];
 
@@ -131,6 +133,7 @@ pub fn fake_ws_content_data() -> WSContentData {
             id_votation: "content-2".into(),
             content: "hello worl2".into(),
             approved: false,
+            state: "rejected".into(),
         },
     ];
 
@@ -1,14 +1,15 @@
 mod types;
 
-use messages_p2p::{Keypair, PeerId};
+use messages_p2p::PeerId;
 
 //use chrono::TimeZone;
 use crate::types::{
-    APIError, ConnectionData, DataContent, Reputation, RuntimePendingContent, Topic, Votation,
-    Vote, VoteId,
+    APIError, ConnectionData, Conversation, DataContent, DirectMessage, Reputation,
+    RuntimePendingContent, Topic, Votation, Vote, VoteId,
 };
 use log::info;
 use messages_p2p::p2p::api::APIClient;
+use messages_p2p::p2p::config::IdentityConfig;
 use pyo3::{pyclass, pyfunction, pymethods};
 use serde::Deserialize;
 use std::sync::Arc;
@@ -43,6 +44,8 @@ fn bindings_p2p(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<RuntimePendingContent>()?;
     m.add_class::<Votation>()?;
     m.add_class::<DataContent>()?;
+    m.add_class::<DirectMessage>()?;
+    m.add_class::<Conversation>()?;
     m.add_function(wrap_pyfunction!(download_connection_data, m)?)?;
     Ok(())
 }
@@ -59,13 +62,19 @@ pub struct ClientWrapper {
 #[pymethods]
 impl ClientWrapper {
     #[new]
+    #[pyo3(signature = (server_address, server_peer_id, username, identity_path=None, passphrase=None))]
     pub fn new_py(
         server_address: String,
         server_peer_id: String,
         username: String,
+        identity_path: Option<String>,
+        passphrase: Option<String>,
     ) -> PyResult<Self> {
         RUNTIME.block_on(async {
-            let keypair = Keypair::generate_ed25519();
+            let identity_path = identity_path.unwrap_or_else(|| format!("{username}.key"));
+            let keypair = IdentityConfig::new(&identity_path, passphrase)
+                .load_keypair()
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
             let peer_id = PeerId::from(keypair.public());
             let peer_id_str = peer_id.to_string();
 
@@ -99,10 +108,7 @@ impl ClientWrapper {
 
     pub fn remote_new_topic(&self, name: String, description: String) -> PyResult<()> {
         RUNTIME.block_on(async {
-            let topic = messages_p2p::Topic {
-                name: name.clone(),
-                description,
-            };
+            let topic = messages_p2p::Topic::new(&name, &description);
             let mut guard = self.client.lock().await;
             guard
                 .remote_new_topic(&topic)
@@ -114,10 +120,7 @@ impl ClientWrapper {
 
     pub fn register_topic(&self, name: String, description: String) -> PyResult<()> {
         RUNTIME.block_on(async {
-            let topic = messages_p2p::Topic {
-                name: name.clone(),
-                description,
-            };
+            let topic = messages_p2p::Topic::new(&name, &description);
             let mut guard = self.client.lock().await;
             guard
                 .register_topic(&topic)
@@ -127,6 +130,94 @@ impl ClientWrapper {
         })
     }
 
+    pub fn leave_topic(&self, name: String) -> PyResult<()> {
+        RUNTIME.block_on(async {
+            let guard = self.client.lock().await;
+            guard
+                .leave_topic(&name)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
+    pub fn sync_history(&self, topic: String) -> PyResult<Vec<String>> {
+        RUNTIME.block_on(async {
+            let guard = self.client.lock().await;
+            guard
+                .sync_history(&topic)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
+    pub fn request(&self, peer_id: String, content: Vec<u8>) -> PyResult<Vec<u8>> {
+        RUNTIME.block_on(async {
+            /* not holding the lock while waiting for the peer */
+            let client = self.client.lock().await.clone();
+            client
+                .request(&peer_id, content)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
+    pub fn send_direct(&self, peer_id: String, body: String) -> PyResult<String> {
+        RUNTIME.block_on(async {
+            let guard = self.client.lock().await;
+            guard
+                .send_direct(&peer_id, &body)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
+    pub fn list_conversations(&self) -> PyResult<Vec<Conversation>> {
+        RUNTIME.block_on(async {
+            let guard = self.client.lock().await;
+            Ok(guard
+                .list_conversations()
+                .into_iter()
+                .map(Conversation::from)
+                .collect())
+        })
+    }
+
+    pub fn read_conversation(&self, peer_id: String) -> PyResult<Vec<DirectMessage>> {
+        RUNTIME.block_on(async {
+            let guard = self.client.lock().await;
+            guard
+                .read_conversation(&peer_id)
+                .map(|messages| messages.into_iter().map(DirectMessage::from).collect())
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
+    pub fn list_topics(&self) -> PyResult<Vec<Topic>> {
+        let topics = RUNTIME.block_on(async {
+            let guard = self.client.lock().await;
+            guard
+                .list_topics()
+                .await
+                .into_iter()
+                .map(Topic::from)
+                .collect::<Vec<Topic>>()
+        });
+        Ok(topics)
+    }
+
+    pub fn browse_topics(&self) -> PyResult<Vec<Topic>> {
+        let topics = RUNTIME.block_on(async {
+            let guard = self.client.lock().await;
+            guard
+                .browse_topics()
+                .await
+                .into_iter()
+                .map(Topic::from)
+                .collect::<Vec<Topic>>()
+        });
+        Ok(topics)
+    }
+
     pub fn get_my_topics(&self) -> PyResult<Vec<Topic>> {
         let topics = RUNTIME.block_on(async {
             let mut guard = self.client.lock().await;
@@ -156,11 +247,7 @@ impl ClientWrapper {
         let vote = RUNTIME
             .block_on(async {
                 let client = self.client.clone();
-                let parser_vote = if vote.good {
-                    messages_p2p::Vote::Yes
-                } else {
-                    messages_p2p::Vote::No
-                };
+                let parser_vote = messages_p2p::Ballot::from(vote);
                 let mut locked = client.lock().await;
                 let dis_vote = format!("{:?}", parser_vote);
                 info!("add id_votation: {id_votation} topic: {topic}, vote: {dis_vote}");
@@ -170,6 +257,28 @@ impl ClientWrapper {
         Ok(vote)
     }
 
+    pub fn retract_content(&self, id_votation: String, topic: String) -> PyResult<()> {
+        let client = self.client.clone();
+        RUNTIME.block_on(async {
+            let locked = client.lock().await;
+            locked
+                .retract_content(&topic, &id_votation)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
+    pub fn flag_content(&self, id_votation: String, topic: String) -> PyResult<String> {
+        let client = self.client.clone();
+        RUNTIME.block_on(async {
+            let locked = client.lock().await;
+            locked
+                .flag_content(&topic, &id_votation)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
     pub fn voters(&self, key: String, topic: String) -> PyResult<Vec<String>> {
         let client = self.client.clone();
         RUNTIME.block_on(async {
@@ -237,7 +346,7 @@ impl ClientWrapper {
 
             let mut guard = client.lock().await;
             guard
-                .validate_content(&key, &topic, &content)
+                .validate_content(&key, &topic, content.as_str())
                 .await
                 .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
             Ok(key)
@@ -278,7 +387,7 @@ impl ClientWrapper {
                 .map(|(a, b, c, time)| RuntimePendingContent {
                     key: a.clone(),
                     topic: b.clone(),
-                    content: c.clone(),
+                    content: c.to_string(),
                     wait_timeout: UNIX_EPOCH + (*time),
                 })
                 .collect();
@@ -46,6 +46,10 @@ pub struct Topic {
     pub name: String,
     #[pyo3(get, set)]
     pub description: String,
+    #[pyo3(get, set)]
+    pub creator: String,
+    #[pyo3(get, set)]
+    pub subscribed: bool,
 }
 
 impl From<messages_p2p::Topic> for Topic {
@@ -53,6 +57,8 @@ impl From<messages_p2p::Topic> for Topic {
         Topic {
             name: value.name,
             description: value.description,
+            creator: value.creator,
+            subscribed: value.subscribed,
         }
     }
 }
@@ -73,15 +79,40 @@ pub struct ConnectionData {
 pub struct Vote {
     #[pyo3(get, set)]
     pub good: bool,
+    #[pyo3(get, set)]
+    pub abstain: bool,
+    #[pyo3(get, set)]
+    pub confidence: f32,
+    #[pyo3(get, set)]
+    pub justification: Option<String>,
 }
 
 #[pymethods]
 impl Vote {
     #[new]
-    fn new(good: bool) -> Self { Self { good } }
+    #[pyo3(signature = (good, abstain=false, confidence=1.0, justification=None))]
+    fn new(good: bool, abstain: bool, confidence: f32, justification: Option<String>) -> Self {
+        Self { good, abstain, confidence, justification }
+    }
 
     fn __repr__(&self) -> String {
-        format!("Vote(good={})", self.good)
+        format!(
+            "Vote(good={}, abstain={}, confidence={}, justification={:?})",
+            self.good, self.abstain, self.confidence, self.justification
+        )
+    }
+}
+
+impl From<Vote> for messages_p2p::Ballot {
+    fn from(value: Vote) -> Self {
+        let vote = if value.abstain {
+            messages_p2p::Vote::Abstain
+        } else if value.good {
+            messages_p2p::Vote::Yes
+        } else {
+            messages_p2p::Vote::No
+        };
+        messages_p2p::Ballot::new(vote, value.confidence, value.justification)
     }
 }
 
@@ -140,7 +171,7 @@ impl From<messages_p2p::Votation> for Votation {
             id_votation: v.id_votation,
             timestam: UNIX_EPOCH
                 + std::time::Duration::from_millis(v.timestamp.timestamp_millis() as u64),
-            content: v.content,
+            content: v.content.to_string(),
             status: v.status,
             leader_id: v.leader_id,
             my_role: v.my_role,
@@ -160,7 +191,7 @@ impl From<&messages_p2p::Votation> for Votation {
             id_votation: v.id_votation,
             timestam: UNIX_EPOCH
                 + std::time::Duration::from_millis(v.timestamp.timestamp_millis() as u64),
-            content: v.content,
+            content: v.content.to_string(),
             status: v.status,
             leader_id: v.leader_id,
             my_role: v.my_role,
@@ -178,6 +209,8 @@ impl From<&messages_p2p::Votation> for Votation {
 enum StateContent {
     Approved,
     Rejected,
+    Retracted,
+    Removed,
 }
 
 #[pyclass]
@@ -199,6 +232,63 @@ pub struct DataContent {
     #[pyo3(get, set)]
     pub approved: StateContent,
 }
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct DirectMessage {
+    #[pyo3(get)]
+    pub id: String,
+    #[pyo3(get)]
+    pub peer_id: String,
+    #[pyo3(get)]
+    pub outgoing: bool,
+    #[pyo3(get)]
+    pub body: String,
+    #[pyo3(get)]
+    pub sent_at: u64,
+    #[pyo3(get)]
+    pub delivered: bool,
+    #[pyo3(get)]
+    pub read: bool,
+}
+
+impl From<messages_p2p::p2p::direct::DirectMessage> for DirectMessage {
+    fn from(value: messages_p2p::p2p::direct::DirectMessage) -> Self {
+        DirectMessage {
+            id: value.id,
+            peer_id: value.peer,
+            outgoing: value.outgoing,
+            body: value.body,
+            sent_at: value.sent_at,
+            delivered: value.delivered,
+            read: value.read,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    #[pyo3(get)]
+    pub peer_id: String,
+    #[pyo3(get)]
+    pub messages: usize,
+    #[pyo3(get)]
+    pub unread: usize,
+    #[pyo3(get)]
+    pub last_at: u64,
+}
+
+impl From<messages_p2p::p2p::direct::Conversation> for Conversation {
+    fn from(value: messages_p2p::p2p::direct::Conversation) -> Self {
+        Conversation {
+            peer_id: value.peer,
+            messages: value.messages,
+            unread: value.unread,
+            last_at: value.last_at,
+        }
+    }
+}
+
 #[pymethods]
 impl DataContent {
     fn __repr__(&self) -> PyResult<String> {
@@ -269,11 +359,12 @@ impl From<messages_p2p::DataContent> for DataContent {
     fn from(d: messages_p2p::DataContent) -> Self {
         Self {
             id_votation: d.id_votation,
-            content: d.content,
-            approved: if d.approved == messages_p2p::StateContent::Approved {
-                StateContent::Approved
-            } else {
-                StateContent::Rejected
+            content: d.content.to_string(),
+            approved: match d.approved {
+                messages_p2p::StateContent::Approved => StateContent::Approved,
+                messages_p2p::StateContent::Rejected => StateContent::Rejected,
+                messages_p2p::StateContent::Retracted => StateContent::Retracted,
+                messages_p2p::StateContent::Removed => StateContent::Removed,
             },
         }
     }
@@ -89,6 +89,11 @@ pub async fn handle_command(command: &str, sender: &mpsc::Sender<ChatCommand>) -
             let _ = sender.send(ChatCommand::Subscribe(channel)).await;
             false
         }
+        Ok((_, ChatCommand::Unsubscribe(channel))) => {
+            println!("Leaving channel: {channel}");
+            let _ = sender.send(ChatCommand::Unsubscribe(channel)).await;
+            false
+        }
         Ok((_, ChatCommand::Publish(channel, msg))) => {
             println!(
                 "Publishing to channel: {} with message: {}",
@@ -98,6 +103,10 @@ pub async fn handle_command(command: &str, sender: &mpsc::Sender<ChatCommand>) -
             let _ = sender.send(ChatCommand::Publish(channel, msg)).await;
             false
         }
+        Ok((_, ChatCommand::FindProviders(_)
+        | ChatCommand::SyncHistory(_, _)
+        | ChatCommand::SyncSummary(_, _)
+        | ChatCommand::SendDirect(_, _))) => false,
         Ok((_, ChatCommand::SendOne(topic, msg))) => {
             println!(
                 "Publishing to topic: {} with message: {}",
@@ -122,7 +131,7 @@ fn parse_commands(input: &str) -> IResult<&str, ChatCommand> {
             code: nom::error::ErrorKind::Tag,
         }));
     }
-    alt((parse_quit, parse_subscribe, parse_publish)).parse(input)
+    alt((parse_quit, parse_subscribe, parse_unsubscribe, parse_publish)).parse(input)
 }
 
 fn parse_quit(input: &str) -> IResult<&str, ChatCommand> {
@@ -136,6 +145,12 @@ fn parse_subscribe(input: &str) -> IResult<&str, ChatCommand> {
     Ok((input, ChatCommand::Subscribe(input.to_string())))
 }
 
+fn parse_unsubscribe(input: &str) -> IResult<&str, ChatCommand> {
+    let (input, _) = tag("leave")(input)?;
+    let (input, _) = space1(input)?;
+    Ok((input, ChatCommand::Unsubscribe(input.to_string())))
+}
+
 fn parse_publish(input: &str) -> IResult<&str, ChatCommand> {
     let (input, _) = tag("publish")(input)?;
     let (input, _) = space1(input)?;
@@ -1,5 +1,8 @@
 pub mod p2p;
 pub use libp2p::identity::Keypair;
 pub use libp2p::PeerId;
-pub use protocol_p2p::models::db::{DataContent, StateContent, Topic, Votation, VoteStatus};
-pub use protocol_p2p::models::messages::Vote;
+pub use protocol_p2p::models::db::{
+    ContentKind, ContentPayload, DataContent, OutcomeSummary, StateContent, Topic, TopicPolicy,
+    Votation, VoteStatus,
+};
+pub use protocol_p2p::models::messages::{Ballot, Vote};
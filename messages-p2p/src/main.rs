@@ -1,8 +1,14 @@
 use crate::p2p::bootstrap::BootstrapServer;
-use axum::{extract::State, routing::get, Router};
+use crate::p2p::config::IdentityConfig;
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Router,
+};
 use dotenv::dotenv;
-use libp2p::identity::Keypair;
 use once_cell::sync::Lazy;
+use protocol_p2p::archive::ArchiveHandler;
+use protocol_p2p::db::init_db;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::net::{IpAddr, SocketAddr};
@@ -18,6 +24,8 @@ static DEFAULT_LISTENS_ON: Lazy<Vec<String>> = Lazy::new(|| {
         .collect()
 });
 
+const BOOTSTRAP_IDENTITY_PATH: &str = "bootstrap_identity.key";
+
 pub fn init_logging() {
     let _ = env_logger::builder()
         .is_test(false)
@@ -41,28 +49,69 @@ struct DataConnection {
 #[derive(Clone)]
 struct AppState {
     data_connection: DataConnection,
+    archive: Option<ArchiveHandler>,
 }
 
 async fn get_tracker_info(State(state): State<Arc<AppState>>) -> String {
     log::info!("Returning tracker info");
-    serde_json::to_string(&state.data_connection.clone()).unwrap_or_else(|e| {
+    to_json(&state.data_connection)
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|e| {
         eprintln!("{e:?}");
         "{\"error\": \"error parsing\"}".to_string()
     })
 }
 
+const NO_ARCHIVE: &str = "{\"error\": \"archive mode is disabled\"}";
+
+async fn get_archived_topics(State(state): State<Arc<AppState>>) -> String {
+    match &state.archive {
+        Some(archive) => to_json(&archive.topics().await),
+        None => NO_ARCHIVE.to_string(),
+    }
+}
+
+async fn get_archived_contents(
+    State(state): State<Arc<AppState>>,
+    Path(topic): Path<String>,
+) -> String {
+    match &state.archive {
+        Some(archive) => to_json(&archive.contents(&topic)),
+        None => NO_ARCHIVE.to_string(),
+    }
+}
+
+async fn get_archived_results(
+    State(state): State<Arc<AppState>>,
+    Path(topic): Path<String>,
+) -> String {
+    match &state.archive {
+        Some(archive) => to_json(&archive.results(&topic)),
+        None => NO_ARCHIVE.to_string(),
+    }
+}
+
 impl TrackerServer {
     async fn new(ip: String, port: u16) -> Self {
         log::info!("Initializing TrackerServer...");
         TrackerServer { ip, port }
     }
 
-    async fn run(&self, data_connection: (String, Vec<String>)) -> anyhow::Result<()> {
+    async fn run(
+        &self,
+        data_connection: (String, Vec<String>),
+        archive: Option<ArchiveHandler>,
+    ) -> anyhow::Result<()> {
         let data_connection = DataConnection {
             id: data_connection.0,
             addresses: data_connection.1,
         };
-        let shared_state = Arc::new(AppState { data_connection });
+        let shared_state = Arc::new(AppState {
+            data_connection,
+            archive,
+        });
 
         // let addr_str = format!("{}:{}", self.ip, self.port);
         let ip: IpAddr = self.ip.parse()?;
@@ -73,6 +122,9 @@ impl TrackerServer {
 
         let app = Router::new()
             .route("/tracker", get(get_tracker_info))
+            .route("/archive/topics", get(get_archived_topics))
+            .route("/archive/{topic}/contents", get(get_archived_contents))
+            .route("/archive/{topic}/results", get(get_archived_results))
             .with_state(shared_state.clone());
 
         let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -101,7 +153,9 @@ async fn main() {
             .collect::<Vec<_>>(),
         Err(_) => DEFAULT_LISTENS_ON.to_vec(),
     };
-    let keypair = Keypair::generate_ed25519();
+    let keypair = IdentityConfig::from_env(BOOTSTRAP_IDENTITY_PATH)
+        .load_keypair()
+        .expect("Failed to load bootstrap identity");
     println!(
         "TRACKER_ADDRESS: host={} port={}",
         tracker_address, tracker_port
@@ -112,15 +166,25 @@ async fn main() {
     println!("p2p_port = {:?}", p2p_port);
     println!("Public key for server = {:?} ", keypair.public());
 
+    /* archive mode keeps the approved history of every topic in its own database */
+    let archive = env::var("ARCHIVE_DB").ok().map(|path| {
+        println!("Archive database = {:?}", path);
+        let db = init_db(&path).expect("Failed to open the archive database");
+        ArchiveHandler::new(Arc::new(db))
+    });
+
     let tracker = TrackerServer::new(tracker_address.to_string(), tracker_port).await;
 
     /*  p2p bootstrap server */
     let mut p2p_bootstrap_server = BootstrapServer::new(keypair, listen_ons, vec![], p2p_port)
         .await
         .unwrap();
+    if let Some(archive) = archive.clone() {
+        p2p_bootstrap_server = p2p_bootstrap_server.with_archive(archive);
+    }
     let data_connection = p2p_bootstrap_server.data_connection();
     select! {
-        res = tracker.run(data_connection) => match res {
+        res = tracker.run(data_connection, archive) => match res {
             Ok(_) => println!("Tracker server exited."),
             Err(e) => eprintln!("Tracker server error: {e:?}"),
         },
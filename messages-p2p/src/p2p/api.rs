@@ -1,16 +1,19 @@
-use crate::p2p::config::{load_config, BootstrapConfig, Config};
-use crate::p2p::node::NetworkClientNode;
+use crate::p2p::config::{load_config, BootstrapConfig, Config, IdentityConfig};
+use crate::p2p::direct::{Conversation, Conversations, DirectMessage};
+use crate::p2p::behaviours::REQUEST_TIMEOUT;
+use crate::p2p::node::{DirectRequest, NetworkClientNode};
+use crate::p2p::store::KadStore;
 use libp2p::identity;
 use messages_types::ChatCommand;
 use protocol_p2p::client::ValidatorClient;
 use protocol_p2p::db::init_db;
-use protocol_p2p::handler::ValidatorHandler;
-use protocol_p2p::models::db::{DataContent, Topic, Votation, VoteStatus};
-use protocol_p2p::models::messages::Vote;
+use protocol_p2p::handler::{RequestHandler, ValidatorHandler};
+use protocol_p2p::models::db::{ContentPayload, DataContent, Topic, Votation, VoteStatus};
+use protocol_p2p::models::messages::Ballot;
 use protocol_p2p::{db, Db};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
 
 pub const BUFFER_SIZE: usize = 32;
 
@@ -19,8 +22,11 @@ pub struct APIClient {
     pub peer_id: libp2p::PeerId,
     pub db: Arc<Db>,
     validator_client: Arc<ValidatorClient>,
+    validator_handler: ValidatorHandler,
     pub node: Arc<Mutex<Option<NetworkClientNode<ValidatorHandler>>>>,
     tx: mpsc::Sender<ChatCommand>,
+    request_tx: mpsc::Sender<DirectRequest>,
+    conversations: Conversations,
 }
 
 pub async fn load_server_tracker_data(url: &str) -> anyhow::Result<(String, Vec<String>)> {
@@ -61,6 +67,8 @@ impl APIClient {
                 peer_id: peer_id_server.to_string(),
                 address: address.to_string(),
             },
+            identity: IdentityConfig::default(),
+            persistent_dht: false,
         };
         Self::inner_from_config(keypair, &config, name_peer)
     }
@@ -78,6 +86,16 @@ impl APIClient {
         }
     }
 
+    /// Builds the client with the persistent keypair referenced by the config identity.
+    pub fn from_config_identity(
+        name_peer: Option<String>,
+        path: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let config = load_config(path)?;
+        let keypair = config.identity.load_keypair()?;
+        Self::inner_from_config(keypair, &config, name_peer)
+    }
+
     pub fn inner_from_config(
         keypair: identity::Keypair,
         config: &Config,
@@ -95,16 +113,32 @@ impl APIClient {
         let (tx, rx) = mpsc::channel::<ChatCommand>(BUFFER_SIZE); // save tx if needed outside
         let validator_client =
             ValidatorClient::new(peer_id, tx.clone(), db.clone(), keypair.clone());
-        let validator_handler = ValidatorHandler::new(peer_id, db.clone());
-        let node =
-            NetworkClientNode::new(keypair.clone(), config, validator_handler, (tx.clone(), rx))?;
+        let validator_handler = ValidatorHandler::new(keypair.clone(), db.clone());
+        let store = if config.persistent_dht {
+            KadStore::sled(&db, peer_id)?
+        } else {
+            KadStore::memory(peer_id)
+        };
+        let conversations = Conversations::new(&db, keypair.clone())?;
+        let node = NetworkClientNode::from_config_with_store(
+            keypair.clone(),
+            config,
+            validator_handler.clone(),
+            (tx.clone(), rx),
+            store,
+        )?
+        .with_conversations(conversations.clone());
+        let request_tx = node.request_sender();
 
         Ok(Self {
             peer_id,
             db,
             validator_client: Arc::new(validator_client),
+            validator_handler,
             node: Arc::new(Mutex::new(Some(node))),
             tx,
+            request_tx,
+            conversations,
         })
     }
 
@@ -112,13 +146,68 @@ impl APIClient {
         self.tx.clone()
     }
 
+    /// Serves the direct requests of the peers with `handler`, until then
+    /// they are answered with an error. It can be set while the node runs.
+    pub fn set_request_handler(&self, handler: RequestHandler) {
+        self.validator_handler.set_request_handler(handler);
+    }
+
+    /// Sends a direct request to a peer and waits for its response.
+    /// It fails if the peer has not registered a request handler.
+    pub async fn request(&self, peer_id: &str, content: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let peer = peer_id.parse::<libp2p::PeerId>().map_err(anyhow::Error::msg)?;
+        let (reply, response) = oneshot::channel();
+        self.request_tx
+            .send(DirectRequest {
+                peer,
+                content,
+                reply,
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("Network node is not running"))?;
+        /* the behaviour times out on its own, this only guards a stalled node */
+        match tokio::time::timeout(REQUEST_TIMEOUT * 2, response).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(anyhow::anyhow!("Network node dropped the request to {peer}")),
+            Err(_) => Err(anyhow::anyhow!("Request to {peer} timed out")),
+        }
+    }
+
+    /// Sends an encrypted message that only `peer_id` can read, returns its id.
+    /// It is marked as delivered once the peer acknowledges it.
+    pub async fn send_direct(&self, peer_id: &str, body: &str) -> anyhow::Result<String> {
+        let peer = peer_id.parse::<libp2p::PeerId>().map_err(anyhow::Error::msg)?;
+        let envelope = self.conversations.seal(&peer, body)?;
+        self.tx
+            .send(ChatCommand::SendDirect(
+                peer_id.to_string(),
+                serde_json::to_vec(&envelope)?,
+            ))
+            .await?;
+        Ok(envelope.id)
+    }
+
+    /// Peers with direct messages, the most recent conversation first.
+    pub fn list_conversations(&self) -> Vec<Conversation> {
+        self.conversations.list()
+    }
+
+    /// Decrypted messages exchanged with a peer, they are marked as read.
+    pub fn read_conversation(&self, peer_id: &str) -> anyhow::Result<Vec<DirectMessage>> {
+        self.conversations.read(peer_id)
+    }
+
     pub async fn remote_new_topic(&self, topic: &Topic) -> anyhow::Result<()> {
         match self
             .validator_client
-            .remote_new_topic(topic.name.as_str())
+            .remote_new_topic(topic)
             .await
         {
             Ok(_) => {
+                let topic = Topic {
+                    creator: self.peer_id.to_string(),
+                    ..topic.clone()
+                };
                 db::save_topic(&self.db, &topic).await?;
                 Ok(())
             }
@@ -133,24 +222,87 @@ impl APIClient {
             .await
         {
             Ok(_) => {
-                db::save_topic(&self.db, topic).await?;
+                let topic = Topic {
+                    subscribed: true,
+                    ..topic.clone()
+                };
+                db::save_topic(&self.db, &topic).await?;
                 Ok(())
             }
             Err(e) => Err(anyhow::anyhow!(e)),
         }
     }
 
-    pub async fn get_my_topics(&self) -> Vec<Topic> {
+    /// Creates a topic whose content only `members` can read, and joins it.
+    pub async fn create_private_topic(&self, topic: &Topic, members: &[String]) -> anyhow::Result<()> {
+        self.validator_client
+            .create_private_topic(topic, members)
+            .await
+    }
+
+    /// Sends the key of a private topic I own to a new member.
+    pub async fn invite_to_topic(&self, topic: &str, peer_id: &str) -> anyhow::Result<()> {
+        self.validator_client.invite_to_topic(topic, peer_id).await
+    }
+
+    /// Removes a member of a private topic I own, the topic key is rotated.
+    pub async fn remove_from_topic(&self, topic: &str, peer_id: &str) -> anyhow::Result<()> {
+        self.validator_client.remove_from_topic(topic, peer_id).await
+    }
+
+    /// Stops listening to a topic, it stays in the registry as not subscribed.
+    pub async fn leave_topic(&self, name: &str) -> anyhow::Result<()> {
+        if !db::set_topic_subscribed(&self.db, name, false)? {
+            return Err(anyhow::anyhow!("Unknown topic: {}", name));
+        }
+        self.validator_client.unregister_topic(name).await
+    }
+
+    /// Every topic known by this node, joined or only announced by other peers.
+    pub async fn list_topics(&self) -> Vec<Topic> {
         db::get_topics(&self.db).await
     }
 
+    /// Peers known to serve a topic, looked up in the DHT.
+    pub async fn find_topic_peers(&self, topic: &str) -> anyhow::Result<Vec<String>> {
+        self.validator_client.find_topic_peers(topic).await
+    }
+
+    /// Asks the peers of a topic for the content decided before I joined it.
+    /// The results are recounted and merged as they arrive, returns the peers asked.
+    pub async fn sync_history(&self, topic: &str) -> anyhow::Result<Vec<String>> {
+        self.validator_client.sync_history(topic).await
+    }
+
+    /// Topics announced on the network that this node has not joined.
+    pub async fn browse_topics(&self) -> Vec<Topic> {
+        db::get_topics(&self.db)
+            .await
+            .into_iter()
+            .filter(|topic| !topic.subscribed)
+            .collect()
+    }
+
+    pub async fn get_my_topics(&self) -> Vec<Topic> {
+        db::get_topics(&self.db)
+            .await
+            .into_iter()
+            .filter(|topic| topic.subscribed)
+            .collect()
+    }
+
     pub fn new_key_available(&self, topic: &str, content: &str) -> anyhow::Result<String> {
         self.validator_client.new_key_available(topic, content)
     }
 
-    pub async fn add_vote(&self, id_votation: &str, topic: &str, vote: Vote) -> anyhow::Result<()> {
+    pub async fn add_vote(
+        &self,
+        id_votation: &str,
+        topic: &str,
+        ballot: impl Into<Ballot>,
+    ) -> anyhow::Result<()> {
         self.validator_client
-            .add_vote(id_votation, topic, vote)
+            .add_vote(id_votation, topic, ballot)
             .await
     }
 
@@ -173,7 +325,7 @@ impl APIClient {
         &self,
         key: &str,
         topic: &str,
-        content: &str,
+        content: impl Into<ContentPayload>,
     ) -> anyhow::Result<()> {
         self.validator_client
             .ask_validation(key, topic, content)
@@ -181,6 +333,22 @@ impl APIClient {
         Ok(())
     }
 
+    pub async fn appeal_content(&self, topic: &str, content: &str) -> anyhow::Result<String> {
+        self.validator_client.appeal(topic, content).await
+    }
+
+    pub async fn retract_content(&self, topic: &str, key: &str) -> anyhow::Result<()> {
+        self.validator_client.retract(topic, key).await
+    }
+
+    pub async fn flag_content(&self, topic: &str, key: &str) -> anyhow::Result<String> {
+        self.validator_client.flag(topic, key).await
+    }
+
+    pub fn content_history(&self, key: &str) -> Vec<DataContent> {
+        self.validator_client.get_content_history(key)
+    }
+
     pub fn get_status_vote(&self, key: &str) -> Option<Votation> {
         self.validator_client.get_status_vote(key)
     }
@@ -188,7 +356,9 @@ impl APIClient {
         self.validator_client.get_status_voteses()
     }
 
-    pub async fn get_runtime_content_to_validate(&self) -> Vec<(String, String, String, Duration)> {
+    pub async fn get_runtime_content_to_validate(
+        &self,
+    ) -> Vec<(String, String, ContentPayload, Duration)> {
         self.validator_client.get_content_to_evaluate().await
     }
     /* db */
@@ -219,10 +389,10 @@ impl APIClient {
     pub async fn spawn_validator(&self) -> tokio::task::JoinHandle<()> {
         let client = self.clone().validator_client;
         tokio::spawn(async move {
-            client
-                .wait_for_validators()
-                .await
-                .expect("Validator client failed");
+            let (validators, votations) =
+                tokio::join!(client.wait_for_validators(), client.watch_votations());
+            validators.expect("Validator client failed");
+            votations.expect("Votations watcher failed");
         })
     }
 
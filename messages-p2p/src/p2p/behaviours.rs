@@ -1,12 +1,16 @@
 use libp2p::identity::Keypair;
-use libp2p::kad::store::MemoryStore;
-use libp2p::kad::Behaviour;
+use crate::p2p::store::KadStore;
+use libp2p::kad::{Behaviour, RecordKey};
 use libp2p::{gossipsub, identify, relay, request_response};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io;
 use std::time::Duration;
 
 use libp2p::{request_response::json::Behaviour as JsonBehaviour, StreamProtocol};
+use crate::p2p::direct::{DirectAck, DirectEnvelope};
+use protocol_p2p::models::messages::{
+    HistoryPage, HistoryRequest, SummaryRequest, SummaryResponse,
+};
 
 pub fn build_gossipsub_behaviour(
     client_pair_keys: &Keypair,
@@ -38,33 +42,74 @@ pub fn build_gossipsub_behaviour(
     Ok(gossipsub)
 }
 
-pub fn build_kademlia_behaviour(key: &Keypair) -> Behaviour<MemoryStore> {
-    Behaviour::new(
-        key.public().to_peer_id(),
-        MemoryStore::new(key.public().to_peer_id()),
-    )
+pub fn build_kademlia_behaviour(key: &Keypair, store: KadStore) -> Behaviour<KadStore> {
+    Behaviour::new(key.public().to_peer_id(), store)
+}
+
+/* DHT key under which the peers subscribed to a topic announce themselves */
+pub fn topic_provider_key(topic: &str) -> RecordKey {
+    RecordKey::new(&format!("topic/{topic}"))
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct OneToOneRequest {
-    pub(crate) content: Vec<u8>,
+    pub content: Vec<u8>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct OneToOneResponse {
-    content: Vec<u8>,
+    pub content: Vec<u8>,
+    /* set when the peer did not serve the request, the content is then empty */
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
+/* a direct request fails if the peer does not answer in this time */
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub fn build_request_response_behaviour() -> JsonBehaviour<OneToOneRequest, OneToOneResponse> {
     JsonBehaviour::<OneToOneRequest, OneToOneResponse>::new(
         [(
             StreamProtocol::new("/json-one-to-one"),
             request_response::ProtocolSupport::Full,
         )],
+        request_response::Config::default().with_request_timeout(REQUEST_TIMEOUT),
+    )
+}
+
+/// Pages of the approved history of a topic, served to the peers that join it late.
+pub fn build_history_behaviour() -> JsonBehaviour<HistoryRequest, HistoryPage> {
+    JsonBehaviour::<HistoryRequest, HistoryPage>::new(
+        [(
+            StreamProtocol::new("/history-sync/1.0.0"),
+            request_response::ProtocolSupport::Full,
+        )],
         request_response::Config::default(),
     )
 }
 
+/// Walk of the summaries of a topic, to fetch only the content a peer is missing.
+pub fn build_summary_behaviour() -> JsonBehaviour<SummaryRequest, SummaryResponse> {
+    JsonBehaviour::<SummaryRequest, SummaryResponse>::new(
+        [(
+            StreamProtocol::new("/topic-summary/1.0.0"),
+            request_response::ProtocolSupport::Full,
+        )],
+        request_response::Config::default(),
+    )
+}
+
+/// Delivery of encrypted direct messages, acknowledged once stored by the receiver.
+pub fn build_direct_behaviour() -> JsonBehaviour<DirectEnvelope, DirectAck> {
+    JsonBehaviour::<DirectEnvelope, DirectAck>::new(
+        [(
+            StreamProtocol::new("/direct-message/1.0.0"),
+            request_response::ProtocolSupport::Full,
+        )],
+        request_response::Config::default().with_request_timeout(REQUEST_TIMEOUT),
+    )
+}
+
 pub fn build_relay_behaviour(key: &Keypair) -> relay::Behaviour {
     relay::Behaviour::new(key.public().to_peer_id(), Default::default())
 }
@@ -1,23 +1,35 @@
 use crate::p2p::behaviours::{
-    build_gossipsub_behaviour, build_identify_behaviour, build_kademlia_behaviour, build_relay_behaviour,
-    build_request_response_behaviour, OneToOneRequest, OneToOneResponse,
+    build_gossipsub_behaviour, build_history_behaviour, build_identify_behaviour,
+    build_kademlia_behaviour, build_relay_behaviour, build_request_response_behaviour,
+    build_summary_behaviour, topic_provider_key, OneToOneRequest, OneToOneResponse,
 };
 use futures::StreamExt;
 use libp2p::gossipsub::IdentTopic;
 use libp2p::identity::Keypair;
-use libp2p::kad::store::MemoryStore;
 use libp2p::request_response::json::Behaviour as JsonBehaviour;
 use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
-use libp2p::{gossipsub, identify, kad, noise, relay, tcp, yamux, Multiaddr, PeerId, Swarm};
-use protocol_p2p::models::messages::{ContentMessage, DEFAULT_TOPIC};
+use libp2p::{
+    gossipsub, identify, kad, noise, relay, request_response, tcp, yamux, Multiaddr, PeerId, Swarm,
+};
+use crate::p2p::config::data_dir_from_env;
+use crate::p2p::store::KadStore;
+use protocol_p2p::archive::ArchiveHandler;
+use protocol_p2p::models::messages::{
+    ContentMessage, HistoryPage, HistoryRequest, SignedMessage, SummaryRequest, SummaryResponse,
+    DEFAULT_TOPIC,
+};
+use protocol_p2p::MessageHandler;
+use std::path::Path;
 
 #[derive(NetworkBehaviour)]
 struct BootstrapNodeBehaviour {
-    kademlia: kad::Behaviour<MemoryStore>,
+    kademlia: kad::Behaviour<KadStore>,
     gossipsub: gossipsub::Behaviour,
     relay: relay::Behaviour,
     identify: identify::Behaviour,
     request_response: JsonBehaviour<OneToOneRequest, OneToOneResponse>,
+    history: JsonBehaviour<HistoryRequest, HistoryPage>,
+    summary: JsonBehaviour<SummaryRequest, SummaryResponse>,
 }
 
 pub struct BootstrapServer {
@@ -27,14 +39,27 @@ pub struct BootstrapServer {
     listen_ons: Vec<String>,
     external_multi_address: String,
     p2p_port: i32,
+    /* when set, the results and topics seen on the network are archived */
+    archive: Option<ArchiveHandler>,
 }
 
 impl BootstrapServer {
+    /// Bootstrap server keeping its databases in `DATA_DIR`, see `with_data_dir`.
     pub async fn new(
         keypair: Keypair,
         listen_ons: Vec<String>,
         topics: Vec<String>,
         p2p_port: i32,
+    ) -> anyhow::Result<Self> {
+        Self::with_data_dir(keypair, listen_ons, topics, p2p_port, &data_dir_from_env()).await
+    }
+
+    pub async fn with_data_dir(
+        keypair: Keypair,
+        listen_ons: Vec<String>,
+        topics: Vec<String>,
+        p2p_port: i32,
+        data_dir: &Path,
     ) -> anyhow::Result<Self> {
         let peer_id = keypair.public().to_peer_id();
 
@@ -48,13 +73,18 @@ impl BootstrapServer {
             log::info!("Subscribing to topic={:?}", topic);
             gossipsub.subscribe(&IdentTopic::new(topic.clone()))?;
         }
+        /* the DHT of the bootstrap survives restarts, one database per identity */
+        let dht_db = sled::open(data_dir.join(format!("dht_{peer_id}")))?;
+        let store = KadStore::sled(&dht_db, peer_id)?;
         let behaviour = |key: &Keypair| -> BootstrapNodeBehaviour {
             BootstrapNodeBehaviour {
-                kademlia: build_kademlia_behaviour(key),
+                kademlia: build_kademlia_behaviour(key, store),
                 gossipsub,
                 relay: build_relay_behaviour(key),
                 identify: build_identify_behaviour(key),
                 request_response: build_request_response_behaviour(),
+                history: build_history_behaviour(),
+                summary: build_summary_behaviour(),
             }
         };
 
@@ -83,9 +113,16 @@ impl BootstrapServer {
             listen_ons,
             p2p_port,
             external_multi_address,
+            archive: None,
         })
     }
 
+    /// Runs the server as an archive node, recording the approved history of every topic.
+    pub fn with_archive(mut self, archive: ArchiveHandler) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
     pub fn data_connection(&self) -> (String, Vec<String>) {
         let peer_id_str = self.peer_id.to_string();
         let mut listen_ons = self.listen_ons.clone();
@@ -146,12 +183,28 @@ impl BootstrapServer {
                         String::from_utf8_lossy(&message.data),
                     );
 
+                    if let (Some(archive), Some(source)) = (self.archive.as_mut(), message.source) {
+                        archive.handle_message(source, &message.data, &message.topic.to_string());
+                    }
+
                     //parsing message
 
-                    if let Ok(res) = serde_json::from_slice::<ContentMessage>(&message.data) {
+                    if let Ok(res) = serde_json::from_slice::<SignedMessage>(&message.data) {
                         log::debug!("Got message from peer: {res:?}");
-                        if let ContentMessage::RegisterTopic { topic } = res {
+                        if let Err(e) = res.verify() {
+                            log::warn!("⛔ Discarding message with invalid signature: {e}");
+                        } else if let ContentMessage::RegisterTopic { topic, .. } = res.payload {
                             log::debug!("Registering topic: {topic:?}");
+                            // the archive is found in the DHT by the peers joining the topic
+                            if self.archive.is_some()
+                                && let Err(e) = self
+                                    .swarm
+                                    .behaviour_mut()
+                                    .kademlia
+                                    .start_providing(topic_provider_key(&topic))
+                            {
+                                log::warn!("Failed to provide topic {topic:?}: {e}");
+                            }
                             let subscribed = self
                                 .swarm
                                 .behaviour_mut()
@@ -162,6 +215,47 @@ impl BootstrapServer {
                     }
                 }
 
+                /* the archive serves the history of the topics, without it there is nothing to serve */
+                SwarmEvent::Behaviour(BootstrapNodeBehaviourEvent::History(
+                    request_response::Event::Message {
+                        peer,
+                        message: request_response::Message::Request { request, channel, .. },
+                        ..
+                    },
+                )) => {
+                    let page = match self.archive.as_mut() {
+                        Some(archive) => archive.handle_history_request(peer, &request),
+                        None => HistoryPage {
+                            topic: request.topic.clone(),
+                            ..Default::default()
+                        },
+                    };
+                    log::debug!(
+                        "Serving {} results of topic {} to {peer}",
+                        page.results.len(),
+                        page.topic
+                    );
+                    if self.swarm.behaviour_mut().history.send_response(channel, page).is_err() {
+                        log::warn!("Failed to send history to {peer}");
+                    }
+                }
+
+                SwarmEvent::Behaviour(BootstrapNodeBehaviourEvent::Summary(
+                    request_response::Event::Message {
+                        peer,
+                        message: request_response::Message::Request { request, channel, .. },
+                        ..
+                    },
+                )) => {
+                    let response = match self.archive.as_mut() {
+                        Some(archive) => archive.handle_summary_request(peer, &request),
+                        None => request.empty_response(),
+                    };
+                    if self.swarm.behaviour_mut().summary.send_response(channel, response).is_err() {
+                        log::warn!("Failed to send summary to {peer}");
+                    }
+                }
+
                 SwarmEvent::Behaviour(BootstrapNodeBehaviourEvent::Kademlia(
                     kad::Event::InboundRequest { request },
                 )) => {
@@ -1,12 +1,19 @@
+use crate::p2p::identity::load_or_create_keypair;
 use libp2p::identity::Keypair;
 use libp2p::{Multiaddr, PeerId};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::{Path, PathBuf};
 use tokio::io;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     pub bootstrap: BootstrapConfig,
+    #[serde(default)]
+    pub identity: IdentityConfig,
+    /* keep the DHT records in the node database instead of memory */
+    #[serde(default)]
+    pub persistent_dht: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -15,7 +22,51 @@ pub struct BootstrapConfig {
     pub address: String,
 }
 
+/// Where the node keypair lives. The passphrase, if any, encrypts the file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IdentityConfig {
+    pub path: String,
+    pub passphrase: Option<String>,
+}
+
+impl Default for IdentityConfig {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_IDENTITY_PATH.to_string(),
+            passphrase: None,
+        }
+    }
+}
+
+impl IdentityConfig {
+    pub fn new(path: &str, passphrase: Option<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            passphrase,
+        }
+    }
+
+    /// Reads `IDENTITY_PATH` and `IDENTITY_PASSPHRASE`, falling back to `default_path`.
+    pub fn from_env(default_path: &str) -> Self {
+        Self {
+            path: std::env::var("IDENTITY_PATH").unwrap_or(default_path.to_string()),
+            passphrase: std::env::var("IDENTITY_PASSPHRASE").ok(),
+        }
+    }
+
+    pub fn load_keypair(&self) -> anyhow::Result<Keypair> {
+        load_or_create_keypair(Path::new(&self.path), self.passphrase.as_deref())
+    }
+}
+
 const DEFAULT_CONFIG: &str = "temp_config.toml";
+pub const DEFAULT_IDENTITY_PATH: &str = "identity.key";
+pub const DEFAULT_DATA_DIR: &str = "data";
+
+/// Directory of the databases kept by a node, `DATA_DIR` or `data` in the working directory.
+pub fn data_dir_from_env() -> PathBuf {
+    PathBuf::from(std::env::var("DATA_DIR").unwrap_or(DEFAULT_DATA_DIR.to_string()))
+}
 
 pub fn save_config(peer_id: &PeerId, address: Multiaddr) -> anyhow::Result<()> {
     let bootstrap_config = BootstrapConfig {
@@ -24,6 +75,8 @@ pub fn save_config(peer_id: &PeerId, address: Multiaddr) -> anyhow::Result<()> {
     };
     let config = Config {
         bootstrap: bootstrap_config,
+        identity: IdentityConfig::default(),
+        persistent_dht: false,
     };
     fs::write(DEFAULT_CONFIG, toml::to_string(&config)?.as_str())?;
     Ok(())
@@ -0,0 +1,313 @@
+use anyhow::anyhow;
+use libp2p::identity::Keypair;
+use libp2p::PeerId;
+use protocol_p2p::crypto;
+use protocol_p2p::Db;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CONVERSATIONS_TREE: &str = "direct_messages";
+/// Longest body of a direct message, in bytes.
+pub const MAX_BODY_LEN: usize = 64 * 1024;
+/* a sealed body carries its nonce and authentication tag */
+const MAX_SEALED_LEN: usize = MAX_BODY_LEN + 12 + 16;
+/* separates the keys of direct messages from other uses of the identity keys */
+const DIRECT_CONTEXT: &str = "p2p-direct-message/1";
+
+/// Direct message as it travels and as it is stored, only the two peers can open it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectEnvelope {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+    /* milliseconds since the epoch, set by the sender */
+    pub sent_at: u64,
+    pub sealed: Vec<u8>,
+}
+
+impl DirectEnvelope {
+    // header authenticated with the body, it can not be moved to another conversation
+    fn header(&self) -> Vec<u8> {
+        format!("{}:{}:{}:{}", self.id, self.from, self.to, self.sent_at).into_bytes()
+    }
+}
+
+/// Answer of the receiver once the message is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectAck {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredMessage {
+    envelope: DirectEnvelope,
+    outgoing: bool,
+    delivered: bool,
+    read: bool,
+}
+
+/// A direct message opened for reading.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectMessage {
+    pub id: String,
+    pub peer: String,
+    pub outgoing: bool,
+    pub body: String,
+    pub sent_at: u64,
+    pub delivered: bool,
+    pub read: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Conversation {
+    pub peer: String,
+    pub messages: usize,
+    pub unread: usize,
+    pub last_at: u64,
+}
+
+/// Encrypted conversations of this node with other peers. The key of each
+/// conversation is agreed from the identity keys of both peers, so nothing
+/// has to be exchanged before the first message. Messages are kept sealed in
+/// sled and only opened when read.
+#[derive(Clone)]
+pub struct Conversations {
+    keypair: Keypair,
+    peer_id: PeerId,
+    messages: sled::Tree,
+}
+
+fn message_key(peer: &str, sent_at: u64, id: &str) -> String {
+    format!("{peer}/{sent_at:020}/{id}")
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl Conversations {
+    pub fn new(db: &Db, keypair: Keypair) -> anyhow::Result<Self> {
+        Ok(Self {
+            peer_id: keypair.public().to_peer_id(),
+            keypair,
+            messages: db.open_tree(CONVERSATIONS_TREE)?,
+        })
+    }
+
+    fn key_with(&self, peer: &str) -> anyhow::Result<[u8; 32]> {
+        crypto::shared_key(&self.keypair, peer, DIRECT_CONTEXT)
+    }
+
+    fn store(&self, peer: &str, message: &StoredMessage) -> anyhow::Result<()> {
+        let envelope = &message.envelope;
+        self.messages.insert(
+            message_key(peer, envelope.sent_at, &envelope.id),
+            serde_json::to_vec(message)?,
+        )?;
+        Ok(())
+    }
+
+    fn stored(&self, peer: &str) -> Vec<(sled::IVec, StoredMessage)> {
+        self.messages
+            .scan_prefix(format!("{peer}/"))
+            .filter_map(Result::ok)
+            .filter_map(|(key, value)| Some((key, serde_json::from_slice(&value).ok()?)))
+            .collect()
+    }
+
+    /// Encrypts a message for `to` and keeps it as not delivered yet.
+    pub fn seal(&self, to: &PeerId, body: &str) -> anyhow::Result<DirectEnvelope> {
+        if body.len() > MAX_BODY_LEN {
+            return Err(anyhow!("Direct message of {} bytes, at most {MAX_BODY_LEN}", body.len()));
+        }
+        let to = to.to_string();
+        let mut envelope = DirectEnvelope {
+            id: format!("{:016x}", rand::rng().random::<u64>()),
+            from: self.peer_id.to_string(),
+            to: to.clone(),
+            sent_at: now_millis(),
+            sealed: Vec::new(),
+        };
+        envelope.sealed = crypto::seal(&self.key_with(&to)?, body.as_bytes(), &envelope.header())?;
+        self.store(
+            &to,
+            &StoredMessage {
+                envelope: envelope.clone(),
+                outgoing: true,
+                delivered: false,
+                read: true,
+            },
+        )?;
+        Ok(envelope)
+    }
+
+    /// Stores a message received from `peer`, it is rejected if it was not sealed by it for me.
+    pub fn receive(&self, peer: &PeerId, envelope: DirectEnvelope) -> anyhow::Result<DirectAck> {
+        let from = peer.to_string();
+        if envelope.from != from || envelope.to != self.peer_id.to_string() {
+            return Err(anyhow!("Direct message {} is not from {from} to me", envelope.id));
+        }
+        if envelope.sealed.len() > MAX_SEALED_LEN {
+            return Err(anyhow!("Direct message {} is too large", envelope.id));
+        }
+        self.open(&from, &envelope)?;
+        let ack = DirectAck {
+            id: envelope.id.clone(),
+        };
+        let key = message_key(&from, envelope.sent_at, &envelope.id);
+        if !self.messages.contains_key(key)? {
+            self.store(
+                &from,
+                &StoredMessage {
+                    envelope,
+                    outgoing: false,
+                    delivered: true,
+                    read: false,
+                },
+            )?;
+        }
+        Ok(ack)
+    }
+
+    pub fn mark_delivered(&self, peer: &PeerId, id: &str) -> anyhow::Result<()> {
+        let peer = peer.to_string();
+        let suffix = format!("/{id}");
+        for (key, mut message) in self.stored(&peer) {
+            if message.outgoing && key.ends_with(suffix.as_bytes()) {
+                message.delivered = true;
+                self.store(&peer, &message)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Messages sent to `peer` it has not acknowledged yet, to be sent again.
+    pub fn undelivered(&self, peer: &PeerId) -> Vec<DirectEnvelope> {
+        self.stored(&peer.to_string())
+            .into_iter()
+            .filter(|(_, message)| message.outgoing && !message.delivered)
+            .map(|(_, message)| message.envelope)
+            .collect()
+    }
+
+    fn open(&self, peer: &str, envelope: &DirectEnvelope) -> anyhow::Result<String> {
+        let plain = crypto::open(&self.key_with(peer)?, &envelope.sealed, &envelope.header())?;
+        Ok(String::from_utf8(plain)?)
+    }
+
+    /// Peers I have exchanged messages with, the most recent first.
+    pub fn list(&self) -> Vec<Conversation> {
+        let mut conversations: Vec<Conversation> = Vec::new();
+        for (key, value) in self.messages.iter().filter_map(Result::ok) {
+            let Ok(message) = serde_json::from_slice::<StoredMessage>(&value) else {
+                continue;
+            };
+            let key = String::from_utf8_lossy(&key);
+            let Some((peer, _)) = key.split_once('/') else {
+                continue;
+            };
+            // keys are sorted by peer, its messages are contiguous
+            if conversations.last().is_none_or(|last| last.peer != peer) {
+                conversations.push(Conversation {
+                    peer: peer.to_string(),
+                    messages: 0,
+                    unread: 0,
+                    last_at: 0,
+                });
+            }
+            if let Some(conversation) = conversations.last_mut() {
+                conversation.messages += 1;
+                conversation.unread += usize::from(!message.read);
+                conversation.last_at = conversation.last_at.max(message.envelope.sent_at);
+            }
+        }
+        conversations.sort_by_key(|conversation| std::cmp::Reverse(conversation.last_at));
+        conversations
+    }
+
+    /// Opens the messages exchanged with `peer` in the order they were sent, and marks them read.
+    pub fn read(&self, peer: &str) -> anyhow::Result<Vec<DirectMessage>> {
+        let mut messages = Vec::new();
+        for (_, mut message) in self.stored(peer) {
+            let body = self.open(peer, &message.envelope)?;
+            if !message.read {
+                message.read = true;
+                self.store(peer, &message)?;
+            }
+            messages.push(DirectMessage {
+                id: message.envelope.id,
+                peer: peer.to_string(),
+                outgoing: message.outgoing,
+                body,
+                sent_at: message.envelope.sent_at,
+                delivered: message.delivered,
+                read: message.read,
+            });
+        }
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol_p2p::db::init_db;
+
+    #[test]
+    fn direct_messages_are_only_read_by_both_ends() {
+        let dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+        let [alice_db, bob_db] =
+            dirs.each_ref().map(|dir| init_db(dir.path().to_str().unwrap()).unwrap());
+        let alice = Conversations::new(&alice_db, Keypair::generate_ed25519()).unwrap();
+        let bob = Conversations::new(&bob_db, Keypair::generate_ed25519()).unwrap();
+
+        let envelope = alice.seal(&bob.peer_id, "why was it rejected?").unwrap();
+        assert!(!String::from_utf8_lossy(&envelope.sealed).contains("rejected"));
+
+        // somebody else can not pass it as theirs
+        let eve = PeerId::random();
+        assert!(bob.receive(&eve, envelope.clone()).is_err());
+        let mut forged = envelope.clone();
+        forged.sent_at += 1;
+        assert!(bob.receive(&alice.peer_id, forged).is_err());
+
+        // kept to be sent again until bob acknowledges it
+        assert_eq!(alice.undelivered(&bob.peer_id).len(), 1);
+        let ack = bob.receive(&alice.peer_id, envelope).unwrap();
+        alice.mark_delivered(&bob.peer_id, &ack.id).unwrap();
+        assert!(alice.undelivered(&bob.peer_id).is_empty());
+
+        let conversations = bob.list();
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(conversations[0].peer, alice.peer_id.to_string());
+        assert_eq!(conversations[0].unread, 1);
+
+        let messages = bob.read(&alice.peer_id.to_string()).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, "why was it rejected?");
+        assert!(!messages[0].outgoing);
+        assert_eq!(bob.list()[0].unread, 0);
+
+        let sent = alice.read(&bob.peer_id.to_string()).unwrap();
+        assert!(sent[0].outgoing && sent[0].delivered);
+    }
+
+    #[test]
+    fn large_direct_messages_are_refused() {
+        let dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+        let [alice_db, bob_db] =
+            dirs.each_ref().map(|dir| init_db(dir.path().to_str().unwrap()).unwrap());
+        let alice = Conversations::new(&alice_db, Keypair::generate_ed25519()).unwrap();
+        let bob = Conversations::new(&bob_db, Keypair::generate_ed25519()).unwrap();
+
+        assert!(alice.seal(&bob.peer_id, &"a".repeat(MAX_BODY_LEN + 1)).is_err());
+        let mut envelope = alice.seal(&bob.peer_id, &"a".repeat(MAX_BODY_LEN)).unwrap();
+        assert!(bob.receive(&alice.peer_id, envelope.clone()).is_ok());
+        envelope.sealed.push(0);
+        assert!(bob.receive(&alice.peer_id, envelope).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{anyhow, bail};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use libp2p::identity::Keypair;
+use rand::Rng;
+use std::fs;
+use std::path::Path;
+
+/// Header written in front of passphrase protected identity files.
+const ENCRYPTED_MAGIC: &[u8] = b"P2PKEY1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Loads the node identity stored in `path`, creating a new ed25519 keypair
+/// the first time. The keypair is stored protobuf-encoded; when a passphrase
+/// is given the file is encrypted with a key derived from it.
+pub fn load_or_create_keypair(path: &Path, passphrase: Option<&str>) -> anyhow::Result<Keypair> {
+    if path.exists() {
+        let keypair = load_keypair(path, passphrase)?;
+        log::info!(
+            "Loaded identity {} from {:?}",
+            keypair.public().to_peer_id(),
+            path
+        );
+        return Ok(keypair);
+    }
+    let keypair = Keypair::generate_ed25519();
+    save_keypair(path, &keypair, passphrase)?;
+    log::info!(
+        "Created new identity {} in {:?}",
+        keypair.public().to_peer_id(),
+        path
+    );
+    Ok(keypair)
+}
+
+pub fn load_keypair(path: &Path, passphrase: Option<&str>) -> anyhow::Result<Keypair> {
+    let data = fs::read(path)?;
+    let encoded = match data.strip_prefix(ENCRYPTED_MAGIC) {
+        Some(encrypted) => {
+            let passphrase = passphrase
+                .ok_or_else(|| anyhow!("Identity file {path:?} is encrypted, passphrase required"))?;
+            decrypt(encrypted, passphrase)?
+        }
+        None => data,
+    };
+    Ok(Keypair::from_protobuf_encoding(&encoded)?)
+}
+
+pub fn save_keypair(path: &Path, keypair: &Keypair, passphrase: Option<&str>) -> anyhow::Result<()> {
+    let encoded = keypair.to_protobuf_encoding()?;
+    let data = match passphrase {
+        Some(passphrase) => [ENCRYPTED_MAGIC, encrypt(&encoded, passphrase)?.as_slice()].concat(),
+        None => encoded,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> anyhow::Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive identity key: {e}"))?;
+    Ok(key)
+}
+
+// layout: salt | nonce | ciphertext
+fn encrypt(plain: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::rng().fill(&mut salt);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plain)
+        .map_err(|e| anyhow!("Failed to encrypt identity: {e}"))?;
+    Ok([salt.as_slice(), nonce.as_slice(), ciphertext.as_slice()].concat())
+}
+
+fn decrypt(data: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    if data.len() < SALT_LEN + NONCE_LEN {
+        bail!("Identity file is truncated");
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Wrong passphrase or corrupted identity file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_is_kept_between_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+
+        let first = load_or_create_keypair(&path, None).unwrap();
+        let second = load_or_create_keypair(&path, None).unwrap();
+        assert_eq!(first.public().to_peer_id(), second.public().to_peer_id());
+    }
+
+    #[test]
+    fn encrypted_identity_needs_the_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+
+        let created = load_or_create_keypair(&path, Some("secret")).unwrap();
+        let loaded = load_keypair(&path, Some("secret")).unwrap();
+        assert_eq!(created.public().to_peer_id(), loaded.public().to_peer_id());
+
+        assert!(load_keypair(&path, None).is_err());
+        assert!(load_keypair(&path, Some("wrong")).is_err());
+    }
+}
@@ -3,4 +3,7 @@ pub mod api;
 pub mod behaviours;
 pub mod bootstrap;
 pub mod config;
+pub mod direct;
+pub mod identity;
 pub mod node;
+pub mod store;
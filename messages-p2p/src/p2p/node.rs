@@ -1,36 +1,53 @@
 use crate::p2p::behaviours::{
-    build_gossipsub_behaviour, build_identify_behaviour, build_kademlia_behaviour,
-    build_request_response_behaviour,
+    build_direct_behaviour, build_gossipsub_behaviour, build_history_behaviour,
+    build_identify_behaviour, build_kademlia_behaviour, build_request_response_behaviour,
+    build_summary_behaviour, topic_provider_key,
 };
 use crate::p2p::behaviours::{OneToOneRequest, OneToOneResponse};
 use crate::p2p::config::{load_config, print_config, Config};
+use crate::p2p::direct::{Conversations, DirectAck, DirectEnvelope};
+use crate::p2p::store::KadStore;
 use futures::StreamExt;
 use libp2p::request_response::json::Behaviour as JsonBehaviour;
+use libp2p::request_response::OutboundRequestId;
 use libp2p::{
     gossipsub::{self, IdentTopic as Topic}, identity,
-    kad::{self, store::MemoryStore},
+    kad,
     noise,
     swarm::{NetworkBehaviour, Swarm, SwarmEvent},
     tcp,
     yamux,
     Multiaddr, PeerId,
 };
-use libp2p::{identify, relay};
+use libp2p::{identify, relay, request_response};
 use messages_types::ChatCommand;
-use protocol_p2p::models::messages::DEFAULT_TOPIC;
+use protocol_p2p::models::messages::{
+    HistoryPage, HistoryRequest, SummaryRequest, SummaryResponse, DEFAULT_TOPIC,
+};
 use protocol_p2p::MessageHandler;
 use rand::Rng;
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
 
 #[derive(NetworkBehaviour)]
 pub struct NodeBehaviour {
-    pub kademlia: kad::Behaviour<MemoryStore>,
+    pub kademlia: kad::Behaviour<KadStore>,
     pub gossip_sub: gossipsub::Behaviour,
-    pub request_response: JsonBehaviour<OneToOneRequest, OneToOneResponse>, //TODO is working?
+    pub request_response: JsonBehaviour<OneToOneRequest, OneToOneResponse>,
     pub relay: relay::client::Behaviour,
     pub identify: identify::Behaviour,
+    pub history: JsonBehaviour<HistoryRequest, HistoryPage>,
+    pub summary: JsonBehaviour<SummaryRequest, SummaryResponse>,
+    pub direct: JsonBehaviour<DirectEnvelope, DirectAck>,
+}
+
+/// Direct request to a peer, its response or the reason it failed is sent on `reply`.
+pub struct DirectRequest {
+    pub peer: PeerId,
+    pub content: Vec<u8>,
+    pub reply: oneshot::Sender<anyhow::Result<Vec<u8>>>,
 }
 
 pub struct NetworkClientNode<H: MessageHandler> {
@@ -38,6 +55,14 @@ pub struct NetworkClientNode<H: MessageHandler> {
     swarm: Swarm<NodeBehaviour>,
     command_rx: mpsc::Receiver<ChatCommand>,
     command_tx: mpsc::Sender<ChatCommand>,
+    request_rx: mpsc::Receiver<DirectRequest>,
+    request_tx: mpsc::Sender<DirectRequest>,
+    /* direct requests waiting for their response */
+    pending_requests: HashMap<OutboundRequestId, oneshot::Sender<anyhow::Result<Vec<u8>>>>,
+    /* lookups of topic providers in flight, by the topic they look for */
+    provider_lookups: HashMap<kad::QueryId, String>,
+    /* store of the encrypted direct messages, they are refused without it */
+    conversations: Option<Conversations>,
     handler: H,
 }
 
@@ -63,13 +88,24 @@ impl MessageHandler for SimpleClientHandler {
         }
         None
     }
+
+    fn handle_request(&mut self, peer: PeerId, data: &[u8]) -> Option<Vec<u8>> {
+        self.handle_message(peer, data, "")
+    }
 }
 
 pub fn run_node() -> anyhow::Result<Arc<Mutex<NetworkClientNode<SimpleClientHandler>>>> {
+    let config = load_config(None)?;
+    let keypair = config.identity.load_keypair()?;
+    run_node_with_keypair(keypair)
+}
+
+pub fn run_node_with_keypair(
+    keypair: identity::Keypair,
+) -> anyhow::Result<Arc<Mutex<NetworkClientNode<SimpleClientHandler>>>> {
     let handler = SimpleClientHandler;
     let config = load_config(None)?;
 
-    let keypair = identity::Keypair::generate_ed25519();
     let (tx, rx) = mpsc::channel::<ChatCommand>(32); // save tx if needed outside
     let node = Arc::new(Mutex::new(NetworkClientNode::new(
         keypair,
@@ -97,6 +133,18 @@ impl<H: MessageHandler> NetworkClientNode<H> {
         node_config: &Config,
         handler: H,
         channel: (mpsc::Sender<ChatCommand>, mpsc::Receiver<ChatCommand>),
+    ) -> anyhow::Result<Self> {
+        let store = KadStore::memory(client_keypair.public().to_peer_id());
+        Self::from_config_with_store(client_keypair, node_config, handler, channel, store)
+    }
+
+    /// Builds the node keeping the DHT records in the given store.
+    pub fn from_config_with_store(
+        client_keypair: identity::Keypair,
+        node_config: &Config,
+        handler: H,
+        channel: (mpsc::Sender<ChatCommand>, mpsc::Receiver<ChatCommand>),
+        store: KadStore,
     ) -> anyhow::Result<Self> {
         // bootstrap info/
         let server_peer_id: PeerId = node_config.bootstrap.peer_id.parse()?;
@@ -114,11 +162,14 @@ impl<H: MessageHandler> NetworkClientNode<H> {
 
         let behaviour = |key: &identity::Keypair, relay_behaviour| {
             Ok(NodeBehaviour {
-                kademlia: build_kademlia_behaviour(key),
+                kademlia: build_kademlia_behaviour(key, store),
                 gossip_sub: gossipsub,
                 request_response: build_request_response_behaviour(),
                 relay: relay_behaviour,
                 identify: build_identify_behaviour(key),
+                history: build_history_behaviour(),
+                summary: build_summary_behaviour(),
+                direct: build_direct_behaviour(),
             })
         };
 
@@ -148,11 +199,17 @@ impl<H: MessageHandler> NetworkClientNode<H> {
         // Running bootstrap kadmelia
         swarm.behaviour_mut().kademlia.bootstrap()?;
 
+        let (request_tx, request_rx) = mpsc::channel::<DirectRequest>(32);
         Ok(Self {
             peer_id: client_peer_id,
             swarm,
             command_rx: channel.1,
             command_tx: channel.0.clone(),
+            request_rx,
+            request_tx,
+            pending_requests: HashMap::new(),
+            provider_lookups: HashMap::new(),
+            conversations: None,
             handler,
         })
     }
@@ -179,6 +236,16 @@ impl<H: MessageHandler> NetworkClientNode<H> {
         self.command_tx.clone()
     }
 
+    pub fn with_conversations(mut self, conversations: Conversations) -> Self {
+        self.conversations = Some(conversations);
+        self
+    }
+
+    /// Channel for direct requests that wait for the response of the peer.
+    pub fn request_sender(&self) -> mpsc::Sender<DirectRequest> {
+        self.request_tx.clone()
+    }
+
     pub async fn run(&mut self) -> anyhow::Result<()> {
         let peer_id_str = self.peer_id.to_string();
 
@@ -188,10 +255,28 @@ impl<H: MessageHandler> NetworkClientNode<H> {
                 Some(cmd) = self.command_rx.recv() => {
                     match cmd {
                         ChatCommand::Subscribe(topic_name) => {
-                            let topic = Topic::new(topic_name);
+                            let key = topic_provider_key(&topic_name);
+                            let topic = Topic::new(topic_name.clone());
                             if self.swarm.behaviour_mut().gossip_sub.subscribe(&topic).is_ok() {
                                 log::debug!("✅ Subscribed to topic: {topic}");
                             }
+                            let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+                            if let Err(e) = kademlia.start_providing(key.clone()) {
+                                log::warn!("❌ Failed to provide topic {topic}: {e}");
+                            }
+                            let query_id = kademlia.get_providers(key);
+                            self.provider_lookups.insert(query_id, topic_name);
+                        }
+                        ChatCommand::Unsubscribe(topic_name) => {
+                            self.swarm.behaviour_mut().kademlia.stop_providing(&topic_provider_key(&topic_name));
+                            let topic = Topic::new(topic_name);
+                            if self.swarm.behaviour_mut().gossip_sub.unsubscribe(&topic) {
+                                log::debug!("👋 Unsubscribed from topic: {topic}");
+                            }
+                        }
+                        ChatCommand::FindProviders(topic_name) => {
+                            let query_id = self.swarm.behaviour_mut().kademlia.get_providers(topic_provider_key(&topic_name));
+                            self.provider_lookups.insert(query_id, topic_name);
                         }
                         ChatCommand::Publish(topic_name, msg) => {
                             let topic = Topic::new(topic_name);
@@ -209,12 +294,58 @@ impl<H: MessageHandler> NetworkClientNode<H> {
                                 }
                             );
                         },
+                        ChatCommand::SyncHistory(peer_id, request) => {
+                            let Ok(request) = serde_json::from_slice::<HistoryRequest>(&request) else {
+                                log::warn!("❌ Malformed history request for {peer_id}");
+                                continue;
+                            };
+                            let Ok(peer) = PeerId::from_str(&peer_id) else {
+                                log::warn!("❌ Invalid peer id {peer_id} to sync history from");
+                                continue;
+                            };
+                            log::debug!("📚 Asking {peer_id} for the history of topic {}", request.topic);
+                            self.swarm.behaviour_mut().history.send_request(&peer, request);
+                        }
+                        ChatCommand::SendDirect(peer_id, envelope) => {
+                            let Ok(envelope) = serde_json::from_slice::<DirectEnvelope>(&envelope) else {
+                                log::warn!("❌ Malformed direct message for {peer_id}");
+                                continue;
+                            };
+                            let Ok(peer) = PeerId::from_str(&peer_id) else {
+                                log::warn!("❌ Invalid peer id {peer_id} to send a direct message to");
+                                continue;
+                            };
+                            log::debug!("✉️ Sending direct message {} to {peer_id}", envelope.id);
+                            self.swarm.behaviour_mut().direct.send_request(&peer, envelope);
+                        }
+                        ChatCommand::SyncSummary(peer_id, request) => {
+                            let Ok(request) = serde_json::from_slice::<SummaryRequest>(&request) else {
+                                log::warn!("❌ Malformed summary request for {peer_id}");
+                                continue;
+                            };
+                            let Ok(peer) = PeerId::from_str(&peer_id) else {
+                                log::warn!("❌ Invalid peer id {peer_id} to sync the summary with");
+                                continue;
+                            };
+                            self.swarm.behaviour_mut().summary.send_request(&peer, request);
+                        }
                         ChatCommand::Quit => {
                             log::debug!("👋 Quitting the node: {peer_id_str}");
                             return Ok(());
                         }
                     }
                 }
+                /* direct requests, correlated with their response by the request id */
+                Some(request) = self.request_rx.recv() => {
+                    log::debug!("🟢 Sending direct request to peer: {}", request.peer);
+                    let request_id = self.swarm.behaviour_mut().request_response.send_request(
+                        &request.peer,
+                        OneToOneRequest {
+                            content: request.content
+                        }
+                    );
+                    self.pending_requests.insert(request_id, request.reply);
+                }
                 event = self.swarm.select_next_some() => {
                     match event {
                         SwarmEvent::NewListenAddr { address, .. } => {
@@ -222,6 +353,12 @@ impl<H: MessageHandler> NetworkClientNode<H> {
                         }
                         SwarmEvent::ConnectionEstablished { peer_id, .. } => {
                             log::debug!("✅ Connected to: {peer_id}");
+                            /* the direct messages the peer missed while it was away are sent again */
+                            let undelivered = self.conversations.as_ref().map(|conversations| conversations.undelivered(&peer_id));
+                            for envelope in undelivered.unwrap_or_default() {
+                                log::debug!("✉️ Sending direct message {} to {peer_id} again", envelope.id);
+                                self.swarm.behaviour_mut().direct.send_request(&peer_id, envelope);
+                            }
                         }
                         SwarmEvent::Behaviour(NodeBehaviourEvent::Relay(event)) => {
                             log::debug!("<UNK> Relay event: {event:?}");
@@ -269,6 +406,140 @@ impl<H: MessageHandler> NetworkClientNode<H> {
                             }
 
 
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::Kademlia(kad::Event::OutboundQueryProgressed {
+                            id, result: kad::QueryResult::GetProviders(result), step, ..
+                        })) => {
+                            let topic_name = if step.last {
+                                self.provider_lookups.remove(&id)
+                            } else {
+                                self.provider_lookups.get(&id).cloned()
+                            };
+                            let Some(topic_name) = topic_name else {
+                                continue;
+                            };
+                            // a lookup finding nobody is reported too, the callers waiting for it stop
+                            let providers: Vec<PeerId> = match result {
+                                Ok(kad::GetProvidersOk::FoundProviders { providers, .. }) => providers.into_iter().collect(),
+                                Ok(kad::GetProvidersOk::FinishedWithNoAdditionalRecord { .. }) => Vec::new(),
+                                Err(e) => {
+                                    log::debug!("❌ Lookup of the providers of {topic_name} failed: {e}");
+                                    Vec::new()
+                                }
+                            };
+                            /* joining the providers lets the gossipsub mesh of the topic form */
+                            for provider in providers.iter().filter(|provider| **provider != self.peer_id) {
+                                if !self.swarm.is_connected(provider)
+                                    && let Err(e) = self.swarm.dial(*provider)
+                                {
+                                    log::debug!("❌ Failed to dial provider {provider}: {e}");
+                                }
+                            }
+                            self.handler.handle_providers(&topic_name, &providers);
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::RequestResponse(request_response::Event::Message { peer, message, .. })) => {
+                            match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    log::debug!("📨 Direct request from {peer}: {}", String::from_utf8_lossy(&request.content));
+                                    let response = match self.handler.handle_request(peer, &request.content) {
+                                        Some(content) => OneToOneResponse { content, error: None },
+                                        None => OneToOneResponse { content: Vec::new(), error: Some("request not served".to_string()) },
+                                    };
+                                    if self.swarm.behaviour_mut().request_response.send_response(channel, response).is_err() {
+                                        log::warn!("❌ Failed to answer the request of {peer}");
+                                    }
+                                }
+                                request_response::Message::Response { request_id, response } => {
+                                    match self.pending_requests.remove(&request_id) {
+                                        Some(reply) => {
+                                            let result = match response.error {
+                                                Some(error) => Err(anyhow::anyhow!("{peer} refused the request: {error}")),
+                                                None => Ok(response.content),
+                                            };
+                                            let _ = reply.send(result);
+                                        }
+                                        None => log::info!(
+                                            "📨 Got response from {peer}: '{}'",
+                                            String::from_utf8_lossy(&response.content)
+                                        ),
+                                    }
+                                }
+                            }
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::RequestResponse(request_response::Event::OutboundFailure { peer, request_id, error, .. })) => {
+                            log::warn!("❌ Direct request to {peer} failed: {error}");
+                            if let Some(reply) = self.pending_requests.remove(&request_id) {
+                                let _ = reply.send(Err(anyhow::anyhow!("request to {peer} failed: {error}")));
+                            }
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::RequestResponse(request_response::Event::InboundFailure { peer, error, .. })) => {
+                            log::warn!("❌ Failed to serve the request of {peer}: {error}");
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::History(request_response::Event::Message { peer, message, .. })) => {
+                            match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    let page = self.handler.handle_history_request(peer, &request);
+                                    log::debug!("📚 Serving {} results of topic {} to {peer}", page.results.len(), page.topic);
+                                    if self.swarm.behaviour_mut().history.send_response(channel, page).is_err() {
+                                        log::warn!("❌ Failed to send history to {peer}");
+                                    }
+                                }
+                                request_response::Message::Response { response, .. } => {
+                                    if let Some(next) = self.handler.handle_history_page(peer, response) {
+                                        self.swarm.behaviour_mut().history.send_request(&peer, next);
+                                    }
+                                }
+                            }
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::History(request_response::Event::OutboundFailure { peer, error, .. })) => {
+                            log::warn!("❌ History sync with {peer} failed: {error}");
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::Summary(request_response::Event::Message { peer, message, .. })) => {
+                            match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    let response = self.handler.handle_summary_request(peer, &request);
+                                    if self.swarm.behaviour_mut().summary.send_response(channel, response).is_err() {
+                                        log::warn!("❌ Failed to send summary to {peer}");
+                                    }
+                                }
+                                request_response::Message::Response { response, .. } => {
+                                    if let Some(next) = self.handler.handle_summary_response(peer, response) {
+                                        self.swarm.behaviour_mut().summary.send_request(&peer, next);
+                                    }
+                                }
+                            }
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::Summary(request_response::Event::OutboundFailure { peer, error, .. })) => {
+                            log::warn!("❌ Summary walk with {peer} failed: {error}");
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::Direct(request_response::Event::Message { peer, message, .. })) => {
+                            match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    let Some(conversations) = &self.conversations else {
+                                        log::warn!("❌ Refusing direct message from {peer}, no conversation store");
+                                        continue;
+                                    };
+                                    match conversations.receive(&peer, request) {
+                                        Ok(ack) => {
+                                            log::info!("✉️ Direct message {} from {peer}", ack.id);
+                                            if self.swarm.behaviour_mut().direct.send_response(channel, ack).is_err() {
+                                                log::warn!("❌ Failed to acknowledge the direct message of {peer}");
+                                            }
+                                        }
+                                        Err(e) => log::warn!("⛔ Discarding direct message from {peer}: {e}"),
+                                    }
+                                }
+                                request_response::Message::Response { response, .. } => {
+                                    if let Some(conversations) = &self.conversations
+                                        && let Err(e) = conversations.mark_delivered(&peer, &response.id)
+                                    {
+                                        log::warn!("❌ Failed to mark direct message {} as delivered: {e}", response.id);
+                                    }
+                                }
+                            }
+                        }
+                        SwarmEvent::Behaviour(NodeBehaviourEvent::Direct(request_response::Event::OutboundFailure { peer, error, .. })) => {
+                            log::warn!("❌ Direct message to {peer} not delivered, sent again once connected: {error}");
                         }
                         SwarmEvent::Behaviour(NodeBehaviourEvent::Kademlia(event)) => {
                             log::debug!("🧠 Kademlia event: {event:?}");
@@ -282,3 +553,107 @@ impl<H: MessageHandler> NetworkClientNode<H> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::config::{BootstrapConfig, IdentityConfig};
+    use std::time::Duration;
+
+    /* echoes the requests when it serves them */
+    struct EchoHandler(bool);
+
+    impl MessageHandler for EchoHandler {
+        fn handle_message(&mut self, _: PeerId, _: &[u8], _: &str) -> Option<Vec<u8>> {
+            None
+        }
+
+        fn handle_request(&mut self, _peer: PeerId, data: &[u8]) -> Option<Vec<u8>> {
+            self.0.then(|| [b"echo: ", data].concat())
+        }
+    }
+
+    fn node_for_test(
+        keypair: identity::Keypair,
+        bootstrap: (PeerId, Multiaddr),
+        serves: bool,
+    ) -> NetworkClientNode<EchoHandler> {
+        let config = Config {
+            bootstrap: BootstrapConfig {
+                peer_id: bootstrap.0.to_string(),
+                address: bootstrap.1.to_string(),
+            },
+            identity: IdentityConfig::default(),
+            persistent_dht: false,
+        };
+        NetworkClientNode::new(keypair, &config, EchoHandler(serves), mpsc::channel(32)).unwrap()
+    }
+
+    async fn request_for_test(
+        requests: &mpsc::Sender<DirectRequest>,
+        peer: PeerId,
+        content: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        let (reply, response) = oneshot::channel();
+        requests
+            .send(DirectRequest {
+                peer,
+                content: content.to_vec(),
+                reply,
+            })
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), response)
+            .await
+            .expect("no answer to the request")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_are_answered_or_fail() {
+        // clients do not listen, the one answering listens on a free port and the other one dials it
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let address: Multiaddr = format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap();
+        let nowhere: Multiaddr = "/ip4/127.0.0.1/tcp/1".parse().unwrap();
+
+        let answering_keypair = identity::Keypair::generate_ed25519();
+        let answering_id = answering_keypair.public().to_peer_id();
+        let mut answering = node_for_test(answering_keypair, (PeerId::random(), nowhere), true);
+        answering.swarm.listen_on(address.clone()).unwrap();
+        let asking_keypair = identity::Keypair::generate_ed25519();
+        let asking_id = asking_keypair.public().to_peer_id();
+        let mut asking = node_for_test(asking_keypair, (answering_id, address), false);
+        let requests = asking.request_sender();
+        let answering_requests = answering.request_sender();
+        let nodes = [
+            tokio::spawn(async move { answering.run().await }),
+            tokio::spawn(async move { asking.run().await }),
+        ];
+
+        // a request fails while the dial of the bootstrap node is still in flight
+        let mut response = request_for_test(&requests, answering_id, b"ping").await;
+        for _ in 0..50 {
+            if response.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            response = request_for_test(&requests, answering_id, b"ping").await;
+        }
+        assert_eq!(response.unwrap(), b"echo: ping");
+
+        // the asking node does not serve requests, it says so instead of answering empty
+        let refused = request_for_test(&answering_requests, asking_id, b"ping").await;
+        assert!(refused.unwrap_err().to_string().contains("not served"));
+
+        // nobody can be dialed for an unknown peer, the failure reaches the caller
+        assert!(request_for_test(&requests, PeerId::random(), b"ping").await.is_err());
+
+        for node in nodes {
+            node.abort();
+        }
+    }
+}
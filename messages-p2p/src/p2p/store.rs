@@ -0,0 +1,483 @@
+use libp2p::kad::store::{Error, MemoryStore, RecordStore, Result};
+use libp2p::kad::{KBucketKey, ProviderRecord, Record, RecordKey, K_VALUE};
+use libp2p::{Multiaddr, PeerId};
+use protocol_p2p::Db;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::time::{Instant, SystemTime};
+
+const RECORDS_TREE: &str = "kad/records";
+const PROVIDERS_TREE: &str = "kad/providers";
+
+/// Limits of a `SledStore`, the same ones `MemoryStore` applies.
+#[derive(Debug, Clone)]
+pub struct SledStoreConfig {
+    pub max_records: usize,
+    pub max_value_bytes: usize,
+    pub max_providers_per_key: usize,
+    pub max_provided_keys: usize,
+}
+
+impl Default for SledStoreConfig {
+    fn default() -> Self {
+        Self {
+            max_records: 1024,
+            max_value_bytes: 65 * 1024,
+            max_providers_per_key: K_VALUE.get(),
+            max_provided_keys: 1024,
+        }
+    }
+}
+
+/* expiry is kept as wall clock time, an `Instant` does not survive a restart */
+#[derive(Serialize, Deserialize)]
+struct StoredRecord {
+    value: Vec<u8>,
+    publisher: Option<String>,
+    expires_at: Option<SystemTime>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredProvider {
+    provider: String,
+    expires_at: Option<SystemTime>,
+    addresses: Vec<String>,
+}
+
+fn to_system_time(instant: Instant) -> SystemTime {
+    SystemTime::now() + instant.saturating_duration_since(Instant::now())
+}
+
+/* None once the time has passed */
+fn to_instant(time: SystemTime) -> Option<Instant> {
+    time.duration_since(SystemTime::now())
+        .ok()
+        .map(|left| Instant::now() + left)
+}
+
+/* providers of a key are stored under the key length, the key and the provider */
+fn providers_prefix(key: &RecordKey) -> Vec<u8> {
+    let key = key.as_ref();
+    let mut prefix = (key.len() as u32).to_be_bytes().to_vec();
+    prefix.extend_from_slice(key);
+    prefix
+}
+
+fn provider_entry_key(key: &RecordKey, provider: &PeerId) -> Vec<u8> {
+    let mut entry_key = providers_prefix(key);
+    entry_key.extend_from_slice(&provider.to_bytes());
+    entry_key
+}
+
+fn record_key_of(entry_key: &[u8]) -> Option<RecordKey> {
+    let len = u32::from_be_bytes(entry_key.get(..4)?.try_into().ok()?) as usize;
+    Some(RecordKey::new(&entry_key.get(4..4 + len)?))
+}
+
+/// Kademlia record store kept in sled, so the DHT survives a restart.
+/// It lives in its own trees, so it can share the database of the protocol.
+pub struct SledStore {
+    local_id: PeerId,
+    config: SledStoreConfig,
+    records: sled::Tree,
+    providers: sled::Tree,
+}
+
+impl SledStore {
+    pub fn new(db: &Db, local_id: PeerId) -> anyhow::Result<Self> {
+        Self::with_config(db, local_id, SledStoreConfig::default())
+    }
+
+    pub fn with_config(db: &Db, local_id: PeerId, config: SledStoreConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            local_id,
+            config,
+            records: db.open_tree(RECORDS_TREE)?,
+            providers: db.open_tree(PROVIDERS_TREE)?,
+        })
+    }
+
+    fn decode_record(key: RecordKey, value: &[u8]) -> Option<Record> {
+        let stored: StoredRecord = serde_json::from_slice(value).ok()?;
+        let expires = match stored.expires_at {
+            Some(at) => Some(to_instant(at)?),
+            None => None,
+        };
+        Some(Record {
+            key,
+            value: stored.value,
+            publisher: stored.publisher.and_then(|peer| peer.parse().ok()),
+            expires,
+        })
+    }
+
+    fn decode_provider(key: RecordKey, value: &[u8]) -> Option<ProviderRecord> {
+        let stored: StoredProvider = serde_json::from_slice(value).ok()?;
+        let expires = match stored.expires_at {
+            Some(at) => Some(to_instant(at)?),
+            None => None,
+        };
+        Some(ProviderRecord {
+            key,
+            provider: stored.provider.parse().ok()?,
+            expires,
+            addresses: stored
+                .addresses
+                .iter()
+                .filter_map(|address| address.parse::<Multiaddr>().ok())
+                .collect(),
+        })
+    }
+
+    /* expired records are only dropped when read, they must not take the room of new ones */
+    fn purge_expired_records(&self) {
+        for (key, value) in self.records.iter().filter_map(|item| item.ok()) {
+            if Self::decode_record(RecordKey::new(&key), &value).is_none() {
+                let _ = self.records.remove(key);
+            }
+        }
+    }
+
+    fn provided_keys(&self) -> usize {
+        self.providers
+            .iter()
+            .values()
+            .filter_map(|value| serde_json::from_slice::<StoredProvider>(&value.ok()?).ok())
+            .filter(|stored| stored.provider == self.local_id.to_string())
+            .count()
+    }
+}
+
+impl RecordStore for SledStore {
+    type RecordsIter<'a> = std::vec::IntoIter<Cow<'a, Record>>;
+    type ProvidedIter<'a> = std::vec::IntoIter<Cow<'a, ProviderRecord>>;
+
+    fn get(&self, k: &RecordKey) -> Option<Cow<'_, Record>> {
+        let value = self.records.get(k.as_ref()).ok()??;
+        match Self::decode_record(k.clone(), &value) {
+            Some(record) => Some(Cow::Owned(record)),
+            None => {
+                /* expired or unreadable */
+                let _ = self.records.remove(k.as_ref());
+                None
+            }
+        }
+    }
+
+    fn put(&mut self, r: Record) -> Result<()> {
+        if r.value.len() >= self.config.max_value_bytes {
+            return Err(Error::ValueTooLarge);
+        }
+        let is_new = !self.records.contains_key(r.key.as_ref()).unwrap_or(false);
+        if is_new && self.records.len() >= self.config.max_records {
+            self.purge_expired_records();
+            if self.records.len() >= self.config.max_records {
+                return Err(Error::MaxRecords);
+            }
+        }
+        let stored = StoredRecord {
+            value: r.value,
+            publisher: r.publisher.map(|peer| peer.to_string()),
+            expires_at: r.expires.map(to_system_time),
+        };
+        let value = serde_json::to_vec(&stored).map_err(|_| Error::ValueTooLarge)?;
+        // the store errors have no variant for I/O, a failed write leaves it as full
+        if let Err(e) = self.records.insert(r.key.as_ref(), value) {
+            log::error!("Failed to store kademlia record: {e}");
+            return Err(Error::MaxRecords);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, k: &RecordKey) {
+        let _ = self.records.remove(k.as_ref());
+    }
+
+    fn records(&self) -> Self::RecordsIter<'_> {
+        self.records
+            .iter()
+            .filter_map(|item| {
+                let (key, value) = item.ok()?;
+                Self::decode_record(RecordKey::new(&key), &value)
+            })
+            .map(Cow::Owned)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn add_provider(&mut self, record: ProviderRecord) -> Result<()> {
+        let entry_key = provider_entry_key(&record.key, &record.provider);
+        let is_new = !self.providers.contains_key(&entry_key).unwrap_or(false);
+        if is_new {
+            if record.provider == self.local_id
+                && self.provided_keys() >= self.config.max_provided_keys
+            {
+                return Err(Error::MaxProvidedKeys);
+            }
+            /* a full list keeps the providers closest to the key, as MemoryStore does */
+            let providers = self.providers(&record.key);
+            if providers.len() >= self.config.max_providers_per_key {
+                let target = KBucketKey::new(record.key.clone());
+                let distance = |peer: PeerId| target.distance(&KBucketKey::from(peer));
+                let farthest = providers
+                    .iter()
+                    .map(|known| known.provider)
+                    .max_by_key(|provider| distance(*provider));
+                match farthest {
+                    Some(farthest) if distance(record.provider) < distance(farthest) => {
+                        let _ = self.providers.remove(provider_entry_key(&record.key, &farthest));
+                    }
+                    _ => return Ok(()),
+                }
+            }
+        }
+        let stored = StoredProvider {
+            provider: record.provider.to_string(),
+            expires_at: record.expires.map(to_system_time),
+            addresses: record.addresses.iter().map(|a| a.to_string()).collect(),
+        };
+        let value = serde_json::to_vec(&stored).map_err(|_| Error::ValueTooLarge)?;
+        if let Err(e) = self.providers.insert(entry_key, value) {
+            log::error!("Failed to store kademlia provider: {e}");
+            return Err(Error::MaxProvidedKeys);
+        }
+        Ok(())
+    }
+
+    fn providers(&self, key: &RecordKey) -> Vec<ProviderRecord> {
+        self.providers
+            .scan_prefix(providers_prefix(key))
+            .filter_map(|item| {
+                let (entry_key, value) = item.ok()?;
+                let provider = Self::decode_provider(key.clone(), &value);
+                if provider.is_none() {
+                    let _ = self.providers.remove(entry_key);
+                }
+                provider
+            })
+            .collect()
+    }
+
+    fn provided(&self) -> Self::ProvidedIter<'_> {
+        self.providers
+            .iter()
+            .filter_map(|item| {
+                let (entry_key, value) = item.ok()?;
+                Self::decode_provider(record_key_of(&entry_key)?, &value)
+            })
+            .filter(|record| record.provider == self.local_id)
+            .map(Cow::Owned)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn remove_provider(&mut self, k: &RecordKey, p: &PeerId) {
+        let _ = self.providers.remove(provider_entry_key(k, p));
+    }
+}
+
+/// Record store of a node, in memory or persisted in sled.
+pub enum KadStore {
+    Memory(MemoryStore),
+    Sled(SledStore),
+}
+
+impl KadStore {
+    pub fn memory(local_id: PeerId) -> Self {
+        KadStore::Memory(MemoryStore::new(local_id))
+    }
+
+    pub fn sled(db: &Db, local_id: PeerId) -> anyhow::Result<Self> {
+        Ok(KadStore::Sled(SledStore::new(db, local_id)?))
+    }
+}
+
+impl RecordStore for KadStore {
+    type RecordsIter<'a> = std::vec::IntoIter<Cow<'a, Record>>;
+    type ProvidedIter<'a> = std::vec::IntoIter<Cow<'a, ProviderRecord>>;
+
+    fn get(&self, k: &RecordKey) -> Option<Cow<'_, Record>> {
+        match self {
+            KadStore::Memory(store) => store.get(k),
+            KadStore::Sled(store) => store.get(k),
+        }
+    }
+
+    fn put(&mut self, r: Record) -> Result<()> {
+        match self {
+            KadStore::Memory(store) => store.put(r),
+            KadStore::Sled(store) => store.put(r),
+        }
+    }
+
+    fn remove(&mut self, k: &RecordKey) {
+        match self {
+            KadStore::Memory(store) => store.remove(k),
+            KadStore::Sled(store) => store.remove(k),
+        }
+    }
+
+    fn records(&self) -> Self::RecordsIter<'_> {
+        match self {
+            KadStore::Memory(store) => store.records().collect::<Vec<_>>().into_iter(),
+            KadStore::Sled(store) => store.records(),
+        }
+    }
+
+    fn add_provider(&mut self, record: ProviderRecord) -> Result<()> {
+        match self {
+            KadStore::Memory(store) => store.add_provider(record),
+            KadStore::Sled(store) => store.add_provider(record),
+        }
+    }
+
+    fn providers(&self, key: &RecordKey) -> Vec<ProviderRecord> {
+        match self {
+            KadStore::Memory(store) => store.providers(key),
+            KadStore::Sled(store) => store.providers(key),
+        }
+    }
+
+    fn provided(&self) -> Self::ProvidedIter<'_> {
+        match self {
+            KadStore::Memory(store) => store.provided().collect::<Vec<_>>().into_iter(),
+            KadStore::Sled(store) => store.provided(),
+        }
+    }
+
+    fn remove_provider(&mut self, k: &RecordKey, p: &PeerId) {
+        match self {
+            KadStore::Memory(store) => store.remove_provider(k, p),
+            KadStore::Sled(store) => store.remove_provider(k, p),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol_p2p::db::init_db;
+    use std::time::Duration;
+
+    #[test]
+    fn records_are_kept_in_the_protocol_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let local_id = PeerId::random();
+        let key = RecordKey::new(&"topic/topicA");
+        {
+            let db = init_db(path).unwrap();
+            let mut store = SledStore::new(&db, local_id).unwrap();
+            let mut record = Record::new(key.clone(), b"value".to_vec());
+            record.publisher = Some(local_id);
+            store.put(record).unwrap();
+            db.flush().unwrap();
+        }
+
+        let db = init_db(path).unwrap();
+        let mut store = SledStore::new(&db, local_id).unwrap();
+        let record = store.get(&key).unwrap();
+        assert_eq!(record.value, b"value".to_vec());
+        assert_eq!(record.publisher, Some(local_id));
+        assert_eq!(store.records().count(), 1);
+
+        store.remove(&key);
+        assert!(store.get(&key).is_none());
+    }
+
+    #[test]
+    fn expired_records_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path().to_str().unwrap()).unwrap();
+        let mut store = SledStore::new(&db, PeerId::random()).unwrap();
+        let key = RecordKey::new(&"expired");
+        let mut record = Record::new(key.clone(), b"value".to_vec());
+        record.expires = Some(Instant::now() + Duration::from_millis(20));
+        store.put(record).unwrap();
+        assert!(store.get(&key).is_some());
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(store.get(&key).is_none());
+        assert_eq!(store.records().count(), 0);
+    }
+
+    #[test]
+    fn expired_records_do_not_count_toward_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path().to_str().unwrap()).unwrap();
+        let config = SledStoreConfig {
+            max_records: 1,
+            ..Default::default()
+        };
+        let mut store = SledStore::with_config(&db, PeerId::random(), config).unwrap();
+        let mut record = Record::new(RecordKey::new(&"expired"), b"value".to_vec());
+        record.expires = Some(Instant::now() + Duration::from_millis(20));
+        store.put(record).unwrap();
+        assert!(store.put(Record::new(RecordKey::new(&"full"), b"value".to_vec())).is_err());
+
+        std::thread::sleep(Duration::from_millis(40));
+        store
+            .put(Record::new(RecordKey::new(&"fresh"), b"value".to_vec()))
+            .unwrap();
+        assert_eq!(store.records().count(), 1);
+    }
+
+    #[test]
+    fn full_provider_list_keeps_the_closest() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path().to_str().unwrap()).unwrap();
+        let config = SledStoreConfig {
+            max_providers_per_key: 2,
+            ..Default::default()
+        };
+        let mut store = SledStore::with_config(&db, PeerId::random(), config).unwrap();
+        let key = RecordKey::new(&"topic/topicA");
+        let target = KBucketKey::new(key.clone());
+        let mut peers: Vec<PeerId> = (0..3).map(|_| PeerId::random()).collect();
+        peers.sort_by_key(|peer| target.distance(&KBucketKey::from(*peer)));
+
+        for peer in peers.iter().rev() {
+            store
+                .add_provider(ProviderRecord::new(key.clone(), *peer, vec![]))
+                .unwrap();
+        }
+
+        let mut kept: Vec<PeerId> = store.providers(&key).iter().map(|p| p.provider).collect();
+        kept.sort_by_key(|peer| target.distance(&KBucketKey::from(*peer)));
+        assert_eq!(kept, peers[..2].to_vec());
+    }
+
+    #[test]
+    fn providers_are_listed_per_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path().to_str().unwrap()).unwrap();
+        let local_id = PeerId::random();
+        let other = PeerId::random();
+        let mut store = SledStore::new(&db, local_id).unwrap();
+        let key = RecordKey::new(&"topic/topicA");
+        let longer_key = RecordKey::new(&"topic/topicAB");
+
+        store
+            .add_provider(ProviderRecord::new(key.clone(), local_id, vec![]))
+            .unwrap();
+        let address: Multiaddr = "/ip4/127.0.0.1/tcp/15000".parse().unwrap();
+        store
+            .add_provider(ProviderRecord::new(key.clone(), other, vec![address.clone()]))
+            .unwrap();
+        store
+            .add_provider(ProviderRecord::new(longer_key.clone(), other, vec![]))
+            .unwrap();
+
+        let providers = store.providers(&key);
+        assert_eq!(providers.len(), 2);
+        let remote = providers.iter().find(|p| p.provider == other).unwrap();
+        assert_eq!(remote.addresses, vec![address]);
+        let provided: Vec<_> = store.provided().collect();
+        assert_eq!(provided.len(), 1);
+        assert_eq!(provided[0].key, key);
+
+        store.remove_provider(&key, &other);
+        assert_eq!(store.providers(&key).len(), 1);
+        assert_eq!(store.providers(&longer_key).len(), 1);
+    }
+}
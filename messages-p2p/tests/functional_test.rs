@@ -54,10 +54,10 @@ async fn simple_node_communication() {
     println!("Relay server started, waiting for 5 seconds to stabilize...");
     tokio::time::sleep(Duration::from_secs(5)).await;
 
-    let node = p2p::node::run_node().unwrap();
+    let node = p2p::node::run_node_with_keypair(identity::Keypair::generate_ed25519()).unwrap();
     let tx1 = node.lock().await.command_sender();
 
-    let node2 = p2p::node::run_node().unwrap();
+    let node2 = p2p::node::run_node_with_keypair(identity::Keypair::generate_ed25519()).unwrap();
     let tx2 = node2.lock().await.command_sender();
 
     tokio::time::sleep(Duration::from_secs(2)).await;
@@ -51,10 +51,7 @@ async fn validation_among_clients() {
 
     // Todos se registran al topic
     for client in &clients {
-        let topic = Topic {
-            name: topic_to_register.to_string(),
-            description: "example for description".to_string(),
-        };
+        let topic = Topic::new(topic_to_register, "example for description");
         client.register_topic(&topic).await.unwrap();
         sleep(Duration::from_secs(1)).await
     }
@@ -199,10 +196,7 @@ async fn validation_among_clients_with_local_server() {
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 
-    let topic = Topic {
-        name: topic_to_register.clone(),
-        description: "mocked description".to_string(),
-    };
+    let topic = Topic::new(&topic_to_register, "mocked description");
     client_asker.remote_new_topic(&topic).await.unwrap();
 
     for client in clients.clone() {
@@ -318,10 +312,7 @@ async fn validation_among_clients_with_remote_server() {
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 
-    let topic = Topic {
-        name: topic_to_register.clone(),
-        description: "mocked description".to_string(),
-    };
+    let topic = Topic::new(&topic_to_register, "mocked description");
     client_asker.remote_new_topic(&topic).await.unwrap();
 
     for client in clients.clone() {
@@ -484,6 +475,9 @@ async fn api_operations_workflow() {
         "runtime pending content to validate: {:?}",
         runtime_content_to_validate
     );
-    assert_eq!(get_my_topics.len(), 1);
+    /* the registry keeps every topic, the one created and the one joined */
+    let mut my_topics: Vec<&str> = get_my_topics.iter().map(|topic| topic.name.as_str()).collect();
+    my_topics.sort();
+    assert_eq!(my_topics, ["topic2", "topic5"]);
     assert_eq!(runtime_content_to_validate.len(), 1);
 }
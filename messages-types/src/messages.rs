@@ -1,7 +1,16 @@
 #[derive(Debug, Clone)]
 pub enum ChatCommand {
     Subscribe(String),
+    Unsubscribe(String),
+    /* look up in the DHT the peers providing a topic */
+    FindProviders(String),
     Publish(String, Vec<u8>),
     SendOne(String, Vec<u8>),
+    /* asks a peer for a page of the history of a topic, the request is serialized */
+    SyncHistory(String, Vec<u8>),
+    /* asks a peer for a step of the walk of a topic summary, the request is serialized */
+    SyncSummary(String, Vec<u8>),
+    /* delivers an encrypted direct message to a peer, the envelope is serialized */
+    SendDirect(String, Vec<u8>),
     Quit,
 }
@@ -0,0 +1,146 @@
+use crate::handler::{history_page, import_result, summary_answer};
+use crate::models::db::{DataContent, Topic};
+use crate::models::messages::{
+    ContentMessage, HistoryPage, HistoryRequest, SignedMessage, SummaryRequest, SummaryResponse,
+};
+use crate::{db, MessageHandler};
+use libp2p::PeerId;
+use sled::Db;
+use std::sync::Arc;
+
+/// Observer run by an archive node. It takes no part in votations, it keeps the
+/// topics announced and every result whose ballots recount, so peers joining a
+/// topic late have a durable source of its approved history. Only results of
+/// requests it saw on the topic, or of juries it knows, are kept.
+#[derive(Debug, Clone)]
+pub struct ArchiveHandler {
+    db: Arc<Db>,
+}
+
+impl ArchiveHandler {
+    pub fn new(db: Arc<Db>) -> Self {
+        ArchiveHandler { db }
+    }
+
+    pub async fn topics(&self) -> Vec<Topic> {
+        db::get_topics(&self.db).await
+    }
+
+    /// Decided content of a topic, as archived.
+    pub fn contents(&self, topic: &str) -> Vec<DataContent> {
+        db::get_topic_contents(&self.db, topic)
+    }
+
+    /// Results of a topic signed by their leaders, anyone can recount them.
+    pub fn results(&self, topic: &str) -> Vec<SignedMessage> {
+        db::get_history(&self.db, topic, None, usize::MAX)
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect()
+    }
+
+}
+
+impl MessageHandler for ArchiveHandler {
+    fn handle_message(&mut self, source_peer: PeerId, data: &[u8], topic: &str) -> Option<Vec<u8>> {
+        let envelope = serde_json::from_slice::<SignedMessage>(data).ok()?;
+        if let Err(e) = envelope.verify() {
+            log::warn!("⛔ Archive discarding message from {}: {}", source_peer, e);
+            return None;
+        }
+        match &envelope.payload {
+            ContentMessage::RegisterTopic {
+                topic,
+                description,
+                creator,
+                created_at,
+                policy,
+            } => {
+                if !creator.is_empty() && *creator != envelope.signer {
+                    return None;
+                }
+                let announced = Topic {
+                    creator: envelope.signer.clone(),
+                    created_at: *created_at,
+                    subscribed: false,
+                    policy: policy.clone(),
+                    ..Topic::new(topic, description)
+                };
+                if db::announce_topic(&self.db, &announced).ok()? {
+                    log::info!("🗄 Archived topic {:?}", topic);
+                }
+            }
+            /* the jury of a result is trusted once its request was seen live */
+            request @ ContentMessage::VoteLeaderRequest {
+                id_votation,
+                publisher_peer_id,
+                ..
+            } => {
+                if request.verify_vote_leader_request().is_err()
+                    || *publisher_peer_id != envelope.signer
+                    || db::get_vote_request(&self.db, id_votation).is_some()
+                {
+                    return None;
+                }
+                db::save_vote_request(&self.db, id_votation, request).ok()?;
+            }
+            ContentMessage::IncludeNewValidatedContent { id_votation, .. } => {
+                match import_result(&self.db, topic, &envelope) {
+                    Ok(true) => log::info!("🗄 Archived result of votation={}", id_votation),
+                    Ok(false) => log::debug!("Result of votation={} already archived", id_votation),
+                    Err(e) => log::warn!(
+                        "⛔ Archive discarding result of votation={} from {}: {}",
+                        id_votation,
+                        envelope.signer,
+                        e
+                    ),
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_history_request(&mut self, _peer: PeerId, request: &HistoryRequest) -> HistoryPage {
+        history_page(&self.db, request)
+    }
+
+    fn handle_summary_request(&mut self, _peer: PeerId, request: &SummaryRequest) -> SummaryResponse {
+        summary_answer(&self.db, request)
+    }
+}
+
+#[test]
+fn test_archive_keeps_recounted_results() {
+    let (_tmp_dirs, _juror, leader, result, publisher) = crate::handler::leader_result_for_test();
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = Arc::new(db::init_db(tmp_dir.path().to_str().unwrap()).unwrap());
+    let mut archive = ArchiveHandler::new(db);
+
+    // the jury is unknown until its request is seen on the topic
+    archive.handle_message(leader.public().to_peer_id(), &result, "topicA");
+    assert!(archive.results("topicA").is_empty());
+    let envelope: SignedMessage = serde_json::from_slice(&result).unwrap();
+    let ContentMessage::IncludeNewValidatedContent {
+        request: Some(request),
+        ..
+    } = &envelope.payload
+    else {
+        panic!("result without its request");
+    };
+    let request = SignedMessage::sign(*request.clone(), &publisher).unwrap().to_bytes().unwrap();
+    archive.handle_message(publisher.public().to_peer_id(), &request, "topicA");
+
+    // a result the leader did not sign is not archived
+    let outsider = libp2p::identity::Keypair::generate_ed25519();
+    let forged = SignedMessage::sign(envelope.payload, &outsider).unwrap().to_bytes().unwrap();
+    archive.handle_message(outsider.public().to_peer_id(), &forged, "topicA");
+    assert!(archive.results("topicA").is_empty());
+
+    archive.handle_message(leader.public().to_peer_id(), &result, "topicA");
+    assert_eq!(archive.results("topicA").len(), 1);
+    let contents = archive.contents("topicA");
+    assert_eq!(contents.len(), 1);
+    assert_eq!(contents[0].id_votation, "vote_status/topicA:1:pending:1");
+    assert!(archive.contents("topicB").is_empty());
+}
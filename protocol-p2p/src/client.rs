@@ -2,22 +2,30 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::anyhow;
+use chrono::Utc;
 use libp2p::identity::Keypair;
 use libp2p::PeerId;
 use serde::{Deserialize, Serialize};
 use sled::Db;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::Mutex;
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
 
 use messages_types::ChatCommand;
 
 use crate::models::db::Votation;
-use crate::models::db::{DataContent, VoteStatus};
-use crate::models::messages::{ContentMessage, Vote, DEFAULT_TOPIC};
+use crate::models::db::{
+    ContentPayload, DataContent, PrivateTopic, StateContent, Topic, TopicPolicy, VoteStatus,
+};
+use crate::models::messages::{
+    ballot_commitment, new_salt, Ballot, ContentMessage, HistoryRequest, SignedMessage,
+    SummaryRequest, DEFAULT_TOPIC,
+};
+use crate::private::{new_topic_key, seal_for_topic, topic_key_message};
 use crate::protocol::MessageHandler;
+use crate::summary::topic_summary;
 use crate::{
-    db, models, DEFAULT_REPUTATION, MEMBERS_FOR_CONSENSUS, MIN_REPUTATION_THRESHOLD, TIMEOUT_SECS,
+    db, models, DEFAULT_REPUTATION, HISTORY_PAGE_SIZE, PROVIDERS_WAIT_SECS, SUMMARY_SECS,
 };
 
 pub struct ValidatorClient {
@@ -26,7 +34,7 @@ pub struct ValidatorClient {
     pub inner_handler: Arc<Mutex<dyn MessageHandler + Send + Sync>>,
     db: Arc<Db>,
     keypair: Keypair,
-    pub content_to_evaluate: Mutex<Vec<(String, String, String, Duration)>>,
+    pub content_to_evaluate: Mutex<Vec<(String, String, ContentPayload, Duration)>>,
 }
 
 impl ValidatorClient {
@@ -35,7 +43,7 @@ impl ValidatorClient {
             peer_id,
             command_tx: tx,
             inner_handler: Arc::new(Mutex::new(crate::handler::ValidatorHandler::new(
-                peer_id.clone(),
+                keypair.clone(),
                 db.clone(),
             ))),
             db,
@@ -44,12 +52,15 @@ impl ValidatorClient {
         }
     }
     pub fn new_key_available(&self, topic: &str, content: &str) -> anyhow::Result<String> {
-        let key_for_checking = db::create_key_without_status(topic, content);
+        let key_for_checking = format!(
+            "pending_content/{}:",
+            db::create_key_without_status(content, topic)
+        );
         /*  check if content was added before */
         //TODO return reason
         let is_added = self
             .db
-            .scan_prefix(key_for_checking.clone())
+            .scan_prefix(key_for_checking)
             .next()
             .is_some();
         if is_added {
@@ -64,25 +75,83 @@ impl ValidatorClient {
         &self,
         key: &str,
         topic: &str,
-        content: &str,
+        content: impl Into<ContentPayload>,
     ) -> anyhow::Result<()> {
-        self.add_validation_request(key.to_string(), topic.to_string(), content.to_string())
+        let mut content = content.into();
+        // appeals and removals keep the metadata of the original submission
+        if content.publisher.is_empty() {
+            content.publisher = self.peer_id.to_string();
+            content.topic = topic.to_string();
+            content.submitted_at = Some(Utc::now());
+        }
+        self.add_validation_request(key.to_string(), topic.to_string(), content.clone())
             .await;
         // send petition
         let message = ContentMessage::Interested {
             id_votation: key.to_string(),
-            content: content.to_string(),
+            content,
         };
         self.send(topic.to_string(), &message).await?;
         Ok(())
     }
-    async fn add_validation_request(&self, key: String, topic: String, content: String) {
-        self.content_to_evaluate.lock().await.push((
-            key,
-            topic,
-            content,
-            Duration::from_secs(TIMEOUT_SECS),
-        ));
+    /// Contests the last decision taken on `content`, a larger jury votes it again.
+    /// Returns the id of the appeal votation.
+    pub async fn appeal(&self, topic: &str, content: &str) -> anyhow::Result<String> {
+        let previous = db::get_latest_decided_votation(&self.db, topic, content)
+            .ok_or(anyhow!("No decided votation to appeal for content: {}", content))?;
+        let result = db::get_result(&self.db, &previous.id_votation).ok_or(anyhow!(
+            "The result of votation={} is unknown",
+            previous.id_votation
+        ))?;
+        let key = db::appeal_key(&previous.id_votation);
+        let policy = db::get_topic_policy(&self.db, topic);
+        if db::votation_level(&key) > policy.max_appeals + 1 {
+            return Err(anyhow!("No more appeals allowed for content: {}", content));
+        }
+        db::save_pending_appeal(&self.db, &key, &result)?;
+        self.ask_validation(&key, topic, previous.content).await?;
+        Ok(key)
+    }
+
+    /// Withdraws my content from the network, only its publisher can do it.
+    pub async fn retract(&self, topic: &str, id_votation: &str) -> anyhow::Result<()> {
+        let request = db::get_vote_request(&self.db, &db::first_level_key(id_votation))
+            .ok_or(anyhow!("Votation={} was not published by me", id_votation))?;
+        let message = ContentMessage::RetractContent {
+            id_votation: id_votation.to_string(),
+            request: Box::new(request),
+        };
+        crate::handler::verify_retraction(&self.peer_id.to_string(), &message)?;
+        let data = self.sign(message)?;
+        self.inner_handler
+            .lock()
+            .await
+            .handle_message(self.peer_id, &data, topic);
+        self.publish(topic.to_string(), data).await
+    }
+
+    /// Asks the topic to vote the removal of approved content.
+    /// Returns the id of the removal votation.
+    pub async fn flag(&self, topic: &str, id_votation: &str) -> anyhow::Result<String> {
+        let content = db::get_contents(&self.db)
+            .into_iter()
+            .find(|content| content.id_votation == id_votation)
+            .filter(|content| content.approved == StateContent::Approved)
+            .ok_or(anyhow!("No approved content for votation={}", id_votation))?;
+        let key = db::removal_key(id_votation);
+        if db::get_status_vote(&self.db, &key).is_some() {
+            return Err(anyhow!("Content of votation={} is already flagged", id_votation));
+        }
+        self.ask_validation(&key, topic, content.content).await?;
+        Ok(key)
+    }
+
+    async fn add_validation_request(&self, key: String, topic: String, content: ContentPayload) {
+        let timeout = Duration::from_secs(db::get_topic_policy(&self.db, &topic).timeout_secs);
+        self.content_to_evaluate
+            .lock()
+            .await
+            .push((key, topic, content, timeout));
     }
 
     pub fn peer_id(&self) -> PeerId {
@@ -93,12 +162,18 @@ impl ValidatorClient {
         self.db.clone()
     }
 
-    pub async fn remote_new_topic(&self, topic: &str) -> anyhow::Result<()> {
+    /// Announces a new topic to the network, with its description and rules.
+    pub async fn remote_new_topic(&self, topic: &Topic) -> anyhow::Result<()> {
+        db::save_topic_policy(&self.db, &topic.name, &topic.policy)?;
         self.command_tx
             .send(ChatCommand::Publish(
                 DEFAULT_TOPIC.to_string(),
-                serde_json::to_vec(&ContentMessage::RegisterTopic {
-                    topic: topic.to_string(),
+                self.sign(ContentMessage::RegisterTopic {
+                    topic: topic.name.clone(),
+                    description: topic.description.clone(),
+                    creator: self.peer_id.to_string(),
+                    created_at: topic.created_at.or(Some(Utc::now())),
+                    policy: topic.policy.clone(),
                 })?,
             ))
             .await
@@ -106,6 +181,82 @@ impl ValidatorClient {
         Ok(())
     }
 
+    /// Creates a topic only `members` can read. It is not announced, each member
+    /// gets the topic key sealed to its identity and is invited to join.
+    pub async fn create_private_topic(&self, topic: &Topic, members: &[String]) -> anyhow::Result<()> {
+        let me = self.peer_id.to_string();
+        if db::get_private_topic(&self.db, &topic.name).is_some() {
+            return Err(anyhow!("Private topic {} already exists", topic.name));
+        }
+        let mut members = members.to_vec();
+        members.push(me.clone());
+        members.sort();
+        members.dedup();
+        let owned = Topic {
+            creator: me.clone(),
+            subscribed: true,
+            ..topic.clone()
+        };
+        let private = PrivateTopic {
+            owner: me,
+            members,
+            epoch: 1,
+        };
+        db::save_topic_policy(&self.db, &topic.name, &topic.policy)?;
+        db::save_topic(&self.db, &owned).await?;
+        self.distribute_topic_key(&topic.name, &private, &new_topic_key())
+            .await?;
+        self.register_topic(&topic.name).await
+    }
+
+    /// Adds a member to a private topic I own, it gets the current key.
+    pub async fn invite_to_topic(&self, topic: &str, member: &str) -> anyhow::Result<()> {
+        let mut private = self.owned_private_topic(topic)?;
+        if private.is_member(member) {
+            return Ok(());
+        }
+        private.members.push(member.to_string());
+        let key = db::get_topic_key(&self.db, topic, private.epoch)
+            .ok_or_else(|| anyhow!("No key for private topic {topic}"))?;
+        self.distribute_topic_key(topic, &private, &key).await
+    }
+
+    /// Removes a member of a private topic I own. The key is rotated, so the
+    /// content published from now on can not be read by the removed member.
+    pub async fn remove_from_topic(&self, topic: &str, member: &str) -> anyhow::Result<()> {
+        let mut private = self.owned_private_topic(topic)?;
+        if member == private.owner || !private.is_member(member) {
+            return Err(anyhow!("{member} can not be removed from topic {topic}"));
+        }
+        private.members.retain(|known| known != member);
+        private.epoch += 1;
+        self.distribute_topic_key(topic, &private, &new_topic_key())
+            .await
+    }
+
+    fn owned_private_topic(&self, topic: &str) -> anyhow::Result<PrivateTopic> {
+        db::get_private_topic(&self.db, topic)
+            .filter(|private| private.owner == self.peer_id.to_string())
+            .ok_or_else(|| anyhow!("Topic {topic} is not a private topic of mine"))
+    }
+
+    /* members are not subscribed to the topic yet, the key goes on the default one */
+    async fn distribute_topic_key(
+        &self,
+        topic: &str,
+        private: &PrivateTopic,
+        key: &[u8; 32],
+    ) -> anyhow::Result<()> {
+        let known = db::get_topic(&self.db, topic).unwrap_or_else(|| Topic::new(topic, ""));
+        let message = topic_key_message(&self.keypair, &known, private, key)?;
+        db::save_topic_key(&self.db, topic, private.epoch, key)?;
+        db::save_private_topic(&self.db, topic, private)?;
+        self.command_tx
+            .send(ChatCommand::Publish(DEFAULT_TOPIC.to_string(), self.sign(message)?))
+            .await?;
+        Ok(())
+    }
+
     pub async fn register_topic(&self, topic: &str) -> anyhow::Result<()> {
         self.command_tx
             .send(ChatCommand::Subscribe(topic.to_string()))
@@ -113,15 +264,113 @@ impl ValidatorClient {
         Ok(())
     }
 
-    pub async fn add_vote(&self, id_votation: &str, topic: &str, vote: Vote) -> anyhow::Result<()> {
-        let Some(votation) = db::get_status_vote(&self.db, id_votation) else {
+    /// Peers providing a topic in the DHT, waiting a moment for the lookup to answer.
+    pub async fn find_topic_peers(&self, topic: &str) -> anyhow::Result<Vec<String>> {
+        let started = Utc::now();
+        self.command_tx
+            .send(ChatCommand::FindProviders(topic.to_string()))
+            .await?;
+        let check_interval = Duration::from_millis(250);
+        let mut waited = Duration::ZERO;
+        while waited < Duration::from_secs(PROVIDERS_WAIT_SECS)
+            && db::get_providers_lookup(&self.db, topic).is_none_or(|answered| answered < started)
+        {
+            sleep(check_interval).await;
+            waited += check_interval;
+        }
+        Ok(db::get_topic_providers(&self.db, topic))
+    }
+
+    /// Asks the peers providing a topic for the results I missed, resuming from
+    /// where the history of each peer was read. Returns the peers asked.
+    pub async fn sync_history(&self, topic: &str) -> anyhow::Result<Vec<String>> {
+        let peers = self.find_topic_peers(topic).await?;
+        for peer in &peers {
+            let request = HistoryRequest {
+                topic: topic.to_string(),
+                cursor: db::get_history_cursor(&self.db, topic, peer),
+                limit: HISTORY_PAGE_SIZE,
+            };
+            self.command_tx
+                .send(ChatCommand::SyncHistory(
+                    peer.clone(),
+                    serde_json::to_vec(&request)?,
+                ))
+                .await?;
+        }
+        Ok(peers)
+    }
+
+    pub async fn unregister_topic(&self, topic: &str) -> anyhow::Result<()> {
+        self.command_tx
+            .send(ChatCommand::Unsubscribe(topic.to_string()))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn add_vote(
+        &self,
+        id_votation: &str,
+        topic: &str,
+        ballot: impl Into<Ballot>,
+    ) -> anyhow::Result<()> {
+        let ballot = ballot.into();
+        let Some(mut votation) = db::get_status_vote(&self.db, id_votation) else {
             log::debug!("You are not included in this votation={}", id_votation);
             return Ok(());
         };
 
-        let data = serde_json::to_vec(&ContentMessage::ResultVote {
+        // keep the ballot, a new leader needs it again if this one is silent
+        votation.my_ballot = Some(ballot.clone());
+
+        if let Some(deadline) = votation.commit_deadline {
+            if Utc::now() > deadline {
+                return Err(anyhow!(
+                    "Commit phase of votation={} is over, the vote is not counted",
+                    id_votation
+                ));
+            }
+            // only the hash goes out now, the ballot is revealed after the deadline
+            let salt = new_salt();
+            let commitment =
+                ballot_commitment(id_votation, &self.peer_id.to_string(), &ballot, &salt)?;
+            votation.my_salt = Some(salt);
+            db::save_status_vote(&self.db, &votation)?;
+
+            let data = self.sign(ContentMessage::VoteCommit {
+                id_votation: id_votation.to_string(),
+                commitment,
+            })?;
+            // any juror can lead a later round, I keep my own commitment as well
+            self.inner_handler
+                .lock()
+                .await
+                .handle_message(self.peer_id, &data, topic);
+            self.publish(topic.to_string(), data).await?;
+            return Ok(());
+        }
+
+        self.reveal(votation, topic).await
+    }
+
+    /* sends my ballot to the leader of the current round */
+    async fn reveal(&self, mut votation: Votation, topic: &str) -> anyhow::Result<()> {
+        let Some(ballot) = votation.my_ballot.clone() else {
+            return Err(anyhow!("No ballot for votation={}", votation.id_votation));
+        };
+        votation.revealed = true;
+        if votation.leader_id != self.peer_id.to_string() {
+            votation.round_deadline =
+                Some(Utc::now() + chrono::Duration::seconds(votation.ttl_secs as i64));
+        }
+        db::save_status_vote(&self.db, &votation)?;
+        let id_votation = votation.id_votation.as_str();
+
+        let data = self.sign(ContentMessage::ResultVote {
             id_votation: id_votation.to_string(),
-            result: vote,
+            result: ballot,
+            round: votation.round,
+            salt: votation.my_salt.clone(),
         })?;
 
         if (votation.leader_id == self.peer_id.to_string()) {
@@ -130,28 +379,251 @@ impl ValidatorClient {
                 votation.leader_id.clone(),
                 id_votation
             );
-            self.inner_handler
+            let finalization = self
+                .inner_handler
                 .lock()
                 .await
                 .handle_message(self.peer_id, &data, topic);
+            // the leader vote can close the votation, the others need the result
+            if let Some(finalization) = finalization {
+                self.publish(topic.to_string(), finalization).await?;
+            }
             return Ok(());
         }
 
         log::debug!("Sending a remote publish message");
-        self.command_tx
-            .send(ChatCommand::Publish(topic.to_string(), data))
+        self.publish(topic.to_string(), data)
             .await
             .map_err(|e| anyhow!("Failed to send vote: {}", e))?;
         Ok(())
     }
 
     pub async fn send(&self, topic: String, message: &ContentMessage) -> anyhow::Result<()> {
+        let data = self.sign(message.clone())?;
+        self.publish(topic, data).await
+    }
+
+    /* publishes bytes that are already signed, e.g. responses from the handler,
+    sealed with the topic key when the topic is private */
+    pub async fn publish(&self, topic: String, data: Vec<u8>) -> anyhow::Result<()> {
+        let data = seal_for_topic(&self.db, &self.keypair, &topic, data)?;
         self.command_tx
-            .send(ChatCommand::Publish(topic, serde_json::to_vec(message)?))
+            .send(ChatCommand::Publish(topic, data))
             .await?;
         Ok(())
     }
 
+    /* draws the jury of my content and asks it to vote */
+    async fn start_votation(
+        &self,
+        key: &str,
+        topic: &str,
+        content: &ContentPayload,
+        candidates: Vec<(String, f32)>,
+        policy: &TopicPolicy,
+    ) -> anyhow::Result<()> {
+        // only candidates whose signed answer I hold can be checked by the jury
+        let mut interests = db::get_interests(&self.db, key);
+        let candidates: Vec<(String, f32)> = candidates
+            .into_iter()
+            .filter(|(peer_id, _)| interests.iter().any(|interest| interest.signer == *peer_id))
+            .collect();
+        interests.retain(|interest| candidates.iter().any(|(peer_id, _)| *peer_id == interest.signer));
+        let mut vote_request = ContentMessage::new_vote_leader_request(
+            key.to_string(),
+            content.clone(),
+            self.peer_id.to_string(),
+            candidates,
+            interests,
+            policy,
+            60,
+            &self.keypair,
+        )?;
+        if let Some(previous) = db::get_pending_appeal(&self.db, key) {
+            vote_request = vote_request.with_appeal(previous, &self.keypair)?;
+        }
+        log::debug!("Selected jury for voting: {:?}", vote_request);
+        let envelope = SignedMessage::sign(vote_request, &self.keypair)?;
+        self.publish(topic.to_string(), envelope.to_bytes()?).await?;
+        self.track_published_votation(&envelope, topic)
+    }
+
+    /* the publisher follows its votation to learn the outcome */
+    fn track_published_votation(
+        &self,
+        envelope: &SignedMessage,
+        topic: &str,
+    ) -> anyhow::Result<()> {
+        let vote_request = &envelope.payload;
+        let ContentMessage::VoteLeaderRequest {
+            id_votation,
+            content,
+            voters_peer_id,
+            leader_peer_id,
+            ttl_secs,
+            tally_mode,
+            weights,
+            commit_deadline,
+            ..
+        } = vote_request
+        else {
+            return Err(anyhow!("Not a VoteLeaderRequest"));
+        };
+        let mut votation = Votation::new(
+            id_votation.clone(),
+            content.clone(),
+            "pending".to_string(),
+            leader_peer_id.clone(),
+            "role_publisher".to_string(),
+            voters_peer_id.iter().map(|id| (id.clone(), None)).collect(),
+        );
+        votation.topic = topic.to_string();
+        votation.requested_at = Some(envelope.timestamp);
+        votation.ttl_secs = *ttl_secs;
+        votation.weights = weights.clone();
+        votation.tally_mode = tally_mode.clone();
+        votation.commit_deadline = *commit_deadline;
+        db::save_vote_request(&self.db, id_votation, vote_request)?;
+        db::save_status_vote(&self.db, &votation)
+    }
+
+    fn sign(&self, message: ContentMessage) -> anyhow::Result<Vec<u8>> {
+        SignedMessage::sign(message, &self.keypair)?.to_bytes()
+    }
+
+    /* jurors move to a new round when the leader does not close the current one in time */
+    pub async fn check_rounds(&self) -> anyhow::Result<()> {
+        let my_peer_id = self.peer_id.to_string();
+        let now = Utc::now();
+        for votation in db::get_status_voteses(&self.db) {
+            let timed_out = votation
+                .round_deadline
+                .is_some_and(|deadline| now > deadline);
+            if !votation.is_pending() || !timed_out || votation.leader_id == my_peer_id {
+                continue;
+            }
+            let next_round = votation.round + 1;
+            let (next, resend) =
+                crate::handler::enter_round(&self.db, &self.keypair, &votation, next_round)?;
+            if let Some(message) = resend {
+                log::debug!(
+                    "Sending my vote to the leader={} of round={}",
+                    next.leader_id,
+                    next_round
+                );
+                self.send(next.topic.clone(), &message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /* every joined topic with approved content gossips the summary of it */
+    pub async fn publish_summaries(&self) -> anyhow::Result<()> {
+        for topic in db::get_topics(&self.db).await {
+            if !topic.subscribed {
+                continue;
+            }
+            let summary = topic_summary(&self.db, &topic.name);
+            if summary.count == 0 {
+                continue;
+            }
+            let digest = self.sign(ContentMessage::TopicDigest {
+                topic: topic.name.clone(),
+                summary,
+            })?;
+            self.publish(topic.name, digest).await?;
+        }
+        Ok(())
+    }
+
+    /* walks the buckets that differ with the peers that gossiped them */
+    pub async fn repair_summaries(&self) -> anyhow::Result<()> {
+        for (topic, peer_id, buckets) in db::take_summary_gaps(&self.db) {
+            let request = SummaryRequest::Buckets { topic, buckets };
+            self.command_tx
+                .send(ChatCommand::SyncSummary(peer_id, serde_json::to_vec(&request)?))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /* once the commit phase is over jurors reveal, and the leader closes with the reveals it got in time */
+    pub async fn check_reveals(&self) -> anyhow::Result<()> {
+        let my_peer_id = self.peer_id.to_string();
+        let now = Utc::now();
+        for votation in db::get_status_voteses(&self.db) {
+            let Some(commit_deadline) = votation.commit_deadline else {
+                continue;
+            };
+            if !votation.is_pending() || now <= commit_deadline {
+                continue;
+            }
+            if votation.my_salt.is_some() && !votation.revealed {
+                let topic = votation.topic.clone();
+                self.reveal(votation, &topic).await?;
+                continue;
+            }
+            let window_closed = votation
+                .reveal_deadline()
+                .is_some_and(|deadline| now > deadline);
+            if votation.leader_id != my_peer_id || !window_closed {
+                continue;
+            }
+            if let Some(message) = crate::handler::close_reveal_window(&self.db, &votation)? {
+                let result = SignedMessage::sign(message, &self.keypair)?;
+                db::save_result(&self.db, &votation.id_votation, &result)?;
+                self.publish(votation.topic.clone(), result.to_bytes()?).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /* closes the votations that run out of time, the leader reports who did not vote */
+    pub async fn check_expired(&self) -> anyhow::Result<()> {
+        let my_peer_id = self.peer_id.to_string();
+        let now = Utc::now();
+        for votation in db::get_status_voteses(&self.db) {
+            let expiry = db::get_topic_policy(&self.db, &votation.topic).expiry();
+            let requested_at = votation.requested_at.unwrap_or(votation.timestamp);
+            if !votation.is_pending() || now <= requested_at + expiry {
+                continue;
+            }
+            if let Some(message) =
+                crate::handler::expire_votation(&self.db, &my_peer_id, &votation)?
+            {
+                self.send(votation.topic.clone(), &message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /* background task keeping the pending votations moving */
+    pub async fn watch_votations(&self) -> anyhow::Result<()> {
+        let check_interval = Duration::from_secs(1);
+        let mut next_summary = Instant::now();
+        loop {
+            if let Err(e) = self.check_reveals().await {
+                log::error!("Failed to reveal votes: {e}");
+            }
+            if let Err(e) = self.check_rounds().await {
+                log::error!("Failed to check votation rounds: {e}");
+            }
+            if let Err(e) = self.check_expired().await {
+                log::error!("Failed to check expired votations: {e}");
+            }
+            if Instant::now() >= next_summary {
+                if let Err(e) = self.publish_summaries().await {
+                    log::error!("Failed to publish topic summaries: {e}");
+                }
+                next_summary = Instant::now() + Duration::from_secs(SUMMARY_SECS);
+            }
+            if let Err(e) = self.repair_summaries().await {
+                log::error!("Failed to repair topic summaries: {e}");
+            }
+            sleep(check_interval).await;
+        }
+    }
+
     pub fn get_voters(&self, key: &str, topic: &str) -> anyhow::Result<Vec<String>> {
         db::get_voters(&self.db, &key, &topic)
     }
@@ -167,6 +639,11 @@ impl ValidatorClient {
         db::get_contents(&self.db)
     }
 
+    /* decisions taken on the content of a votation, appeals included */
+    pub fn get_content_history(&self, key: &str) -> Vec<DataContent> {
+        db::get_content_history(&self.db, key)
+    }
+
     pub fn get_status_vote(&self, key: &str) -> Option<Votation> {
         db::get_status_vote(&self.db, key)
     }
@@ -175,7 +652,7 @@ impl ValidatorClient {
         db::get_status_voteses(&self.db)
     }
 
-    pub async fn get_content_to_evaluate(&self) -> Vec<(String, String, String, Duration)> {
+    pub async fn get_content_to_evaluate(&self) -> Vec<(String, String, ContentPayload, Duration)> {
         //key, topic, content, duration
         let content_to_evaluate = self.content_to_evaluate.lock().await;
         content_to_evaluate.clone()
@@ -223,53 +700,51 @@ impl ValidatorClient {
                     continue;
                 }
 
+                let policy = db::get_topic_policy(&self.db, topic);
+                // appeals are judged by larger juries
+                let jury_size = policy.jury_size(db::votation_level(key));
                 /* we want to receive all the possible voters, f32 is the reputation */
                 let mut filtered_votes: Vec<(String, f32)> = Vec::new();
                 for possible_voter_peer_id in db::get_voters(&self.db, &key, &topic)? {
+                    // the publisher is never part of its own jury
+                    if possible_voter_peer_id == self.peer_id.to_string() {
+                        continue;
+                    }
                     let rep =
-                        db::get_reputation(&self.db, &possible_voter_peer_id.as_str(), &topic)
+                        db::get_reputation(&self.db, &topic, possible_voter_peer_id.as_str())
                             .unwrap_or_else(|| {
                                 // if it is new one we save the default reputation
                                 db::set_reputation(
                                     &self.db,
                                     &topic,
-                                    &possible_voter_peer_id.as_str(),
+                                    possible_voter_peer_id.as_str(),
                                     DEFAULT_REPUTATION,
                                 )
                                 .expect("Failed to set default reputation");
                                 DEFAULT_REPUTATION
                             });
-                    if rep >= MIN_REPUTATION_THRESHOLD {
+                    if rep >= policy.min_reputation_threshold {
                         filtered_votes.push((possible_voter_peer_id, rep));
                     }
                 }
                 log::debug!("Filtered votes for key {}: {:?}", key, filtered_votes);
-                if filtered_votes.len() >= MEMBERS_FOR_CONSENSUS {
+                if filtered_votes.len() >= jury_size {
                     log::debug!(
                         "Enough votes collected for key {}: {:?}",
                         key,
                         filtered_votes
                     );
-                    let filtered_votes: Vec<(String, f32)> =
-                        filtered_votes[0..MEMBERS_FOR_CONSENSUS].to_vec();
-                    let leader_peer = filtered_votes.first().expect("No leader available");
-                    log::debug!("Selected leader for voting: {:?}", leader_peer);
-                    let vote_request = ContentMessage::new_vote_leader_request(
-                        key.clone(),
-                        content.to_string(),
-                        self.peer_id.to_string(),
-                        filtered_votes
-                            .iter()
-                            .map(|(peer_id, _)| peer_id.clone())
-                            .collect(),
-                        leader_peer.0.clone(),
-                        60,
-                        &self.keypair,
-                    )
-                    .expect("Failed to create vote request");
-                    self.send(topic.to_string(), &vote_request)
+                    // jury and leader are drawn from all the candidates, weighted by reputation
+                    let policy = TopicPolicy {
+                        members_for_consensus: jury_size,
+                        ..policy
+                    };
+                    if let Err(e) = self
+                        .start_votation(key, topic, content, filtered_votes, &policy)
                         .await
-                        .expect("Failed to send client message");
+                    {
+                        log::error!("Failed to start votation={}: {}", key, e);
+                    }
                     content_to_evaluate.remove(index); // ✅ remove it
                     continue; // skip index++
                 }
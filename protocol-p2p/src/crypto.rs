@@ -0,0 +1,90 @@
+use crate::models::messages::public_key_from_peer_id;
+use anyhow::anyhow;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use libp2p::identity::Keypair;
+use sha2::{Digest, Sha256, Sha512};
+
+const NONCE_LEN: usize = 12;
+
+/* ed25519 keys are converted to their x25519 form, as done by age or libsodium */
+fn x25519_secret(keypair: &Keypair) -> anyhow::Result<[u8; 32]> {
+    let keypair = keypair
+        .clone()
+        .try_into_ed25519()
+        .map_err(|_| anyhow!("Key agreement needs an ed25519 identity"))?;
+    let hash = Sha512::digest(keypair.secret().as_ref());
+    let mut scalar = [0u8; 32];
+    scalar.copy_from_slice(&hash[..32]);
+    Ok(scalar)
+}
+
+fn x25519_public(peer_id: &str) -> anyhow::Result<MontgomeryPoint> {
+    let public_key = public_key_from_peer_id(peer_id)?
+        .try_into_ed25519()
+        .map_err(|_| anyhow!("Peer {peer_id} has no ed25519 identity"))?;
+    CompressedEdwardsY(public_key.to_bytes())
+        .decompress()
+        .map(|point| point.to_montgomery())
+        .ok_or_else(|| anyhow!("Invalid identity key of peer {peer_id}"))
+}
+
+/// Symmetric key shared by my identity and the identity of `peer_id`, both
+/// sides derive the same one. `context` separates the keys of each use.
+pub fn shared_key(keypair: &Keypair, peer_id: &str, context: &str) -> anyhow::Result<[u8; 32]> {
+    let secret = x25519_public(peer_id)?.mul_clamped(x25519_secret(keypair)?);
+    if secret.as_bytes() == &[0u8; 32] {
+        return Err(anyhow!("Key agreement with {peer_id} failed"));
+    }
+    let mut ends = [keypair.public().to_peer_id().to_string(), peer_id.to_string()];
+    ends.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(context.as_bytes());
+    hasher.update(secret.as_bytes());
+    for end in &ends {
+        hasher.update(end.as_bytes());
+    }
+    Ok(hasher.finalize().into())
+}
+
+// layout: nonce | ciphertext, `aad` is authenticated but not encrypted
+pub fn seal(key: &[u8; 32], plain: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad })
+        .map_err(|e| anyhow!("Failed to encrypt: {e}"))?;
+    Ok([nonce.as_slice(), ciphertext.as_slice()].concat())
+}
+
+pub fn open(key: &[u8; 32], sealed: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow!("Sealed data is truncated"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| anyhow!("Wrong key or tampered data"))
+}
+
+#[test]
+fn test_shared_key_is_agreed_by_both_peers_only() {
+    let alice = Keypair::generate_ed25519();
+    let bob = Keypair::generate_ed25519();
+    let eve = Keypair::generate_ed25519();
+    let alice_id = alice.public().to_peer_id().to_string();
+    let bob_id = bob.public().to_peer_id().to_string();
+
+    let key = shared_key(&alice, &bob_id, "test").unwrap();
+    assert_eq!(key, shared_key(&bob, &alice_id, "test").unwrap());
+    assert_ne!(key, shared_key(&eve, &alice_id, "test").unwrap());
+    assert_ne!(key, shared_key(&alice, &bob_id, "other").unwrap());
+
+    let sealed = seal(&key, b"hello", b"header").unwrap();
+    assert_eq!(open(&key, &sealed, b"header").unwrap(), b"hello");
+    assert!(open(&key, &sealed, b"forged").is_err());
+    assert!(open(&shared_key(&eve, &alice_id, "test").unwrap(), &sealed, b"header").is_err());
+}
@@ -1,13 +1,16 @@
-use crate::models::db::{DataContent, StateContent, Topic, Votation, VoteStatus};
+use crate::models::db::{
+    DataContent, PrivateTopic, StateContent, Topic, TopicPolicy, Votation, VoteStatus,
+};
+use crate::models::messages::{Ballot, ContentMessage, SignedMessage};
+#[cfg(test)]
 use crate::models::messages::Vote;
 use crate::{db, models};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use serde_json;
 use sled;
 use sled::{CompareAndSwapError, Db};
-use std::hash::{DefaultHasher, Hash, Hasher};
-use std::sync::Arc;
 
 pub fn create_key_for_voting_db(
     content_id: &str,
@@ -15,7 +18,7 @@ pub fn create_key_for_voting_db(
     status: &str,
     number_round: u32,
 ) -> String {
-    let hash_content_id = default_hash(content_id);
+    let hash_content_id = content_hash(topic, content_id);
 
     format!(
         "vote_status/{}:{}:{}:{}",
@@ -24,15 +27,182 @@ pub fn create_key_for_voting_db(
 }
 
 pub fn create_key_without_status(content_id: &str, topic: &str) -> String {
-    let content_id = default_hash(content_id);
+    let content_id = content_hash(topic, content_id);
     format!("vote_status/{}:{}", topic, content_id)
 }
 
-fn default_hash(content_id: &str) -> u64 {
-    let mut s = DefaultHasher::new();
-    content_id.hash(&mut s);
-    let hash_content_id = s.finish();
-    hash_content_id
+/// Id of a content inside a topic, the hex SHA-256 of the topic and the trimmed content.
+/// It names the votation on every node, so it must not depend on the toolchain.
+pub fn content_hash(topic: &str, content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(topic.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(content.trim().as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/* ids used to be the decimal std DefaultHasher of the content */
+fn is_legacy_hash(hash: &str) -> bool {
+    !hash.is_empty() && hash.len() <= 20 && hash.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/* `vote_status/{topic}:{hash}:` part of an id keyed with a legacy hash */
+fn legacy_content_key(id_votation: &str) -> Option<(String, &str)> {
+    let (_, rest) = id_votation.split_once("vote_status/")?;
+    let mut parts = rest.splitn(3, ':');
+    let (topic, hash) = (parts.next()?, parts.next()?);
+    parts.next()?;
+    is_legacy_hash(hash).then(|| (format!("vote_status/{topic}:{hash}:"), topic))
+}
+
+const CONTENT_IDS_VERSION_KEY: &str = "meta/content_ids";
+const CONTENT_IDS_VERSION: &str = "sha256";
+/* `meta/content_alias/{new prefix}` holds the legacy prefix of a migrated votation */
+const CONTENT_ALIAS_PREFIX: &str = "meta/content_alias/";
+
+/* legacy id of a migrated votation, its signed envelopes are still stored under it */
+fn legacy_id(db: &Db, id_votation: &str) -> Option<String> {
+    let (_, rest) = id_votation.split_once("vote_status/")?;
+    let (topic, rest) = rest.split_once(':')?;
+    let (hash, _) = rest.split_once(':')?;
+    let new = format!("vote_status/{topic}:{hash}:");
+    let old = db.get(format!("{CONTENT_ALIAS_PREFIX}{new}")).ok()??;
+    Some(id_votation.replacen(&new, &String::from_utf8_lossy(&old), 1))
+}
+
+/* signed record stored under `{prefix}{id_votation}`, or under the legacy id */
+fn get_signed(db: &Db, prefix: &str, id_votation: &str) -> Option<sled::IVec> {
+    match db.get(format!("{prefix}{id_votation}")).ok().flatten() {
+        Some(value) => Some(value),
+        None => db
+            .get(format!("{prefix}{}", legacy_id(db, id_votation)?))
+            .ok()
+            .flatten(),
+    }
+}
+
+/* an id, or the id of a removal votation, starting with a renamed prefix */
+fn renamed_id(renames: &[(String, String)], text: &str) -> Option<String> {
+    let (removal, id) = match text.strip_prefix("removal/") {
+        Some(id) => ("removal/", id),
+        None => ("", text),
+    };
+    renames.iter().find_map(|(old, new)| {
+        id.strip_prefix(old.as_str())
+            .map(|rest| format!("{removal}{new}{rest}"))
+    })
+}
+
+/* renames the ids found in the strings of a record, other text is left as it is */
+fn rename_ids(renames: &[(String, String)], value: &mut serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::String(text) => match renamed_id(renames, text) {
+            Some(new) => {
+                *text = new;
+                true
+            }
+            None => false,
+        },
+        serde_json::Value::Array(items) => items
+            .iter_mut()
+            .fold(false, |renamed, item| rename_ids(renames, item) || renamed),
+        serde_json::Value::Object(fields) => fields
+            .values_mut()
+            .fold(false, |renamed, field| rename_ids(renames, field) || renamed),
+        _ => false,
+    }
+}
+
+/// Rewrites the keys and records of votations named with the old `DefaultHasher` ids.
+/// Signed envelopes can not be rewritten without breaking their signature, they stay
+/// under their old key and an alias of the new id leads to them. Runs once, returns
+/// how many entries were moved.
+pub fn migrate_content_ids(db: &Db) -> anyhow::Result<usize> {
+    if db.get(CONTENT_IDS_VERSION_KEY)?.as_deref() == Some(CONTENT_IDS_VERSION.as_bytes()) {
+        return Ok(0);
+    }
+    let mut renames: Vec<(String, String)> = Vec::new();
+    let votations = db
+        .scan_prefix("pending_content/")
+        .values()
+        .filter_map(|value| serde_json::from_slice::<Votation>(&value.ok()?).ok())
+        .map(|votation| (votation.id_votation, votation.content));
+    let contents = get_all_contents(db)
+        .into_iter()
+        .map(|data_content| (data_content.id_votation, data_content.content));
+    for (id_votation, content) in votations.chain(contents) {
+        if id_votation.starts_with("removal/") {
+            continue;
+        }
+        if let Some((old, topic)) = legacy_content_key(&id_votation) {
+            let new = format!(
+                "vote_status/{topic}:{}:",
+                content_hash(topic, content.key_source())
+            );
+            if !renames.iter().any(|(known, _)| *known == old) {
+                renames.push((old, new));
+            }
+        }
+    }
+
+    let rename_key = |key: &str| {
+        renames
+            .iter()
+            .find(|(old, _)| key.contains(old.as_str()))
+            .map(|(old, new)| key.replacen(old.as_str(), new, 1))
+    };
+    let mut moved = 0;
+    for item in db.iter() {
+        let (key, value) = item?;
+        let key = String::from_utf8_lossy(&key).to_string();
+        let signed = [
+            "election/result/",
+            "election/signed_vote/",
+            "election/request/",
+            "election/appeal/",
+            "election/interest/",
+        ]
+            .iter()
+            .any(|prefix| key.starts_with(prefix));
+        if signed {
+            continue;
+        }
+        // records are JSON, the entries of the history hold a bare id
+        let new_value = match serde_json::from_slice::<serde_json::Value>(&value) {
+            Ok(mut record) => {
+                if rename_ids(&renames, &mut record) {
+                    Some(serde_json::to_vec(&record)?)
+                } else {
+                    None
+                }
+            }
+            Err(_) => renamed_id(&renames, &String::from_utf8_lossy(&value)).map(String::into_bytes),
+        };
+        match (rename_key(&key), new_value) {
+            (Some(new_key), new_value) => {
+                db.insert(new_key, new_value.unwrap_or(value.to_vec()))?;
+                db.remove(key)?;
+            }
+            (None, Some(new_value)) => {
+                db.insert(key, new_value)?;
+            }
+            (None, None) => continue,
+        }
+        moved += 1;
+    }
+    for (old, new) in &renames {
+        db.insert(format!("{CONTENT_ALIAS_PREFIX}{new}"), old.as_bytes())?;
+    }
+    db.insert(CONTENT_IDS_VERSION_KEY, CONTENT_IDS_VERSION.as_bytes())?;
+    db.flush()?;
+    if moved > 0 {
+        log::info!("Migrated {} entries to stable content ids", moved);
+    }
+    Ok(moved)
 }
 
 pub fn init_db(path: &str) -> anyhow::Result<Db> {
@@ -43,6 +213,8 @@ pub fn init_db(path: &str) -> anyhow::Result<Db> {
     } else {
         log::debug!("Fresh database or clean shutdown last time.");
     }
+    migrate_content_ids(&db)?;
+    migrate_topics(&db)?;
     Ok(db)
 }
 
@@ -171,7 +343,7 @@ pub fn update_reputations(
     Ok(())
 }
 
-/* Topics db */
+/* Topics db, one entry per topic under `topics/{name}` */
 pub async fn get_topics(db: &Db) -> Vec<Topic> {
     db.scan_prefix("topics/")
         .filter_map(|item| {
@@ -181,17 +353,207 @@ pub async fn get_topics(db: &Db) -> Vec<Topic> {
                 None
             }
         })
+        .map(|mut topic| {
+            topic.policy = get_topic_policy(db, &topic.name);
+            topic
+        })
         .collect()
 }
 
+/* consensus parameters per topic, the defaults until the topic announces its own */
+pub fn get_topic_policy(db: &Db, topic: &str) -> TopicPolicy {
+    let key = format!("topic_policy/{topic}");
+    db.get(key)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_slice::<TopicPolicy>(&value).ok())
+        .map(TopicPolicy::clamped)
+        .unwrap_or_default()
+}
+
+/* policies are clamped before being stored, they may come from the network */
+pub fn save_topic_policy(db: &Db, topic: &str, policy: &TopicPolicy) -> anyhow::Result<()> {
+    let key = format!("topic_policy/{topic}");
+    db.insert(key, serde_json::to_vec(&policy.clone().clamped())?)?;
+    Ok(())
+}
+
+/* keeps the first policy announced for a topic, returns false if there was one */
+pub fn init_topic_policy(db: &Db, topic: &str, policy: &TopicPolicy) -> anyhow::Result<bool> {
+    let key = format!("topic_policy/{topic}");
+    let policy = policy.clone().clamped();
+    Ok(db
+        .compare_and_swap(key, None::<Vec<u8>>, Some(serde_json::to_vec(&policy)?))?
+        .is_ok())
+}
+
+/// Registers a topic announced by its creator, with its policy. Once a topic
+/// has a creator only it can announce the topic again, to update the policy.
+/// Returns false if the topic was created by another peer.
+pub fn announce_topic(db: &Db, announced: &Topic) -> anyhow::Result<bool> {
+    match get_topic(db, &announced.name) {
+        Some(known) if !known.creator.is_empty() && known.creator != announced.creator => {
+            return Ok(false);
+        }
+        Some(known) if known.creator == announced.creator => {
+            save_topic_policy(db, &announced.name, &announced.policy)?;
+        }
+        _ => {
+            init_topic_policy(db, &announced.name, &announced.policy)?;
+        }
+    }
+    add_known_topic(db, announced)?;
+    Ok(true)
+}
+
+pub fn get_topic(db: &Db, name: &str) -> Option<Topic> {
+    let key = format!("topics/{name}");
+    db.get(key)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_slice::<Topic>(&value).ok())
+        .map(|mut topic| {
+            topic.policy = get_topic_policy(db, &topic.name);
+            topic
+        })
+}
+
+/// Stores a topic in the registry. Who created it and when are kept from the first save.
 pub async fn save_topic(db: &Db, topic: &Topic) -> anyhow::Result<()> {
-    let key = "topics/";
-    let value = serde_json::to_string(topic)?.into_bytes();
-    db.insert(key, value)?;
+    let mut topic = topic.clone();
+    if let Some(known) = get_topic(db, &topic.name) {
+        if !known.creator.is_empty() {
+            topic.creator = known.creator;
+        }
+        topic.created_at = known.created_at.or(topic.created_at);
+    }
+    put_topic(db, &topic)?;
     db.flush()?;
     Ok(())
 }
 
+/* the policy may come from the network, a NaN would be stored as null and hide the record */
+fn put_topic(db: &Db, topic: &Topic) -> anyhow::Result<()> {
+    let key = format!("topics/{}", topic.name);
+    let topic = Topic {
+        policy: topic.policy.clone().clamped(),
+        ..topic.clone()
+    };
+    db.insert(key, serde_json::to_vec(&topic)?)?;
+    Ok(())
+}
+
+/// Adds a topic announced on the network to the catalog, without joining it.
+/// A topic already known only gets the announced details it was missing.
+/// Returns false if the topic was known.
+pub fn add_known_topic(db: &Db, announced: &Topic) -> anyhow::Result<bool> {
+    let Some(mut topic) = get_topic(db, &announced.name) else {
+        put_topic(db, announced)?;
+        db.flush()?;
+        return Ok(true);
+    };
+    if topic.description.is_empty() {
+        topic.description = announced.description.clone();
+    }
+    if topic.creator.is_empty() {
+        topic.creator = announced.creator.clone();
+        topic.created_at = announced.created_at.or(topic.created_at);
+    }
+    put_topic(db, &topic)?;
+    Ok(false)
+}
+
+/// Marks a topic as joined or left, returns false if the topic is not in the registry.
+pub fn set_topic_subscribed(db: &Db, name: &str, subscribed: bool) -> anyhow::Result<bool> {
+    let Some(mut topic) = get_topic(db, name) else {
+        return Ok(false);
+    };
+    topic.subscribed = subscribed;
+    put_topic(db, &topic)?;
+    db.flush()?;
+    Ok(true)
+}
+
+/* members of a topic when it is private, None for public topics */
+pub fn get_private_topic(db: &Db, topic: &str) -> Option<PrivateTopic> {
+    let key = format!("private_topic/{topic}");
+    db.get(key)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_slice::<PrivateTopic>(&value).ok())
+}
+
+pub fn save_private_topic(db: &Db, topic: &str, private: &PrivateTopic) -> anyhow::Result<()> {
+    let key = format!("private_topic/{topic}");
+    db.insert(key, serde_json::to_vec(private)?)?;
+    Ok(())
+}
+
+/* keys of every epoch are kept, the content sealed before a rotation stays readable */
+pub fn save_topic_key(db: &Db, topic: &str, epoch: u32, key: &[u8; 32]) -> anyhow::Result<()> {
+    let db_key = format!("topic_key/{topic}/{epoch:010}");
+    db.insert(db_key, key.as_slice())?;
+    db.flush()?;
+    Ok(())
+}
+
+pub fn get_topic_key(db: &Db, topic: &str, epoch: u32) -> Option<[u8; 32]> {
+    let db_key = format!("topic_key/{topic}/{epoch:010}");
+    db.get(db_key).ok().flatten()?.as_ref().try_into().ok()
+}
+
+/* peers found providing a topic in the DHT, with when they were last seen */
+pub fn add_topic_provider(db: &Db, topic: &str, peer_id: &str) -> anyhow::Result<()> {
+    let key = format!("providers/{topic}/{peer_id}");
+    db.insert(key, serde_json::to_vec(&Utc::now())?)?;
+    Ok(())
+}
+
+/* providers seen within `PROVIDER_TTL`, the older ones are dropped */
+pub fn get_topic_providers(db: &Db, topic: &str) -> Vec<String> {
+    let prefix = format!("providers/{topic}/");
+    let oldest = Utc::now() - crate::PROVIDER_TTL;
+    db.scan_prefix(&prefix)
+        .filter_map(|item| {
+            let (key, value) = item.ok()?;
+            let seen_at = serde_json::from_slice::<DateTime<Utc>>(&value).ok();
+            if seen_at.is_none_or(|seen_at| seen_at < oldest) {
+                let _ = db.remove(&key);
+                return None;
+            }
+            let key = String::from_utf8(key.to_vec()).ok()?;
+            key.strip_prefix(&prefix).map(str::to_string)
+        })
+        .collect()
+}
+
+/* when a DHT lookup of the providers of a topic last answered */
+pub fn set_providers_lookup(db: &Db, topic: &str) -> anyhow::Result<()> {
+    db.insert(format!("providers_lookup/{topic}"), serde_json::to_vec(&Utc::now())?)?;
+    Ok(())
+}
+
+pub fn get_providers_lookup(db: &Db, topic: &str) -> Option<DateTime<Utc>> {
+    db.get(format!("providers_lookup/{topic}"))
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_slice(&value).ok())
+}
+
+/* every topic used to be written to the single key `topics/` */
+fn migrate_topics(db: &Db) -> anyhow::Result<()> {
+    if let Some(value) = db.get("topics/")? {
+        if let Ok(topic) = serde_json::from_slice::<Topic>(&value)
+            && db.get(format!("topics/{}", topic.name))?.is_none()
+        {
+            put_topic(db, &topic)?;
+        }
+        db.remove("topics/")?;
+        db.flush()?;
+    }
+    Ok(())
+}
+
 /*  Content db */
 pub fn include_new_validated_content(
     db: &Db,
@@ -205,7 +567,21 @@ pub fn include_new_validated_content(
     Ok(())
 }
 
-pub fn get_contents(db: &Db) -> Vec<models::db::DataContent> {
+/* retracted or removed, its result is no longer served */
+pub fn is_withdrawn(db: &Db, id_votation: &str) -> bool {
+    db.get(format!("content/{id_votation}"))
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_slice::<models::db::DataContent>(&value).ok())
+        .is_some_and(|content| {
+            matches!(
+                content.approved,
+                StateContent::Retracted | StateContent::Removed
+            )
+        })
+}
+
+fn get_all_contents(db: &Db) -> Vec<models::db::DataContent> {
     db.scan_prefix("content/")
         .filter_map(|item| {
             if let Ok((_key, value)) = item {
@@ -217,6 +593,242 @@ pub fn get_contents(db: &Db) -> Vec<models::db::DataContent> {
         .collect()
 }
 
+/* the decision of the last appeal supersedes the ones before it */
+pub fn get_contents(db: &Db) -> Vec<models::db::DataContent> {
+    let mut latest: Vec<models::db::DataContent> = Vec::new();
+    for content in get_all_contents(db) {
+        let same_content = latest.iter_mut().find(|known| {
+            votation_content_key(&known.id_votation) == votation_content_key(&content.id_votation)
+        });
+        match same_content {
+            Some(known) if votation_level(&known.id_votation) < votation_level(&content.id_votation) => {
+                *known = content
+            }
+            Some(_) => {}
+            None => latest.push(content),
+        }
+    }
+    latest
+}
+
+/* decided content of a single topic, its votation ids start with `vote_status/{topic}:` */
+pub fn get_topic_contents(db: &Db, topic: &str) -> Vec<models::db::DataContent> {
+    get_contents(db)
+        .into_iter()
+        .filter(|content| votation_topic(&content.id_votation) == Some(topic))
+        .collect()
+}
+
+/* every decision taken on the content of a votation, from the first level to the last appeal */
+pub fn get_content_history(db: &Db, id_votation: &str) -> Vec<models::db::DataContent> {
+    let mut history: Vec<models::db::DataContent> = get_all_contents(db)
+        .into_iter()
+        .filter(|content| {
+            votation_content_key(&content.id_votation) == votation_content_key(id_votation)
+        })
+        .collect();
+    history.sort_by_key(|content| votation_level(&content.id_votation));
+    history
+}
+
+/* the content of a votation loses its text and takes a final `state` at every level */
+pub fn withdraw_content(db: &Db, id_votation: &str, state: StateContent) -> anyhow::Result<bool> {
+    let history = get_content_history(db, id_votation);
+    for content in &history {
+        let mut withdrawn = content.clone();
+        withdrawn.content = content.content.withdrawn();
+        withdrawn.approved = state.clone();
+        include_new_validated_content(db, &withdrawn)?;
+    }
+    Ok(!history.is_empty())
+}
+
+/* signed retraction of a content, what proves its withdrawal to the peers that still hold it */
+pub fn save_withdrawal(db: &Db, id_votation: &str, envelope: &SignedMessage) -> anyhow::Result<()> {
+    let key = format!("withdrawal/{}", votation_content_key(id_votation));
+    db.insert(key, envelope.to_bytes()?)?;
+    Ok(())
+}
+
+/// Proof that a content was withdrawn: the retraction of its publisher, or the
+/// approved result of a removal votation on it.
+pub fn get_withdrawal(db: &Db, content_key: &str) -> Option<SignedMessage> {
+    let retraction = db
+        .get(format!("withdrawal/{content_key}"))
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_slice::<SignedMessage>(&value).ok());
+    retraction.or_else(|| {
+        db.scan_prefix(format!("election/result/removal/{content_key}:"))
+            .values()
+            .filter_map(|value| serde_json::from_slice::<SignedMessage>(&value.ok()?).ok())
+            .find(|result| {
+                matches!(
+                    result.payload,
+                    ContentMessage::IncludeNewValidatedContent { approved: true, .. }
+                )
+            })
+    })
+}
+
+/* appeals */
+
+/* votation ids end with their level, `vote_status/{topic}:{hash}:{status}:{level}` */
+pub fn votation_level(id_votation: &str) -> u32 {
+    id_votation
+        .rsplit_once(':')
+        .and_then(|(_, level)| level.parse().ok())
+        .unwrap_or(1)
+}
+
+/* topic a votation belongs to, removal votations are keyed after the content they flag */
+pub fn votation_topic(id_votation: &str) -> Option<&str> {
+    id_votation
+        .strip_prefix("removal/")
+        .unwrap_or(id_votation)
+        .strip_prefix("vote_status/")?
+        .split_once(':')
+        .map(|(topic, _)| topic)
+}
+
+/* the part of a votation id naming its content, the same for all its levels */
+pub fn votation_content_key(id_votation: &str) -> &str {
+    id_votation
+        .rsplit_once(':')
+        .map(|(content_key, _)| content_key)
+        .unwrap_or(id_votation)
+}
+
+/* id of the votation appealing `id_votation` */
+pub fn appeal_key(id_votation: &str) -> String {
+    format!(
+        "{}:{}",
+        votation_content_key(id_votation),
+        votation_level(id_votation) + 1
+    )
+}
+
+/* the first votation of a content, the one its publisher asked for */
+pub fn first_level_key(id_votation: &str) -> String {
+    format!("{}:1", votation_content_key(id_votation))
+}
+
+/* flagged content is voted again in a removal votation, `removal/{id_votation}:{level}` */
+pub fn removal_key(id_votation: &str) -> String {
+    format!("removal/{id_votation}:1")
+}
+
+/* content a removal votation decides on, None for the votations of new content */
+pub fn removal_target(id_votation: &str) -> Option<&str> {
+    id_votation
+        .strip_prefix("removal/")
+        .map(votation_content_key)
+}
+
+/* last decided votation of a content, the one an appeal contests */
+pub fn get_latest_decided_votation(db: &Db, topic: &str, content: &str) -> Option<Votation> {
+    let prefix = format!("pending_content/{}:", create_key_without_status(content, topic));
+    db.scan_prefix(prefix)
+        .values()
+        .filter_map(|value| serde_json::from_slice::<Votation>(&value.ok()?).ok())
+        .filter(|votation| votation.status == "approved" || votation.status == "rejected")
+        .max_by_key(|votation| votation_level(&votation.id_votation))
+}
+
+/* result announced by the leader of a votation, as signed by it, a new one is logged in the history of its topic */
+pub fn save_result(db: &Db, id_votation: &str, envelope: &SignedMessage) -> anyhow::Result<()> {
+    let key = format!("election/result/{id_votation}");
+    let known = db.insert(key, envelope.to_bytes()?)?.is_some();
+    if let (false, Some(topic)) = (known, votation_topic(id_votation)) {
+        let position = db.generate_id()?;
+        db.insert(format!("history/{topic}/{position:020}"), id_votation.as_bytes())?;
+    }
+    Ok(())
+}
+
+pub fn get_result(db: &Db, id_votation: &str) -> Option<SignedMessage> {
+    get_signed(db, "election/result/", id_votation)
+        .and_then(|value| serde_json::from_slice::<SignedMessage>(&value).ok())
+}
+
+/// Results of a topic in the order they were logged, the ones after `cursor` up to `limit`,
+/// each with its position in the history. Results of withdrawn content are left out,
+/// they carry the text that was withdrawn.
+pub fn get_history(
+    db: &Db,
+    topic: &str,
+    cursor: Option<u64>,
+    limit: usize,
+) -> Vec<(u64, SignedMessage)> {
+    let prefix = format!("history/{topic}/");
+    db.scan_prefix(&prefix)
+        .filter_map(|item| {
+            let (key, value) = item.ok()?;
+            let position = String::from_utf8_lossy(&key)
+                .strip_prefix(&prefix)?
+                .parse::<u64>()
+                .ok()?;
+            Some((position, String::from_utf8(value.to_vec()).ok()?))
+        })
+        .filter(|(position, _)| cursor.is_none_or(|cursor| *position > cursor))
+        .filter(|(_, id_votation)| !is_withdrawn(db, id_votation))
+        .filter_map(|(position, id_votation)| Some((position, get_result(db, &id_votation)?)))
+        .take(limit)
+        .collect()
+}
+
+/* where the history of a topic served by a peer was read up to */
+pub fn get_history_cursor(db: &Db, topic: &str, peer_id: &str) -> Option<u64> {
+    let key = format!("history_cursor/{topic}/{peer_id}");
+    db.get(key)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_slice::<u64>(&value).ok())
+}
+
+pub fn set_history_cursor(db: &Db, topic: &str, peer_id: &str, cursor: u64) -> anyhow::Result<()> {
+    let key = format!("history_cursor/{topic}/{peer_id}");
+    db.insert(key, serde_json::to_vec(&cursor)?)?;
+    Ok(())
+}
+
+/* buckets of a topic where the summary gossiped by a peer differs from mine */
+pub fn save_summary_gap(db: &Db, topic: &str, peer_id: &str, buckets: &[usize]) -> anyhow::Result<()> {
+    let key = format!("summary_gap/{topic}/{peer_id}");
+    db.insert(key, serde_json::to_vec(buckets)?)?;
+    Ok(())
+}
+
+/* pending summary gaps as (topic, peer, buckets), removed once taken */
+pub fn take_summary_gaps(db: &Db) -> Vec<(String, String, Vec<usize>)> {
+    db.scan_prefix("summary_gap/")
+        .filter_map(|item| {
+            let (key, value) = item.ok()?;
+            db.remove(&key).ok()?;
+            let key = String::from_utf8(key.to_vec()).ok()?;
+            let (topic, peer_id) = key.strip_prefix("summary_gap/")?.rsplit_once('/')?;
+            let buckets = serde_json::from_slice::<Vec<usize>>(&value).ok()?;
+            Some((topic.to_string(), peer_id.to_string(), buckets))
+        })
+        .collect()
+}
+
+/* contested result for an appeal waiting for its jury */
+pub fn save_pending_appeal(
+    db: &Db,
+    id_votation: &str,
+    previous: &SignedMessage,
+) -> anyhow::Result<()> {
+    let key = format!("election/appeal/{id_votation}");
+    db.insert(key, previous.to_bytes()?)?;
+    Ok(())
+}
+
+pub fn get_pending_appeal(db: &Db, id_votation: &str) -> Option<SignedMessage> {
+    get_signed(db, "election/appeal/", id_votation)
+        .and_then(|value| serde_json::from_slice::<SignedMessage>(&value).ok())
+}
+
 /* Save my pending content to validate, if I am proposed */
 pub fn my_pending_content_to_validate(db: &Db, data_content: &DataContent) -> anyhow::Result<()> {
     let id_votation = data_content.id_votation.clone();
@@ -241,14 +853,15 @@ pub fn get_my_pending_to_contents_to_validate(db: &Db) -> Vec<models::db::DataCo
 
 /* votes db operations */
 
-pub fn get_votes(db: &Db, id_votation: &str) -> Vec<(String, Vote)> {
-    let key = format!("election/vote/{id_votation}");
+/* votes are kept per round, a new leader collects them again */
+pub fn get_votes(db: &Db, id_votation: &str, round: u32) -> Vec<(String, Ballot)> {
+    let key = format!("election/vote/{id_votation}/{round}/");
     db.scan_prefix(key)
         .filter_map(|item| {
-            if let Ok((find_key, value)) = item {
-                let value = serde_json::from_slice::<(&str, Vote)>(&value)
+            if let Ok((_, value)) = item {
+                let value = serde_json::from_slice::<(&str, Ballot)>(&value)
                     .ok()
-                    .map(|(s, vote)| (s.to_string(), vote));
+                    .map(|(s, ballot)| (s.to_string(), ballot));
                 value
             } else {
                 None
@@ -257,20 +870,152 @@ pub fn get_votes(db: &Db, id_votation: &str) -> Vec<(String, Vote)> {
         .collect()
 }
 
-pub fn exists_vote(db: &Db, id_votation: &str, peer_id: &str) -> anyhow::Result<bool> {
-    let key = format!("election/vote/{id_votation}/{peer_id}");
+pub fn exists_vote(
+    db: &Db,
+    id_votation: &str,
+    round: u32,
+    peer_id: &str,
+) -> anyhow::Result<bool> {
+    let key = format!("election/vote/{id_votation}/{round}/{peer_id}");
     let exists = db.contains_key(key)?; // propagates error if any
     Ok(exists)
 }
 
-pub fn add_vote(db: &Db, id_votation: &str, peer_id: &str, vote: &Vote) -> anyhow::Result<()> {
-    let key = format!("election/vote/{id_votation}/{peer_id}");
-    db.insert(
-        key.to_string(),
-        serde_json::to_vec(&(peer_id, vote.clone()))?,
-    )?;
+pub fn add_vote(
+    db: &Db,
+    id_votation: &str,
+    round: u32,
+    peer_id: &str,
+    ballot: &Ballot,
+) -> anyhow::Result<()> {
+    let key = format!("election/vote/{id_votation}/{round}/{peer_id}");
+    db.insert(key.to_string(), serde_json::to_vec(&(peer_id, ballot))?)?;
+    Ok(())
+}
+
+/* signed ballots as the jurors sent them, the leader publishes them with the result */
+pub fn add_signed_vote(
+    db: &Db,
+    id_votation: &str,
+    round: u32,
+    peer_id: &str,
+    envelope: &SignedMessage,
+) -> anyhow::Result<()> {
+    let key = format!("election/signed_vote/{id_votation}/{round}/{peer_id}");
+    db.insert(key, envelope.to_bytes()?)?;
+    Ok(())
+}
+
+pub fn get_signed_votes(db: &Db, id_votation: &str, round: u32) -> Vec<SignedMessage> {
+    let key = format!("election/signed_vote/{id_votation}/{round}/");
+    db.scan_prefix(key)
+        .values()
+        .filter_map(|value| serde_json::from_slice::<SignedMessage>(&value.ok()?).ok())
+        .collect()
+}
+
+/* InterestedResponse signed by a candidate, the publisher hands them to the jury */
+pub fn add_interest(
+    db: &Db,
+    id_votation: &str,
+    peer_id: &str,
+    envelope: &SignedMessage,
+) -> anyhow::Result<()> {
+    let key = format!("election/interest/{id_votation}/{peer_id}");
+    db.insert(key, envelope.to_bytes()?)?;
     Ok(())
 }
+
+pub fn get_interests(db: &Db, id_votation: &str) -> Vec<SignedMessage> {
+    let key = format!("election/interest/{id_votation}/");
+    db.scan_prefix(key)
+        .values()
+        .filter_map(|value| serde_json::from_slice::<SignedMessage>(&value.ok()?).ok())
+        .collect()
+}
+
+/* request signed by the publisher that started the votation */
+pub fn save_vote_request(db: &Db, id_votation: &str, request: &ContentMessage) -> anyhow::Result<()> {
+    let key = format!("election/request/{id_votation}");
+    db.insert(key, serde_json::to_vec(request)?)?;
+    Ok(())
+}
+
+pub fn get_vote_request(db: &Db, id_votation: &str) -> Option<ContentMessage> {
+    get_signed(db, "election/request/", id_votation)
+        .and_then(|value| serde_json::from_slice::<ContentMessage>(&value).ok())
+}
+
+/* commitments of the commit phase, only the first one of each juror counts */
+pub fn add_commitment(
+    db: &Db,
+    id_votation: &str,
+    peer_id: &str,
+    commitment: &str,
+) -> anyhow::Result<bool> {
+    let key = format!("election/commit/{id_votation}/{peer_id}");
+    Ok(db
+        .compare_and_swap(key, None::<Vec<u8>>, Some(commitment.as_bytes()))?
+        .is_ok())
+}
+
+pub fn get_commitment(db: &Db, id_votation: &str, peer_id: &str) -> Option<String> {
+    let key = format!("election/commit/{id_votation}/{peer_id}");
+    db.get(key)
+        .ok()
+        .flatten()
+        .and_then(|value| String::from_utf8(value.to_vec()).ok())
+}
+
+pub fn get_committed_voters(db: &Db, id_votation: &str) -> Vec<String> {
+    let prefix = format!("election/commit/{id_votation}/");
+    db.scan_prefix(prefix.clone())
+        .keys()
+        .filter_map(|key| {
+            let key = key.ok()?;
+            String::from_utf8(key.to_vec())
+                .ok()
+                .and_then(|key| key.strip_prefix(&prefix).map(str::to_string))
+        })
+        .collect()
+}
+const NONCES_PRUNED_KEY: &str = "meta/nonces_pruned_at";
+
+/* envelopes older than MAX_MESSAGE_AGE are rejected before their nonce is checked, so
+their nonces can be dropped; the scan runs at most once per MAX_MESSAGE_AGE */
+fn prune_nonces(db: &Db, now: DateTime<Utc>) -> anyhow::Result<()> {
+    let pruned_at = db
+        .get(NONCES_PRUNED_KEY)?
+        .and_then(|value| serde_json::from_slice::<DateTime<Utc>>(&value).ok());
+    if pruned_at.is_some_and(|pruned_at| now - pruned_at < crate::MAX_MESSAGE_AGE) {
+        return Ok(());
+    }
+    db.insert(NONCES_PRUNED_KEY, serde_json::to_vec(&now)?)?;
+    for item in db.scan_prefix("seen_nonce/") {
+        let (key, value) = item?;
+        let seen_at = serde_json::from_slice::<DateTime<Utc>>(&value).ok();
+        if seen_at.is_none_or(|seen_at| now - seen_at > crate::MAX_MESSAGE_AGE) {
+            db.remove(key)?;
+        }
+    }
+    Ok(())
+}
+
+/* replay protection for signed envelopes, returns false if the nonce was already seen */
+pub fn register_nonce(
+    db: &Db,
+    signer: &str,
+    nonce: u64,
+    timestamp: &DateTime<Utc>,
+) -> anyhow::Result<bool> {
+    prune_nonces(db, Utc::now())?;
+    let key = format!("seen_nonce/{signer}/{nonce}");
+    let value = serde_json::to_vec(timestamp)?;
+    Ok(db
+        .compare_and_swap(key, None::<Vec<u8>>, Some(value))?
+        .is_ok())
+}
+
 //TODO restructure keys (naming and where we save the tables)
 /* Status vote db opers */
 pub fn new_status_vote(
@@ -313,6 +1058,12 @@ pub fn get_status_voteses(db: &sled::Db) -> Vec<Votation> {
         .collect()
 }
 
+pub fn save_status_vote(db: &sled::Db, votation: &Votation) -> anyhow::Result<()> {
+    let id_status_vote = format!("pending_content/{}", votation.id_votation);
+    db.insert(id_status_vote, serde_json::to_vec(votation)?)?;
+    Ok(())
+}
+
 pub fn compare_and_swap_status_vote(
     db: &sled::Db,
     id_votation: &str,
@@ -347,6 +1098,51 @@ fn test_create_key_without_status() {
     assert!(key.starts_with("vote_status/topicA:"));
 }
 
+#[test]
+fn test_content_hash_is_pinned() {
+    assert_eq!(
+        create_key_for_voting_db("content123", "topicA", "pending", 1),
+        "vote_status/topicA:5e466da16a44d108177ade5936db0d93d792b4bde6ede2deccbfc434ba2523b1:pending:1"
+    );
+    assert_eq!(content_hash("topicA", " content123\n"), content_hash("topicA", "content123"));
+    assert_ne!(content_hash("topicA", "content123"), content_hash("topicB", "content123"));
+}
+
+#[test]
+fn test_migrate_legacy_content_ids() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    db.remove(CONTENT_IDS_VERSION_KEY).unwrap();
+    let legacy = "vote_status/topicA:1234567890:pending:1";
+    include_new_validated_content(&db, &DataContent::new(legacy.to_string(), "content".into(), true))
+        .unwrap();
+    db.insert(format!("election/topicA/jury/{legacy}"), b"[]".to_vec()).unwrap();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let result = SignedMessage::sign(
+        ContentMessage::InterestedResponse {
+            id_votation: legacy.to_string(),
+        },
+        &keypair,
+    )
+    .unwrap();
+    save_result(&db, legacy, &result).unwrap();
+
+    // content, jury and the entry of the history
+    assert_eq!(migrate_content_ids(&db).unwrap(), 3);
+    let stable = create_key_for_voting_db("content", "topicA", "pending", 1);
+    let contents = get_contents(&db);
+    assert_eq!(contents.len(), 1);
+    assert_eq!(contents[0].id_votation, stable);
+    assert!(db.get(format!("election/topicA/jury/{stable}")).unwrap().is_some());
+    assert!(db.get(format!("election/topicA/jury/{legacy}")).unwrap().is_none());
+
+    // the signed result stays under its legacy id and is found with the new one
+    assert!(db.get(format!("election/result/{legacy}")).unwrap().is_some());
+    assert!(get_result(&db, &stable).is_some());
+    assert_eq!(get_history(&db, "topicA", None, 10).len(), 1);
+    assert_eq!(migrate_content_ids(&db).unwrap(), 0);
+}
+
 #[test]
 fn test_store_and_get_voters() {
     use std::collections::HashSet;
@@ -386,7 +1182,7 @@ fn test_include_and_read_validated_content() {
     let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
 
     let data =
-        models::db::DataContent::new("vote_01".to_string(), "important_content".to_string(), true);
+        models::db::DataContent::new("vote_01".to_string(), "important_content".to_string().into(), true);
     include_new_validated_content(&db, &data).unwrap();
 
     let key = format!("/content/{}", data.id_votation);
@@ -394,7 +1190,7 @@ fn test_include_and_read_validated_content() {
     let decoded: models::db::DataContent = serde_json::from_slice(&raw).unwrap();
 
     assert_eq!(decoded.id_votation, "vote_01");
-    assert_eq!(decoded.content, "important_content");
+    assert_eq!(decoded.content, "important_content".into());
     matches!(decoded.approved, StateContent::Approved);
 }
 
@@ -405,7 +1201,7 @@ fn test_insert_and_get_status_vote() {
 
     let vote = Votation::new(
         "vote_id_123".to_string(),
-        "content_xyz".to_string(),
+        "content_xyz".to_string().into(),
         "approved".to_string(),
         "leader123".to_string(),
         "leader".to_string(),
@@ -426,7 +1222,7 @@ fn test_insert_and_get_status_vote_and_update() {
 
     let vote = Votation::new(
         "vote_id_123".to_string(),
-        "content_xyz".to_string(),
+        "content_xyz".to_string().into(),
         "approved".to_string(),
         "leader123".to_string(),
         "leader".to_string(),
@@ -446,3 +1242,226 @@ fn test_insert_and_get_status_vote_and_update() {
     let votes = get_status_voteses(&db);
     println!("votes {:?}", votes);
 }
+
+#[test]
+fn test_topic_policy_keeps_first_announcement() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    assert_eq!(get_topic_policy(&db, "topicA"), TopicPolicy::default());
+
+    let small = TopicPolicy {
+        members_for_consensus: 3,
+        ..Default::default()
+    };
+    let large = TopicPolicy {
+        members_for_consensus: 11,
+        ..Default::default()
+    };
+    assert!(init_topic_policy(&db, "topicA", &small).unwrap());
+    assert!(!init_topic_policy(&db, "topicA", &large).unwrap());
+    assert_eq!(get_topic_policy(&db, "topicA").members_for_consensus, 3);
+}
+
+#[test]
+fn test_announced_policy_is_clamped_and_bound_to_its_creator() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    let announced = |creator: &str, policy: TopicPolicy| Topic {
+        creator: creator.to_string(),
+        subscribed: false,
+        policy,
+        ..Topic::new("topicA", "announced")
+    };
+
+    let broken = TopicPolicy {
+        members_for_consensus: 0,
+        threshold_approve: f32::NAN,
+        expiry_secs: i64::MAX,
+        ..Default::default()
+    };
+    assert!(announce_topic(&db, &announced("creator", broken)).unwrap());
+    let policy = get_topic_policy(&db, "topicA");
+    assert_eq!(policy.members_for_consensus, 1);
+    assert_eq!(policy.threshold_approve, TopicPolicy::default().threshold_approve);
+    assert!(policy.expiry() > chrono::Duration::zero());
+
+    // only the creator changes the policy of its topic
+    let squatted = TopicPolicy {
+        members_for_consensus: 2,
+        ..Default::default()
+    };
+    assert!(!announce_topic(&db, &announced("squatter", squatted.clone())).unwrap());
+    assert_eq!(get_topic(&db, "topicA").unwrap().creator, "creator");
+    assert!(announce_topic(&db, &announced("creator", squatted)).unwrap());
+    assert_eq!(get_topic_policy(&db, "topicA").members_for_consensus, 2);
+}
+
+#[tokio::test]
+async fn test_topic_registry_keeps_every_topic() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    save_topic(&db, &Topic::new("topicA", "first")).await.unwrap();
+    save_topic(&db, &Topic::new("topicB", "second")).await.unwrap();
+    let announced = |name: &str| Topic {
+        creator: "peer1".to_string(),
+        subscribed: false,
+        ..Topic::new(name, "announced")
+    };
+    assert!(add_known_topic(&db, &announced("topicC")).unwrap());
+    assert!(!add_known_topic(&db, &announced("topicA")).unwrap());
+    assert_eq!(get_topic(&db, "topicA").unwrap().description, "first");
+
+    let mut names: Vec<String> = get_topics(&db).await.into_iter().map(|t| t.name).collect();
+    names.sort();
+    assert_eq!(names, vec!["topicA", "topicB", "topicC"]);
+
+    let known = get_topic(&db, "topicC").unwrap();
+    assert!(!known.subscribed);
+    assert_eq!(known.creator, "peer1");
+    save_topic(&db, &Topic::new("topicC", "joined")).await.unwrap();
+    let joined = get_topic(&db, "topicC").unwrap();
+    assert!(joined.subscribed);
+    assert_eq!(joined.creator, "peer1");
+    assert_eq!(joined.created_at, known.created_at);
+
+    assert!(set_topic_subscribed(&db, "topicA", false).unwrap());
+    assert!(!get_topic(&db, "topicA").unwrap().subscribed);
+    assert!(!set_topic_subscribed(&db, "unknown", false).unwrap());
+}
+
+#[test]
+fn test_legacy_single_topic_is_migrated() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().to_str().unwrap();
+    {
+        let db = sled::open(path).unwrap();
+        db.insert("topics/", br#"{"name":"topicA","description":"old"}"#.to_vec())
+            .unwrap();
+        db.flush().unwrap();
+    }
+    let db = init_db(path).unwrap();
+    assert!(db.get("topics/").unwrap().is_none());
+    let topic = get_topic(&db, "topicA").unwrap();
+    assert_eq!(topic.description, "old");
+    assert!(topic.subscribed);
+}
+
+#[test]
+fn test_get_votes_reads_bare_and_graded_ballots() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    let id_votation = "vote_status/topicA:1:pending:1";
+
+    // ballots stored before they had a confidence
+    db.insert(
+        format!("election/vote/{id_votation}/1/peer1"),
+        serde_json::to_vec(&("peer1", Vote::Yes)).unwrap(),
+    )
+    .unwrap();
+    let graded = Ballot::new(Vote::Abstain, 0.4, Some("not my field".to_string()));
+    add_vote(&db, id_votation, 1, "peer2", &graded).unwrap();
+
+    let votes = get_votes(&db, id_votation, 1);
+    assert_eq!(votes.len(), 2);
+    assert!(votes.contains(&("peer1".to_string(), Ballot::from(Vote::Yes))));
+    assert!(votes.contains(&("peer2".to_string(), graded)));
+}
+
+#[test]
+fn test_first_commitment_wins() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    let id_votation = "vote_status/topicA:1:pending:1";
+
+    assert!(add_commitment(&db, id_votation, "peer1", "first").unwrap());
+    assert!(!add_commitment(&db, id_votation, "peer1", "second").unwrap());
+    assert!(add_commitment(&db, id_votation, "peer2", "other").unwrap());
+
+    assert_eq!(get_commitment(&db, id_votation, "peer1"), Some("first".to_string()));
+    let mut committed = get_committed_voters(&db, id_votation);
+    committed.sort();
+    assert_eq!(committed, vec!["peer1".to_string(), "peer2".to_string()]);
+}
+
+#[test]
+fn test_appeal_supersedes_previous_decision() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    let first = create_key_for_voting_db("content", "topicA", "pending", 1);
+    let appeal = appeal_key(&first);
+    assert_eq!(votation_level(&appeal), 2);
+
+    include_new_validated_content(&db, &DataContent::new(first.clone(), "content".into(), false))
+        .unwrap();
+    include_new_validated_content(&db, &DataContent::new(appeal.clone(), "content".into(), true))
+        .unwrap();
+
+    let contents = get_contents(&db);
+    assert_eq!(contents.len(), 1);
+    assert_eq!(contents[0].id_votation, appeal);
+    assert_eq!(contents[0].approved, StateContent::Approved);
+    let history = get_content_history(&db, &appeal);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].approved, StateContent::Rejected);
+}
+
+#[test]
+fn test_plain_string_content_still_decodes() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    db.insert(
+        "content/vote_01",
+        br#"{"id_votation":"vote_01","content":"important_content","approved":"Approved"}"#.to_vec(),
+    )
+    .unwrap();
+
+    let contents = get_contents(&db);
+    assert_eq!(contents.len(), 1);
+    assert_eq!(contents[0].content.kind, models::db::ContentKind::Text);
+    assert_eq!(contents[0].content.text, "important_content");
+    assert!(contents[0].decided_at.is_none());
+    assert!(contents[0].outcome.is_none());
+}
+
+#[test]
+fn test_link_content_keeps_metadata_and_outcome() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    let mut content = models::db::ContentPayload::link(
+        "https://example.org/post",
+        Some("A post".to_string()),
+        vec!["news".to_string()],
+        "worth reading",
+    );
+    content.publisher = "peer1".to_string();
+    content.topic = "topicA".to_string();
+    assert_eq!(content.key_source(), "https://example.org/post");
+
+    let votes = vec![("peer2".to_string(), models::messages::Vote::Yes.into())];
+    let tally = crate::tally::tally(&votes, &[], &models::db::TallyMode::Simple, 0.5);
+    include_new_validated_content(&db, &DataContent::decided("vote_01".to_string(), content.clone(), &tally))
+        .unwrap();
+
+    let stored = &get_contents(&db)[0];
+    assert_eq!(stored.content, content);
+    assert_eq!(stored.approved, StateContent::Approved);
+    assert!(stored.decided_at.is_some());
+    let outcome = stored.outcome.as_ref().unwrap();
+    assert_eq!(outcome.ballots, 1);
+    assert_eq!(outcome.approve_ratio, 1.0);
+}
+
+#[test]
+fn test_old_nonces_are_pruned() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    let old = Utc::now() - crate::MAX_MESSAGE_AGE - chrono::Duration::minutes(1);
+    db.insert("seen_nonce/peer1/1", serde_json::to_vec(&old).unwrap())
+        .unwrap();
+
+    let now = Utc::now();
+    assert!(register_nonce(&db, "peer1", 2, &now).unwrap());
+    assert!(!register_nonce(&db, "peer1", 2, &now).unwrap());
+    assert!(db.get("seen_nonce/peer1/1").unwrap().is_none());
+    assert!(db.get("seen_nonce/peer1/2").unwrap().is_some());
+}
@@ -1,29 +1,875 @@
-use crate::models::db::DataContent;
-use crate::models::messages::{ContentMessage, Vote};
+use crate::models::db::{ContentPayload, DataContent, StateContent, Tally, Topic, Votation};
+use crate::models::messages::{
+    ballot_commitment, Ballot, ContentMessage, HistoryPage, HistoryRequest, SignedMessage,
+    SummaryRequest, SummaryResponse,
+};
+use crate::private::{accept_topic_key, may_read, open_sealed, seal_for_topic};
+use crate::summary::{
+    bucket_ids, differing_buckets, topic_leaves, topic_summary, withdrawn_leaf, WITHDRAWN_LEAF,
+};
+use crate::tally::tally;
 use crate::{
-    db, models, MessageHandler, DEFAULT_REPUTATION,
-    EXPIRY_DURATION_IN_DAYS, INCR_REPUTATION, THRESHOLD_APPROVE,
+    db, models, MessageHandler, DEFAULT_REPUTATION, HISTORY_PAGE_SIZE, MAX_MESSAGE_AGE,
 };
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use libp2p::identity::Keypair;
 use libp2p::PeerId;
 use sled::Db;
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Serves the direct requests of the peers, `None` refuses the request.
+pub type RequestHandler = Box<dyn FnMut(PeerId, &[u8]) -> Option<Vec<u8>> + Send>;
+
+/* shared by the clones of a handler, the node owns one and the application another */
+#[derive(Clone, Default)]
+struct RequestSlot(Arc<Mutex<Option<RequestHandler>>>);
+
+impl fmt::Debug for RequestSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let registered = self.0.lock().map(|slot| slot.is_some()).unwrap_or(false);
+        f.debug_tuple("RequestSlot").field(&registered).finish()
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ValidatorHandler {
     peer_id: PeerId,
+    keypair: Keypair,
     db: Arc<Db>,
+    request_handler: RequestSlot,
 }
 
 impl ValidatorHandler {
-    pub fn new(peer_id: PeerId, db: Arc<Db>) -> Self {
-        ValidatorHandler { peer_id, db }
+    pub fn new(keypair: Keypair, db: Arc<Db>) -> Self {
+        ValidatorHandler {
+            peer_id: keypair.public().to_peer_id(),
+            keypair,
+            db,
+            request_handler: RequestSlot::default(),
+        }
+    }
+
+    /// Serves the direct requests with `handler`, replacing the previous one.
+    /// It also applies to the clones of this handler, as the one run by the node.
+    pub fn set_request_handler(&self, handler: RequestHandler) {
+        match self.request_handler.0.lock() {
+            Ok(mut slot) => *slot = Some(handler),
+            Err(e) => log::error!("Failed to register request handler: {}", e),
+        }
+    }
+
+    /* wraps a response in our own signed envelope */
+    fn sign(&self, message: ContentMessage) -> Option<Vec<u8>> {
+        SignedMessage::sign(message, &self.keypair)
+            .and_then(|signed| signed.to_bytes())
+            .map_err(|e| log::error!("Failed to sign response: {e}"))
+            .ok()
+    }
+
+    /* signs the result of a votation I lead, kept in case it is appealed */
+    fn sign_result(&self, id_votation: &str, message: ContentMessage) -> Option<Vec<u8>> {
+        let envelope = SignedMessage::sign(message, &self.keypair)
+            .map_err(|e| log::error!("Failed to sign result: {e}"))
+            .ok()?;
+        db::save_result(&self.db, id_votation, &envelope).ok()?;
+        envelope.to_bytes().ok()
+    }
+
+    /* checks signature, age and replays of an incoming envelope */
+    fn authenticate(&self, envelope: &SignedMessage) -> anyhow::Result<()> {
+        envelope.verify()?;
+        let age = Utc::now() - envelope.timestamp;
+        if age > MAX_MESSAGE_AGE || -age > MAX_MESSAGE_AGE {
+            return Err(anyhow::anyhow!("message timestamp out of range age={age}"));
+        }
+        if !db::register_nonce(&self.db, &envelope.signer, envelope.nonce, &envelope.timestamp)? {
+            return Err(anyhow::anyhow!("replayed nonce {}", envelope.nonce));
+        }
+        Ok(())
     }
 }
 
-impl MessageHandler for ValidatorHandler {
-    fn handle_message(&mut self, source_peer: PeerId, data: &[u8], topic: &str) -> Option<Vec<u8>> {
+fn closed_status(approved: bool) -> String {
+    if approved {
+        "approved".to_string()
+    } else {
+        "rejected".to_string()
+    }
+}
+
+/// Moves a votation to the next `round`, led by the next juror in line, and
+/// penalizes the silent leader. Callers only do it once the round timed out
+/// locally or most of the jury gave up on it. If I already voted, my ballot is
+/// stored when I lead the new round, otherwise it is returned to be sent to the
+/// new leader. Leading it, the ballots held while the round was not over are counted.
+pub fn enter_round(
+    db: &Db,
+    keypair: &Keypair,
+    votation: &Votation,
+    round: u32,
+) -> anyhow::Result<(Votation, Option<ContentMessage>)> {
+    let my_peer_id = keypair.public().to_peer_id().to_string();
+    let my_peer_id = my_peer_id.as_str();
+    let mut next = votation.clone();
+    let Some(leader) = votation.leader_for_round(round) else {
+        return Err(anyhow::anyhow!("votation {} has no jury", votation.id_votation));
+    };
+    log::info!(
+        "⏱ Leader {} did not close votation={} round={}, next leader={}",
+        votation.leader_id,
+        votation.id_votation,
+        votation.round,
+        leader
+    );
+
+    if votation.leader_id != my_peer_id {
+        let policy = db::get_topic_policy(db, &votation.topic);
+        let penalty = vec![(votation.leader_id.clone(), -policy.incr_reputation)];
+        db::update_reputations(db, &votation.topic, &penalty, DEFAULT_REPUTATION)?;
+    }
+
+    next.round = round;
+    next.leader_id = leader.to_string();
+    next.my_role = if leader == my_peer_id {
+        "role_leader".to_string()
+    } else {
+        "role_voter".to_string()
+    };
+    // voted or not, I give up on the new leader as well if it stays silent
+    next.round_deadline = (leader != my_peer_id)
+        .then(|| Utc::now() + Duration::seconds(next.ttl_secs as i64));
+
+    if leader == my_peer_id {
+        admit_held_ballots(db, &next);
+    }
+
+    let mut resend = None;
+    // with commit-reveal the ballot only travels once the juror revealed it
+    let ballot = next
+        .my_ballot
+        .clone()
+        .filter(|_| next.commit_deadline.is_none() || next.revealed);
+    if let Some(ballot) = ballot {
+        let vote = ContentMessage::ResultVote {
+            id_votation: next.id_votation.clone(),
+            result: ballot.clone(),
+            round,
+            salt: next.my_salt.clone(),
+        };
+        if leader == my_peer_id {
+            // signed as well, it is published with the result
+            let envelope = SignedMessage::sign(vote, keypair)?;
+            db::add_vote(db, &next.id_votation, round, my_peer_id, &ballot)?;
+            db::add_signed_vote(db, &next.id_votation, round, my_peer_id, &envelope)?;
+        } else {
+            resend = Some(vote);
+        }
+    }
+    db::save_status_vote(db, &next)?;
+    Ok((next, resend))
+}
+
+/* ballots sent for a round before I entered it, they are counted once I lead it */
+fn admit_held_ballots(db: &Db, votation: &Votation) {
+    for envelope in db::get_signed_votes(db, &votation.id_votation, votation.round) {
+        let ContentMessage::ResultVote { result, salt, .. } = &envelope.payload else {
+            continue;
+        };
+        let signer = envelope.signer.as_str();
+        if votation.commit_deadline.is_some()
+            && let Err(e) = check_reveal(db, &votation.id_votation, signer, result, salt.as_deref())
+        {
+            log::warn!("⛔ Discarding held reveal from {}: {}", signer, e);
+            continue;
+        }
+        if let Err(e) = db::add_vote(db, &votation.id_votation, votation.round, signer, result) {
+            log::warn!("Failed to count the held vote of {}: {}", signer, e);
+        }
+    }
+}
+
+/// Closes a votation that ran out of time. As leader, the jurors missing in the
+/// current round are penalized and returned to be reported to the others.
+pub fn expire_votation(
+    db: &Db,
+    my_peer_id: &str,
+    votation: &Votation,
+) -> anyhow::Result<Option<ContentMessage>> {
+    let mut expired = votation.clone();
+    expired.status = "expired".to_string();
+    expired.round_deadline = None;
+    db::save_status_vote(db, &expired)?;
+
+    if votation.leader_id != my_peer_id {
+        log::debug!("⛔ Votation={} expired", votation.id_votation);
+        return Ok(None);
+    }
+
+    let voted: HashSet<String> = db::get_votes(db, &votation.id_votation, votation.round)
+        .into_iter()
+        .map(|(peer_id, _)| peer_id)
+        .collect();
+    let missing_voters: Vec<String> = votation
+        .votes_id
+        .iter()
+        .map(|(peer_id, _)| peer_id.clone())
+        .filter(|peer_id| !voted.contains(peer_id) && peer_id != my_peer_id)
+        .collect();
+    log::debug!(
+        "⛔ Votation={} expired, decreasing reputation of {:?}",
+        votation.id_votation,
+        missing_voters
+    );
+    let policy = db::get_topic_policy(db, &votation.topic);
+    let penalties = missing_voters
+        .iter()
+        .map(|peer_id| (peer_id.clone(), -policy.incr_reputation))
+        .collect::<Vec<(String, f32)>>();
+    db::update_reputations(db, &votation.topic, &penalties, DEFAULT_REPUTATION)?;
+
+    Ok(Some(ContentMessage::VotationExpired {
+        id_votation: votation.id_votation.clone(),
+        round: votation.round,
+        missing_voters,
+    }))
+}
+
+/* jurors whose ballot is needed to close, with commit-reveal only the ones that committed */
+fn expected_voters(db: &Db, votation: &Votation) -> HashSet<String> {
+    let jury = votation.votes_id.iter().map(|(peer_id, _)| peer_id.clone());
+    if votation.commit_deadline.is_none() {
+        return jury.collect();
+    }
+    let committed: HashSet<String> = db::get_committed_voters(db, &votation.id_votation)
+        .into_iter()
+        .collect();
+    jury.filter(|peer_id| committed.contains(peer_id)).collect()
+}
+
+/* a revealed ballot must match the commitment the juror made in time */
+fn check_reveal(
+    db: &Db,
+    id_votation: &str,
+    peer_id: &str,
+    ballot: &Ballot,
+    salt: Option<&str>,
+) -> anyhow::Result<()> {
+    let salt = salt.ok_or_else(|| anyhow::anyhow!("ballot revealed without its salt"))?;
+    let commitment = db::get_commitment(db, id_votation, peer_id)
+        .ok_or_else(|| anyhow::anyhow!("no commitment before the commit deadline"))?;
+    if ballot_commitment(id_votation, peer_id, ballot, salt)? != commitment {
+        return Err(anyhow::anyhow!("revealed ballot does not match its commitment"));
+    }
+    Ok(())
+}
+
+/// Counts the ballots of the current round, closes the votation and includes
+/// its content. Returns the result the leader announces to the jury.
+pub fn finalize_votation(
+    db: &Db,
+    votation: &Votation,
+    votes: Vec<(String, Ballot)>,
+) -> anyhow::Result<ContentMessage> {
+    let mut votation = votation.clone();
+    let policy = db::get_topic_policy(db, &votation.topic);
+    /* update reputations */
+    log::debug!("Updating reputations for all votes");
+    let reputations = votes
+        .iter()
+        .map(|(peer_id, _)| (peer_id.clone(), policy.incr_reputation))
+        .collect::<Vec<(String, f32)>>();
+    db::update_reputations(db, &votation.topic, &reputations, DEFAULT_REPUTATION)?;
+
+    // start  process to approve with all the votation
+    let result = tally(
+        &votes,
+        &votation.weights,
+        &votation.tally_mode,
+        policy.threshold_approve,
+    );
+    log::debug!("Tally for votation={}: {:?}", votation.id_votation, result);
+    let approved = result.approved;
+
+    // send the result
+    // the signed ballots let the others recount the result
+    let counted: HashSet<&str> = votes.iter().map(|(peer_id, _)| peer_id.as_str()).collect();
+    let ballots = db::get_signed_votes(db, &votation.id_votation, votation.round)
+        .into_iter()
+        .filter(|envelope| counted.contains(envelope.signer.as_str()))
+        .collect();
+    let data = ContentMessage::IncludeNewValidatedContent {
+        id_votation: votation.id_votation.clone(),
+        content: votation.content.clone(),
+        approved,
+        round: votation.round,
+        ballots,
+        threshold_approve: policy.threshold_approve,
+        request: db::get_vote_request(db, &votation.id_votation).map(Box::new),
+    };
+
+    // close it locally, jurors do it when they receive the result
+    votation.status = closed_status(approved);
+    votation.round_deadline = None;
+    apply_decision(db, &votation.id_votation, &votation.content, &result)?;
+    record_tally(&mut votation, result);
+    db::save_status_vote(db, &votation)?;
+
+    // update the reputation for voters
+    let peer_ids = votation
+        .votes_id
+        .iter()
+        .filter(|(_, score)| score.is_some())
+        .map(|(peer_id, _)| (peer_id.clone(), policy.incr_reputation))
+        .collect::<Vec<(String, f32)>>();
+    db::update_reputations(db, &votation.topic, &peer_ids, DEFAULT_REPUTATION)?;
+    Ok(data)
+}
+
+/* stores the outcome of a votation, a removal votation decides on the content it flagged */
+fn apply_decision(
+    db: &Db,
+    id_votation: &str,
+    content: &ContentPayload,
+    tally: &Tally,
+) -> anyhow::Result<()> {
+    match db::removal_target(id_votation) {
+        Some(target) if tally.approved => {
+            log::info!("🗑 Content of votation={} removed", target);
+            withdraw(db, target, content, StateContent::Removed)?;
+        }
+        Some(_) => {}
+        None => {
+            let data_content = DataContent::decided(id_votation.to_string(), content.clone(), tally);
+            db::include_new_validated_content(db, &data_content)?;
+        }
+    }
+    Ok(())
+}
+
+/* content never seen here is kept as withdrawn, so that the summaries of the topic agree */
+fn withdraw(
+    db: &Db,
+    id_votation: &str,
+    content: &ContentPayload,
+    state: StateContent,
+) -> anyhow::Result<()> {
+    if !db::withdraw_content(db, id_votation, state.clone())? {
+        let withdrawn = DataContent {
+            approved: state,
+            ..DataContent::new(id_votation.to_string(), content.withdrawn(), false)
+        };
+        db::include_new_validated_content(db, &withdrawn)?;
+    }
+    Ok(())
+}
+
+/// Withdraws the content named by a retraction of its publisher, the signed
+/// retraction is kept to prove it to the peers that still hold the content.
+pub fn import_retraction(db: &Db, topic: &str, envelope: &SignedMessage) -> anyhow::Result<()> {
+    envelope.verify()?;
+    verify_retraction(&envelope.signer, &envelope.payload)?;
+    let ContentMessage::RetractContent {
+        id_votation,
+        request,
+    } = &envelope.payload
+    else {
+        return Err(anyhow::anyhow!("Not a RetractContent"));
+    };
+    if db::votation_topic(id_votation) != Some(topic) {
+        return Err(anyhow::anyhow!("votation {id_votation} is not part of topic {topic}"));
+    }
+    let ContentMessage::VoteLeaderRequest { content, .. } = request.as_ref() else {
+        return Err(anyhow::anyhow!("Not a VoteLeaderRequest"));
+    };
+    withdraw(db, id_votation, content, StateContent::Retracted)?;
+    db::save_withdrawal(db, id_votation, envelope)
+}
+
+/// Checks the metadata the sender filled in the payload of a votation. New content
+/// is published by `publisher` on `topic`, before `sent_at` and at most one wait for
+/// the jury earlier. Appeals and removals carry the metadata of the first votation
+/// of the content, when it is known here.
+pub fn verify_payload(
+    db: &Db,
+    topic: &str,
+    id_votation: &str,
+    content: &ContentPayload,
+    publisher: &str,
+    sent_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    if content.topic != topic {
+        return Err(anyhow::anyhow!("content of topic {:?} sent to {topic}", content.topic));
+    }
+    let target = db::removal_target(id_votation);
+    if target.is_some() || db::votation_level(id_votation) > 1 {
+        let original = db::get_content_history(db, target.unwrap_or(id_votation))
+            .into_iter()
+            .next()
+            .map(|first| first.content);
+        return match original {
+            Some(original)
+                if original.publisher != content.publisher
+                    || original.submitted_at != content.submitted_at =>
+            {
+                Err(anyhow::anyhow!("content of {id_votation} differs from its first votation"))
+            }
+            _ => Ok(()),
+        };
+    }
+    if content.publisher != publisher {
+        return Err(anyhow::anyhow!(
+            "content published by {:?} sent by {publisher}",
+            content.publisher
+        ));
+    }
+    let policy = db::get_topic_policy(db, topic);
+    let wait = Duration::seconds(policy.timeout_secs as i64) + MAX_MESSAGE_AGE;
+    match content.submitted_at {
+        Some(submitted_at) if submitted_at <= sent_at && sent_at - submitted_at <= wait => Ok(()),
+        submitted_at => Err(anyhow::anyhow!(
+            "content submitted at {submitted_at:?} sent at {sent_at}"
+        )),
+    }
+}
+
+/// Checks that a retraction comes from the publisher of the first votation of the content.
+pub fn verify_retraction(signer: &str, message: &ContentMessage) -> anyhow::Result<()> {
+    let ContentMessage::RetractContent {
+        id_votation,
+        request,
+    } = message
+    else {
+        return Err(anyhow::anyhow!("Not a RetractContent"));
+    };
+    request.verify_vote_leader_request()?;
+    let ContentMessage::VoteLeaderRequest {
+        id_votation: request_id,
+        publisher_peer_id,
+        ..
+    } = request.as_ref()
+    else {
+        return Err(anyhow::anyhow!("Not a VoteLeaderRequest"));
+    };
+    if *request_id != db::first_level_key(id_votation) {
+        return Err(anyhow::anyhow!("request of {request_id} is not the first one of the content"));
+    }
+    if publisher_peer_id != signer {
+        return Err(anyhow::anyhow!("{signer} is not the publisher of the content"));
+    }
+    Ok(())
+}
+
+/* keep the tally and the weight each juror ballot counted with */
+fn record_tally(votation: &mut Votation, result: Tally) {
+    votation.votes_id = votation
+        .votes_id
+        .iter()
+        .map(|(peer_id, _)| {
+            let score = result
+                .ballots
+                .iter()
+                .find(|(id, _, _)| id == peer_id)
+                .map(|(_, _, weight)| *weight);
+            (peer_id.clone(), score)
+        })
+        .collect();
+    votation.tally = Some(result);
+}
+
+/// Recounts the signed ballots of a leader result against the jury of the
+/// request signed by the publisher. Returns the tally if the outcome matches.
+pub fn recount_finalization(
+    db: &Db,
+    votation: &Votation,
+    message: &ContentMessage,
+) -> anyhow::Result<Tally> {
+    recount(db, votation, message, true)
+}
+
+/* with `check_reveals` the revealed ballots must match the commitments I received */
+fn recount(
+    db: &Db,
+    votation: &Votation,
+    message: &ContentMessage,
+    check_reveals: bool,
+) -> anyhow::Result<Tally> {
+    let ContentMessage::IncludeNewValidatedContent {
+        id_votation,
+        content,
+        approved,
+        round,
+        ballots,
+        threshold_approve,
+        request,
+    } = message
+    else {
+        return Err(anyhow::anyhow!("Not an IncludeNewValidatedContent"));
+    };
+    let request = request
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("result without the request of the votation"))?;
+    request.verify_vote_leader_request()?;
+    let ContentMessage::VoteLeaderRequest {
+        id_votation: request_id,
+        content: request_content,
+        voters_peer_id,
+        tally_mode,
+        commit_deadline,
+        ..
+    } = request
+    else {
+        return Err(anyhow::anyhow!("Not a VoteLeaderRequest"));
+    };
+    let jury: Vec<&String> = votation.votes_id.iter().map(|(peer_id, _)| peer_id).collect();
+    if request_id != id_votation
+        || request_content != content
+        || voters_peer_id.iter().collect::<Vec<_>>() != jury
+    {
+        return Err(anyhow::anyhow!("result does not belong to the votation request"));
+    }
+    let policy = db::get_topic_policy(db, &votation.topic);
+    if *threshold_approve != policy.threshold_approve {
+        return Err(anyhow::anyhow!(
+            "threshold {threshold_approve} differs from the topic one {}",
+            policy.threshold_approve
+        ));
+    }
+
+    let mut votes: Vec<(String, Ballot)> = Vec::new();
+    for envelope in ballots {
+        envelope.verify()?;
+        let ContentMessage::ResultVote {
+            id_votation: ballot_id,
+            result,
+            round: ballot_round,
+            salt,
+        } = &envelope.payload
+        else {
+            return Err(anyhow::anyhow!("ballot from {} is not a vote", envelope.signer));
+        };
+        if ballot_id != id_votation || ballot_round != round {
+            return Err(anyhow::anyhow!("ballot from {} for another votation", envelope.signer));
+        }
+        if !voters_peer_id.contains(&envelope.signer) {
+            return Err(anyhow::anyhow!("ballot from {} outside the jury", envelope.signer));
+        }
+        if votes.iter().any(|(peer_id, _)| *peer_id == envelope.signer) {
+            return Err(anyhow::anyhow!("duplicated ballot from {}", envelope.signer));
+        }
+        if check_reveals && commit_deadline.is_some() {
+            check_reveal(db, id_votation, &envelope.signer, result, salt.as_deref())?;
+        }
+        votes.push((envelope.signer.clone(), result.clone()));
+    }
+    if votes.is_empty() {
+        return Err(anyhow::anyhow!("result without ballots"));
+    }
+    // the leader can not leave out the ballots it dislikes
+    let required: Vec<&String> = if commit_deadline.is_some() {
+        let committed = db::get_committed_voters(db, id_votation);
+        voters_peer_id
+            .iter()
+            .filter(|peer_id| committed.contains(*peer_id))
+            .collect()
+    } else {
+        voters_peer_id.iter().collect()
+    };
+    let missing: Vec<&String> = required
+        .into_iter()
+        .filter(|peer_id| !votes.iter().any(|(voter, _)| voter == *peer_id))
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!("result without the ballots of {missing:?}"));
+    }
+
+    // weighed as snapshotted when the jury was drawn
+    let result = tally(
+        &votes,
+        &votation.weights,
+        tally_mode,
+        *threshold_approve,
+    );
+    if result.approved != *approved {
+        return Err(anyhow::anyhow!(
+            "recounted outcome approved={} differs from the announced one",
+            result.approved
+        ));
+    }
+    Ok(result)
+}
+
+/* anybody can sign a request naming its own keys as jury, the jury must be
+known here: the request was received live on the topic, or every juror has
+the reputation the topic asks for */
+fn verify_jury(db: &Db, topic: &str, request: &ContentMessage) -> anyhow::Result<()> {
+    let ContentMessage::VoteLeaderRequest {
+        id_votation,
+        voters_peer_id,
+        signature,
+        ..
+    } = request
+    else {
+        return Err(anyhow::anyhow!("Not a VoteLeaderRequest"));
+    };
+    if let Some(ContentMessage::VoteLeaderRequest {
+        signature: seen_signature,
+        ..
+    }) = db::get_vote_request(db, id_votation)
+        && seen_signature == *signature
+    {
+        return Ok(());
+    }
+    let policy = db::get_topic_policy(db, topic);
+    for peer_id in voters_peer_id {
+        match db::get_reputation(db, topic, peer_id) {
+            Some(reputation) if reputation >= policy.min_reputation_threshold => {}
+            _ => return Err(anyhow::anyhow!("juror {peer_id} is unknown in topic {topic}")),
+        }
+    }
+    Ok(())
+}
+
+/// Checks a result signed by the leader of a votation I did not take part in:
+/// the votation is rebuilt from the request the result carries and its ballots
+/// are recounted. The jury must be known here, see `verify_jury`. The
+/// commitments of that jury are unknown, so reveals are not checked.
+pub fn verify_result(db: &Db, topic: &str, result: &SignedMessage) -> anyhow::Result<Tally> {
+    result.verify()?;
+    let ContentMessage::IncludeNewValidatedContent {
+        id_votation,
+        content,
+        round,
+        request,
+        ..
+    } = &result.payload
+    else {
+        return Err(anyhow::anyhow!("Not an IncludeNewValidatedContent"));
+    };
+    let Some(request) = request.as_deref() else {
+        return Err(anyhow::anyhow!("result without the request of the votation"));
+    };
+    let ContentMessage::VoteLeaderRequest {
+        voters_peer_id,
+        leader_peer_id,
+        weights,
+        ..
+    } = request
+    else {
+        return Err(anyhow::anyhow!("Not a VoteLeaderRequest"));
+    };
+    verify_jury(db, topic, request)?;
+    let mut votation = Votation::new(
+        id_votation.clone(),
+        content.clone(),
+        "pending".to_string(),
+        leader_peer_id.clone(),
+        "role_voter".to_string(),
+        voters_peer_id.iter().map(|id| (id.clone(), None)).collect(),
+    );
+    votation.topic = topic.to_string();
+    votation.weights = weights.clone();
+    if votation.leader_for_round(*round) != Some(result.signer.as_str()) {
+        return Err(anyhow::anyhow!("result not signed by the leader of round {round}"));
+    }
+    recount(db, &votation, &result.payload, false)
+}
+
+/// Stores the result of a votation I did not take part in once its ballots recount.
+/// Returns false if the result was already known.
+pub fn import_result(db: &Db, topic: &str, result: &SignedMessage) -> anyhow::Result<bool> {
+    let ContentMessage::IncludeNewValidatedContent {
+        id_votation,
+        content,
+        ..
+    } = &result.payload
+    else {
+        return Err(anyhow::anyhow!("Not an IncludeNewValidatedContent"));
+    };
+    if db::votation_topic(id_votation) != Some(topic) {
+        return Err(anyhow::anyhow!("votation {id_votation} is not part of topic {topic}"));
+    }
+    if db::get_result(db, id_votation).is_some() {
+        return Ok(false);
+    }
+    let recount = verify_result(db, topic, result)?;
+    apply_decision(db, id_votation, content, &recount)?;
+    db::save_result(db, id_votation, result)?;
+    Ok(true)
+}
+
+/// Page of the results I logged for a topic, at most `HISTORY_PAGE_SIZE` of them.
+pub fn history_page(db: &Db, request: &HistoryRequest) -> HistoryPage {
+    let limit = request.limit.clamp(1, HISTORY_PAGE_SIZE);
+    let mut history = db::get_history(db, &request.topic, request.cursor, limit + 1);
+    // the extra result only tells there is a next page
+    let has_more = history.len() > limit;
+    history.truncate(limit);
+    HistoryPage {
+        topic: request.topic.clone(),
+        cursor: history.last().map(|(position, _)| *position).or(request.cursor),
+        results: history.into_iter().map(|(_, result)| result).collect(),
+        has_more,
+    }
+}
+
+/// Answers a step of the walk of a topic summary, the results sent are at most `HISTORY_PAGE_SIZE`.
+pub fn summary_answer(db: &Db, request: &SummaryRequest) -> SummaryResponse {
+    match request {
+        SummaryRequest::Buckets { topic, buckets } => SummaryResponse::Buckets {
+            topic: topic.clone(),
+            ids: bucket_ids(db, topic, buckets, HISTORY_PAGE_SIZE),
+        },
+        // withdrawn content is answered with the proof of its withdrawal, never with its text
+        SummaryRequest::Results { topic, ids } => SummaryResponse::Results {
+            topic: topic.clone(),
+            results: ids
+                .iter()
+                .take(HISTORY_PAGE_SIZE)
+                .filter_map(|leaf| match leaf.strip_prefix(WITHDRAWN_LEAF) {
+                    Some(content_key) => db::get_withdrawal(db, content_key),
+                    None if db::is_withdrawn(db, leaf) => None,
+                    None => db::get_result(db, leaf),
+                })
+                .filter(|result| {
+                    let id_votation = match &result.payload {
+                        ContentMessage::IncludeNewValidatedContent { id_votation, .. }
+                        | ContentMessage::RetractContent { id_votation, .. } => id_votation,
+                        _ => return false,
+                    };
+                    db::votation_topic(id_votation) == Some(topic.as_str())
+                })
+                .collect(),
+        },
+    }
+}
+
+/// Checks the candidates the publisher drew the jury from: the request must
+/// carry the `InterestedResponse` each one signed, and each one must have the
+/// reputation I hold for it, give or take the last update. The weights of the
+/// jury are the reputations of its candidates, so they are checked here once and kept.
+pub fn verify_candidates(db: &Db, topic: &str, request: &ContentMessage) -> anyhow::Result<()> {
+    let ContentMessage::VoteLeaderRequest {
+        id_votation,
+        candidates,
+        interests,
+        ..
+    } = request
+    else {
+        return Err(anyhow::anyhow!("Not a VoteLeaderRequest"));
+    };
+    let policy = db::get_topic_policy(db, topic);
+    for (peer_id, reputation) in candidates {
+        // gossip gives no order, the answer may not have reached me yet
+        let interested = interests.iter().any(|interest| {
+            interest.signer == *peer_id
+                && matches!(
+                    &interest.payload,
+                    ContentMessage::InterestedResponse { id_votation: answered }
+                        if answered == id_votation
+                )
+                && interest.verify().is_ok()
+        });
+        if !interested {
+            return Err(anyhow::anyhow!("candidate {peer_id} was never interested"));
+        }
+        let known = db::get_reputation(db, topic, peer_id).unwrap_or(DEFAULT_REPUTATION);
+        if (reputation - known).abs() > policy.incr_reputation {
+            return Err(anyhow::anyhow!(
+                "candidate {peer_id} has reputation {known}, not {reputation}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the appeal carried by a `VoteLeaderRequest`: the contested result must
+/// recount, the new jury must be larger and the peer appealing must be the
+/// previous publisher or have the reputation the topic asks for.
+pub fn verify_appeal(db: &Db, topic: &str, request: &ContentMessage) -> anyhow::Result<()> {
+    let ContentMessage::VoteLeaderRequest {
+        id_votation,
+        content,
+        publisher_peer_id,
+        voters_peer_id,
+        appeal,
+        ..
+    } = request
+    else {
+        return Err(anyhow::anyhow!("Not a VoteLeaderRequest"));
+    };
+    let Some(previous) = appeal else {
+        return Ok(());
+    };
+    let ContentMessage::IncludeNewValidatedContent {
+        id_votation: previous_id,
+        content: previous_content,
+        request: previous_request,
+        ..
+    } = &previous.payload
+    else {
+        return Err(anyhow::anyhow!("appeal does not carry a votation result"));
+    };
+    if db::appeal_key(previous_id) != *id_votation || previous_content != content {
+        return Err(anyhow::anyhow!("appeal does not follow votation {previous_id}"));
+    }
+    let policy = db::get_topic_policy(db, topic);
+    if db::votation_level(id_votation) > policy.max_appeals + 1 {
+        return Err(anyhow::anyhow!("no more appeals allowed for {previous_id}"));
+    }
+    verify_result(db, topic, previous)?;
+    let Some(ContentMessage::VoteLeaderRequest {
+        publisher_peer_id: previous_publisher,
+        voters_peer_id: previous_voters,
+        ..
+    }) = previous_request.as_deref()
+    else {
+        return Err(anyhow::anyhow!("appealed result without its request"));
+    };
+
+    if voters_peer_id.len() <= previous_voters.len() {
+        return Err(anyhow::anyhow!("appeal jury is not larger than the previous one"));
+    }
+    if publisher_peer_id != previous_publisher {
+        let reputation =
+            db::get_reputation(db, topic, publisher_peer_id).unwrap_or(DEFAULT_REPUTATION);
+        if reputation < policy.appeal_min_reputation {
+            return Err(anyhow::anyhow!(
+                "{publisher_peer_id} has not enough reputation to appeal, {reputation}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Closes a commit-reveal votation once the round ran out of time for reveals.
+/// Every juror that committed must have revealed, otherwise nothing is closed
+/// and the votation expires, reporting the jurors that did not reveal.
+pub fn close_reveal_window(
+    db: &Db,
+    votation: &Votation,
+) -> anyhow::Result<Option<ContentMessage>> {
+    let expected = expected_voters(db, votation);
+    let votes: Vec<(String, Ballot)> = db::get_votes(db, &votation.id_votation, votation.round)
+        .into_iter()
+        .filter(|(peer_id, _)| expected.contains(peer_id))
+        .collect();
+    // nothing to count or a reveal missing, the votation expires
+    if votes.is_empty() || votes.len() < expected.len() {
+        return Ok(None);
+    }
+    log::debug!(
+        "⏱ Closing votation={} with the reveals of {:?}",
+        votation.id_votation,
+        votes.iter().map(|(peer_id, _)| peer_id).collect::<Vec<_>>()
+    );
+    finalize_votation(db, votation, votes).map(Some)
+}
+
+impl ValidatorHandler {
+    /* a signed message in the clear, as received on a public topic or opened from a sealed one */
+    fn handle_plain(&mut self, source_peer: PeerId, data: &[u8], topic: &str) -> Option<Vec<u8>> {
         log::debug!(
             "{:?} - Received message from {}: {:?}",
             self.peer_id.clone(),
@@ -33,51 +879,155 @@ impl MessageHandler for ValidatorHandler {
 
         let db = &self.db;
 
-        if let Ok(res) = serde_json::from_slice(data) {
-            match res {
-                ContentMessage::RegisterTopic { topic } => {
-                    log::info!("New topic {:?}", topic);
+        if let Ok(envelope) = serde_json::from_slice::<SignedMessage>(data) {
+            if let Err(e) = self.authenticate(&envelope) {
+                log::warn!(
+                    "⛔ Discarding message signed by {} from {}: {}",
+                    envelope.signer,
+                    source_peer,
+                    e
+                );
+                return None;
+            }
+            let signer = envelope.signer.clone();
+            match envelope.payload.clone() {
+                ContentMessage::RegisterTopic {
+                    topic,
+                    description,
+                    creator,
+                    created_at,
+                    policy,
+                } => {
+                    if !creator.is_empty() && creator != signer {
+                        log::warn!(
+                            "⛔ Topic {:?} announced by {} on behalf of {}",
+                            topic,
+                            signer,
+                            creator
+                        );
+                        return None;
+                    }
+                    log::info!("New topic {:?} with policy {:?}", topic, policy);
+                    let announced = Topic {
+                        creator: signer.clone(),
+                        created_at,
+                        subscribed: false,
+                        policy,
+                        ..Topic::new(&topic, &description)
+                    };
+                    if !db::announce_topic(db, &announced).ok()? {
+                        log::warn!(
+                            "⛔ Topic {:?} announced by {} was created by another peer",
+                            topic,
+                            signer
+                        );
+                    }
                 }
                 ContentMessage::Interested {
                     content,
                     id_votation,
                 } => {
                     log::debug!("Received Interested message for content: {}", content);
-                    let str_response: String =
-                        serde_json::to_string(&ContentMessage::InterestedResponse {
-                            id_votation: id_votation.clone(),
-                        })
-                        .unwrap();
+                    if let Err(e) =
+                        verify_payload(db, topic, &id_votation, &content, &signer, envelope.timestamp)
+                    {
+                        log::warn!(
+                            "⛔ Discarding Interested for votation={} from {}: {}",
+                            id_votation,
+                            signer,
+                            e
+                        );
+                        return None;
+                    }
+                    let response = self.sign(ContentMessage::InterestedResponse {
+                        id_votation: id_votation.clone(),
+                    })?;
                     let data_content = DataContent::new(id_votation, content, false);
                     db::my_pending_content_to_validate(&db, &data_content).ok()?;
-                    return Some(str_response.into_bytes());
+                    return Some(response);
                 }
                 ContentMessage::InterestedResponse { id_votation } => {
                     // interested voters
                     log::debug!(
                         "Received response for votation: {} from {}",
                         id_votation,
-                        source_peer.to_string()
+                        signer
                     );
-                    db::store_voter(&db, &id_votation, source_peer.to_string().as_str(), topic)
+                    // the publisher hands the signed answers to the jury with its request
+                    db::add_interest(db, &id_votation, signer.as_str(), &envelope).ok()?;
+                    db::store_voter(db, &id_votation, signer.as_str(), topic)
                         .ok()?; /*
                     convert result to option
                     check if you have enough voters
                      */
                 }
-                ContentMessage::VoteLeaderRequest {
-                    id_votation,
-                    content,
-                    publisher_peer_id: _,
-                    voters_peer_id,
-                    leader_peer_id,
-                    ttl_secs: _,
-                    signature: _,
+                ref request @ ContentMessage::VoteLeaderRequest {
+                    ref id_votation,
+                    ref content,
+                    ref publisher_peer_id,
+                    ref voters_peer_id,
+                    ref leader_peer_id,
+                    ttl_secs,
+                    ref tally_mode,
+                    ref weights,
+                    commit_deadline,
+                    ..
                 } => {
                     log::debug!(
                         "Received  VoteLeaderRequest (petition to be part of the vote) for votation: {}",
                         id_votation
                     );
+                    if let Err(e) = request.verify_vote_leader_request() {
+                        log::warn!(
+                            "⛔ Discarding VoteLeaderRequest for votation={} from {}: {}",
+                            id_votation,
+                            signer,
+                            e
+                        );
+                        return None;
+                    }
+                    if let Err(e) = verify_payload(
+                        db,
+                        topic,
+                        id_votation,
+                        content,
+                        publisher_peer_id,
+                        envelope.timestamp,
+                    ) {
+                        log::warn!(
+                            "⛔ Discarding VoteLeaderRequest for votation={} from {}: {}",
+                            id_votation,
+                            signer,
+                            e
+                        );
+                        return None;
+                    }
+                    if let Err(e) = verify_candidates(db, topic, request) {
+                        log::warn!(
+                            "⛔ Discarding VoteLeaderRequest for votation={} from {}: {}",
+                            id_votation,
+                            signer,
+                            e
+                        );
+                        return None;
+                    }
+                    if let Err(e) = verify_appeal(db, topic, request) {
+                        log::warn!(
+                            "⛔ Discarding appeal for votation={} from {}: {}",
+                            id_votation,
+                            signer,
+                            e
+                        );
+                        return None;
+                    }
+                    if signer != *publisher_peer_id {
+                        log::warn!(
+                            "⛔ Discarding VoteLeaderRequest for votation={} relayed by {} instead of the publisher",
+                            id_votation,
+                            signer
+                        );
+                        return None;
+                    }
                     /* set up a new status vote, check if you are the leader or not */
                     let my_self_str_peer_id = self.peer_id.to_string();
                     let mut votation = models::db::Votation::new(
@@ -91,40 +1041,146 @@ impl MessageHandler for ValidatorHandler {
                             .map(|id| (id.to_string(), None))
                             .collect(),
                     );
-                    if leader_peer_id == my_self_str_peer_id {
+                    if *leader_peer_id == my_self_str_peer_id {
                         votation.leader_id = my_self_str_peer_id.clone();
                         votation.my_role = "role_leader".to_string();
                     }
+                    votation.topic = topic.to_string();
+                    votation.requested_at = Some(envelope.timestamp);
+                    votation.ttl_secs = ttl_secs;
+                    votation.weights = weights.clone();
+                    votation.tally_mode = tally_mode.clone();
+                    votation.commit_deadline = commit_deadline;
+                    // the timer runs from the request, a juror that never votes still sees a silent leader
+                    let is_juror = voters_peer_id.contains(&my_self_str_peer_id);
+                    if is_juror && *leader_peer_id != my_self_str_peer_id {
+                        let start = commit_deadline.map_or(Utc::now(), |deadline| deadline.max(Utc::now()));
+                        votation.round_deadline = Some(start + Duration::seconds(ttl_secs as i64));
+                    }
 
                     if db::get_status_vote(&db, id_votation.as_str()).is_none() {
                         db::new_status_vote(&db, id_votation.as_str(), &votation).ok()?;
+                        db::save_vote_request(db, id_votation, request).ok()?;
                     } else {
                         log::warn!("Trying to insert again a votation");
                     }
                 }
+                /* commitments are kept by every juror, any of them can lead a later round */
+                ContentMessage::VoteCommit {
+                    id_votation,
+                    commitment,
+                } => {
+                    log::debug!("Received VoteCommit for votation: {}", id_votation);
+                    let votation = db::get_status_vote(db, &id_votation)?;
+                    if !votation.votes_id.iter().any(|(id, _)| *id == signer) {
+                        log::warn!(
+                            "⛔ Discarding commitment from {} not part of the jury for votation={}",
+                            signer,
+                            id_votation
+                        );
+                        return None;
+                    }
+                    let Some(deadline) = votation.commit_deadline else {
+                        log::warn!("⛔ Votation={} does not use commit-reveal", id_votation);
+                        return None;
+                    };
+                    if !votation.is_pending() || Utc::now() > deadline {
+                        log::warn!(
+                            "⛔ Discarding late commitment from {} for votation={}",
+                            signer,
+                            id_votation
+                        );
+                        return None;
+                    }
+                    if !db::add_commitment(db, &id_votation, &signer, &commitment).ok()? {
+                        log::warn!(
+                            "Discarding replicated commitment for votation={} peer_id={}",
+                            id_votation,
+                            signer
+                        );
+                    }
+                }
                 ContentMessage::ResultVote {
                     id_votation,
                     result,
+                    round,
+                    salt,
                 } => {
                     log::debug!("Received ResultVote for votation: {}", id_votation);
 
                     // we receive a vote result
 
                     /* are you in the votation process */
-                    let str_peer_id = source_peer.to_string();
+                    let str_peer_id = signer;
 
-                    let Some(mut votation) = db::get_status_vote(&db, &id_votation) else {
+                    let Some(mut votation) = db::get_status_vote(db, &id_votation) else {
                         return None;
                     };
 
+                    // only jurors listed in the request can vote
+                    if !votation.votes_id.iter().any(|(id, _)| *id == str_peer_id) {
+                        log::warn!(
+                            "⛔ Discarding vote from {} not part of the jury for votation={}",
+                            str_peer_id,
+                            id_votation
+                        );
+                        return None;
+                    }
+
+                    if !votation.is_pending() {
+                        log::debug!("Votation={} is already closed", id_votation);
+                        return None;
+                    }
+
+                    // a juror gave up on the current leader before we did
+                    let mut response = None;
+                    if round == votation.round + 1 {
+                        // its ballot attests the timeout, one juror alone can not move the round
+                        let timed_out = votation
+                            .round_deadline
+                            .is_some_and(|deadline| Utc::now() > deadline);
+                        let attested = db::get_signed_votes(db, &id_votation, round)
+                            .iter()
+                            .filter(|held| held.signer != str_peer_id)
+                            .count()
+                            + 1;
+                        if !timed_out && attested * 2 <= votation.votes_id.len() {
+                            log::debug!(
+                                "Holding vote of {} for round={} votation={}, {} of {} jurors gave up",
+                                str_peer_id,
+                                round,
+                                id_votation,
+                                attested,
+                                votation.votes_id.len()
+                            );
+                            db::add_signed_vote(db, &id_votation, round, &str_peer_id, &envelope)
+                                .ok()?;
+                            return None;
+                        }
+                        let (next, resend) =
+                            enter_round(db, &self.keypair, &votation, round)
+                                .map_err(|e| log::error!("Failed to enter round {round}: {e}"))
+                                .ok()?;
+                        votation = next;
+                        response = resend.and_then(|message| self.sign(message));
+                    } else if round != votation.round {
+                        log::debug!(
+                            "Discarding vote for round={} votation={} is in round={}",
+                            round,
+                            id_votation,
+                            votation.round
+                        );
+                        return None;
+                    }
+
                     log::debug!("status extracted votation={:?}", votation);
 
                     /* are you the leader?  */
                     log::debug!(
-                        "leader_id={:?}, my_self_str_peer_id={:?} source_peer={:?}",
+                        "leader_id={:?}, my_self_str_peer_id={:?} signer={:?}",
                         votation.leader_id,
                         self.peer_id.to_string(),
-                        source_peer.to_string()
+                        str_peer_id
                     );
 
                     // I am the leader? only leader can count votes
@@ -134,10 +1190,10 @@ impl MessageHandler for ValidatorHandler {
                             votation,
                             self.peer_id.to_string()
                         );
-                        return None;
+                        return response;
                     }
 
-                    let Ok(exist) = db::exists_vote(db, &id_votation.as_str(), &str_peer_id) else {
+                    let Ok(exist) = db::exists_vote(db, &id_votation, round, &str_peer_id) else {
                         log::warn!("Exists vote failed");
                         return None;
                     };
@@ -151,7 +1207,23 @@ impl MessageHandler for ValidatorHandler {
                         return None;
                     }
 
-                    if db::add_vote(db, &id_votation.as_str(), &str_peer_id, &result).is_err() {
+                    if votation.commit_deadline.is_some()
+                        && let Err(e) =
+                            check_reveal(db, &id_votation, &str_peer_id, &result, salt.as_deref())
+                    {
+                        log::warn!(
+                            "⛔ Discarding reveal from {} for votation={}: {}",
+                            str_peer_id,
+                            id_votation,
+                            e
+                        );
+                        return None;
+                    }
+
+                    if db::add_vote(db, &id_votation, round, &str_peer_id, &result).is_err()
+                        || db::add_signed_vote(db, &id_votation, round, &str_peer_id, &envelope)
+                            .is_err()
+                    {
                         log::warn!(
                             "It could no possible to add a vote={:?} for votation={} and peer_id={}",
                             result,
@@ -161,95 +1233,189 @@ impl MessageHandler for ValidatorHandler {
                         return None;
                     }
 
-                    let votes_and_its_points: Vec<(String, Vote)> =
-                        db::get_votes(db, &id_votation.as_str());
+                    let votes_and_its_points: Vec<(String, Ballot)> =
+                        db::get_votes(db, &id_votation, round);
                     let recollected_votes: HashSet<String> = votes_and_its_points
                         .iter()
                         .map(|(x, _)| x.to_string())
                         .collect();
-                    let expected_votes: HashSet<String> = votation
-                        .votes_id
-                        .iter()
-                        .map(|(x, _)| x.to_string())
-                        .collect();
+                    let expected_votes = expected_voters(db, &votation);
 
-                    /* expire votation */
                     log::debug!("Pending for votation={:?}", votation);
                     log::debug!("Recollected votes={:?}", recollected_votes);
                     log::debug!("Expected_votes votes={:?}", expected_votes);
 
-                    let expires_at = votation.timestamp + EXPIRY_DURATION_IN_DAYS;
-                    let now = Utc::now();
-                    if !expected_votes.is_subset(&recollected_votes) && now > expires_at {
-                        log::debug!("⛔ Vote expired, we are going to decrease reputation");
-
-                        let not_votes: HashSet<String> = recollected_votes
-                            .difference(&expected_votes)
-                            .cloned()
-                            .collect();
-
-                        /* update reputations */
-                        let reputations = not_votes
-                            .iter()
-                            .map(|v| (v.clone(), -INCR_REPUTATION))
-                            .collect::<Vec<(String, f32)>>();
-                        db::update_reputations(db, &topic, &reputations, DEFAULT_REPUTATION)
-                            .ok()?;
+                    // jurors can still commit, their reveal will be needed
+                    let committing = votation
+                        .commit_deadline
+                        .is_some_and(|deadline| Utc::now() <= deadline);
+                    if committing && expected_votes.len() < votation.votes_id.len() {
+                        return response;
                     }
 
                     if expected_votes.is_subset(&recollected_votes) {
-                        /* update reputations */
-                        log::debug!("Updating reputations for all votes");
-                        let reputations = expected_votes
-                            .iter()
-                            .map(|v| (v.clone(), INCR_REPUTATION))
-                            .collect::<Vec<(String, f32)>>();
-                        db::update_reputations(db, &topic, &reputations, DEFAULT_REPUTATION)
-                            .ok()?;
-
-                        let filtered_votes: Vec<(String, Vote)> = votes_and_its_points
+                        let filtered_votes: Vec<(String, Ballot)> = votes_and_its_points
                             .into_iter()
                             .filter(|(peer_id, _)| expected_votes.contains(peer_id))
                             .collect();
-                        // start  process to approve with all the votation
-                        let votes: f32 = filtered_votes
-                            .iter()
-                            .map(|(_, vote)| vote.clone() as u8 as f32)
-                            .sum();
-
-                        let total_votes = filtered_votes.len() as f32;
-                        let percent_accept = votes / total_votes;
-                        let approved = percent_accept >= THRESHOLD_APPROVE;
-
-                        // send the result
-                        let data = ContentMessage::IncludeNewValidatedContent {
-                            id_votation: id_votation.clone(),
-                            content: votation.content.clone(),
-                            approved,
-                        };
-
-                        // update the reputation for voters
-                        let peer_ids = votation
-                            .votes_id
-                            .iter()
-                            .map(|(peer_id, _)| (peer_id.clone(), INCR_REPUTATION))
-                            .collect::<Vec<(String, f32)>>();
-                        db::update_reputations(&db, &topic, &peer_ids, DEFAULT_REPUTATION).ok()?;
-                        return Some(serde_json::to_string(&data).ok()?.into_bytes());
+                        let data = finalize_votation(db, &votation, filtered_votes)
+                            .map_err(|e| log::error!("Failed to close votation={id_votation}: {e}"))
+                            .ok()?;
+                        return self.sign_result(&id_votation, data);
                     }
                 }
                 /* we register included new validated content */
-                ContentMessage::IncludeNewValidatedContent {
-                    id_votation,
-                    content,
+                ref result @ ContentMessage::IncludeNewValidatedContent {
+                    ref id_votation,
+                    ref content,
                     approved,
+                    round,
+                    ..
                 } => {
                     log::debug!(
                         "Received IncludeNewValidatedContent for votation: {}",
                         id_votation
                     );
-                    let data_content = models::db::DataContent::new(id_votation, content, approved);
-                    db::include_new_validated_content(&db, &data_content).ok()?;
+                    // only the leader of the round can finalize a votation
+                    let Some(mut votation) = db::get_status_vote(db, id_votation) else {
+                        log::warn!(
+                            "⛔ Discarding result for unknown votation={} from {}",
+                            id_votation,
+                            signer
+                        );
+                        return None;
+                    };
+                    if votation.leader_for_round(round) != Some(signer.as_str()) {
+                        log::warn!(
+                            "⛔ Discarding result for votation={} from {} who is not the leader of round={}",
+                            id_votation,
+                            signer,
+                            round
+                        );
+                        return None;
+                    }
+                    if !votation.is_pending() {
+                        log::debug!("Votation={} is already closed", id_votation);
+                        return None;
+                    }
+                    // the leader is trusted only if its ballots give the same outcome
+                    let recount = match recount_finalization(db, &votation, result) {
+                        Ok(recount) => recount,
+                        Err(e) => {
+                            log::warn!(
+                                "⛔ Discarding result for votation={} from {}: {}",
+                                id_votation,
+                                signer,
+                                e
+                            );
+                            return None;
+                        }
+                    };
+                    votation.status = closed_status(approved);
+                    votation.round_deadline = None;
+                    apply_decision(db, id_votation, content, &recount).ok()?;
+                    record_tally(&mut votation, recount);
+                    db::save_status_vote(db, &votation).ok()?;
+                    db::save_result(db, id_votation, &envelope).ok()?;
+                }
+                ContentMessage::RetractContent { ref id_votation, .. } => {
+                    log::debug!("Received RetractContent for votation: {}", id_votation);
+                    if let Err(e) = import_retraction(db, topic, &envelope) {
+                        log::warn!(
+                            "⛔ Discarding retraction of votation={} from {}: {}",
+                            id_votation,
+                            signer,
+                            e
+                        );
+                        return None;
+                    }
+                }
+                /* a peer holding other approved content, the buckets that differ are walked later */
+                ContentMessage::TopicDigest {
+                    topic: digest_topic,
+                    summary,
+                } => {
+                    if digest_topic != topic || signer == self.peer_id.to_string() {
+                        return None;
+                    }
+                    let buckets = differing_buckets(&topic_summary(db, topic), &summary);
+                    if !buckets.is_empty() {
+                        log::debug!(
+                            "Summary of topic {} from {} differs in buckets {:?}",
+                            topic,
+                            signer,
+                            buckets
+                        );
+                        db::save_summary_gap(db, topic, &signer, &buckets).ok()?;
+                    }
+                }
+                /* the leader gave up on the jurors that did not vote */
+                ContentMessage::VotationExpired {
+                    id_votation,
+                    round,
+                    missing_voters,
+                } => {
+                    log::debug!("Received VotationExpired for votation: {}", id_votation);
+                    let Some(mut votation) = db::get_status_vote(db, &id_votation) else {
+                        return None;
+                    };
+                    if votation.leader_for_round(round) != Some(signer.as_str()) {
+                        log::warn!(
+                            "⛔ Discarding expiry for votation={} from {} who is not the leader of round={}",
+                            id_votation,
+                            signer,
+                            round
+                        );
+                        return None;
+                    }
+                    if votation.status == "approved" || votation.status == "rejected" {
+                        log::debug!("Votation={} was already decided", id_votation);
+                        return None;
+                    }
+                    // the publisher draws the next juries, it keeps track of who did not vote
+                    if votation.my_role == "role_publisher" {
+                        let policy = db::get_topic_policy(db, &votation.topic);
+                        let penalties = missing_voters
+                            .iter()
+                            .filter(|peer_id| votation.votes_id.iter().any(|(id, _)| id == *peer_id))
+                            .map(|peer_id| (peer_id.clone(), -policy.incr_reputation))
+                            .collect::<Vec<(String, f32)>>();
+                        db::update_reputations(db, &votation.topic, &penalties, DEFAULT_REPUTATION)
+                            .ok()?;
+                    }
+                    votation.status = "expired".to_string();
+                    votation.round_deadline = None;
+                    db::save_status_vote(db, &votation).ok()?;
+                }
+                /* an invitation to a private topic, or a new key after a member was removed */
+                ref message @ ContentMessage::TopicKey {
+                    ref topic,
+                    ref epoch,
+                    ref description,
+                    ref policy,
+                    ..
+                } => match accept_topic_key(db, &self.keypair, &signer, message) {
+                    Ok(Some(_)) => {
+                        log::info!("🔑 Key of epoch {} for private topic {:?}", epoch, topic);
+                        db::init_topic_policy(db, topic, policy).ok()?;
+                        let invited = Topic {
+                            creator: signer.clone(),
+                            subscribed: false,
+                            policy: policy.clone(),
+                            ..Topic::new(topic, description)
+                        };
+                        db::add_known_topic(db, &invited).ok()?;
+                    }
+                    Ok(None) => log::debug!("Key of private topic {:?} is not for me", topic),
+                    Err(e) => log::warn!(
+                        "⛔ Discarding key of topic {:?} from {}: {}",
+                        topic,
+                        signer,
+                        e
+                    ),
+                },
+                ContentMessage::Sealed { .. } => {
+                    log::debug!("Ignoring sealed message nested or outside a private topic");
                 }
             }
         }
@@ -257,3 +1423,1260 @@ impl MessageHandler for ValidatorHandler {
         None
     }
 }
+
+impl MessageHandler for ValidatorHandler {
+    /// Messages of a private topic travel sealed with the topic key. They are
+    /// opened and handled as plain ones, and the response is sealed again.
+    fn handle_message(&mut self, source_peer: PeerId, data: &[u8], topic: &str) -> Option<Vec<u8>> {
+        let Some(private) = db::get_private_topic(&self.db, topic) else {
+            return self.handle_plain(source_peer, data, topic);
+        };
+        // my own messages are handled before being sealed
+        if source_peer == self.peer_id {
+            return self.handle_plain(source_peer, data, topic);
+        }
+        let envelope = serde_json::from_slice::<SignedMessage>(data).ok()?;
+        if let Err(e) = self.authenticate(&envelope) {
+            log::warn!(
+                "⛔ Discarding message of private topic {} from {}: {}",
+                topic,
+                source_peer,
+                e
+            );
+            return None;
+        }
+        let ContentMessage::Sealed {
+            topic: sealed_topic,
+            epoch,
+            sealed,
+        } = envelope.payload
+        else {
+            log::warn!(
+                "⛔ Discarding plain message on private topic {} from {}",
+                topic,
+                envelope.signer
+            );
+            return None;
+        };
+        if sealed_topic != topic || !private.is_member(&envelope.signer) {
+            log::warn!(
+                "⛔ Discarding sealed message of {} who is not a member of {}",
+                envelope.signer,
+                topic
+            );
+            return None;
+        }
+        let opened = open_sealed(&self.db, topic, epoch, &sealed)
+            .map_err(|e| log::warn!("⛔ Failed to open message of private topic {}: {}", topic, e))
+            .ok()?;
+        let response = self.handle_plain(source_peer, &opened, topic)?;
+        seal_for_topic(&self.db, &self.keypair, topic, response)
+            .map_err(|e| log::error!("Failed to seal response for private topic {}: {}", topic, e))
+            .ok()
+    }
+
+    /// Requests are refused until a handler is registered with `set_request_handler`.
+    fn handle_request(&mut self, peer: PeerId, data: &[u8]) -> Option<Vec<u8>> {
+        let mut slot = self.request_handler.0.lock().ok()?;
+        let handler = slot.as_mut()?;
+        handler(peer, data)
+    }
+
+    fn handle_providers(&mut self, topic: &str, providers: &[PeerId]) {
+        for provider in providers.iter().filter(|provider| **provider != self.peer_id) {
+            log::debug!("Peer {} provides topic {}", provider, topic);
+            if let Err(e) = db::add_topic_provider(&self.db, topic, &provider.to_string()) {
+                log::error!("Failed to store provider of topic {}: {}", topic, e);
+            }
+        }
+        // wakes up `find_topic_peers`, the lookup it started has answered
+        if let Err(e) = db::set_providers_lookup(&self.db, topic) {
+            log::error!("Failed to store lookup of topic {}: {}", topic, e);
+        }
+    }
+
+    fn handle_history_request(&mut self, peer: PeerId, request: &HistoryRequest) -> HistoryPage {
+        if !may_read(&self.db, &request.topic, &peer.to_string()) {
+            log::warn!("⛔ Refusing history of private topic {} to {}", request.topic, peer);
+            return HistoryPage {
+                topic: request.topic.clone(),
+                ..Default::default()
+            };
+        }
+        history_page(&self.db, request)
+    }
+
+    /* results that do not recount are left out, the rest of the page is merged */
+    fn handle_history_page(&mut self, peer: PeerId, page: HistoryPage) -> Option<HistoryRequest> {
+        let mut merged = 0;
+        for result in &page.results {
+            match import_result(&self.db, &page.topic, result) {
+                Ok(true) => merged += 1,
+                Ok(false) => {}
+                Err(e) => log::warn!(
+                    "⛔ Discarding result of topic {} synced from {}: {}",
+                    page.topic,
+                    peer,
+                    e
+                ),
+            }
+        }
+        log::info!("📚 Merged {} results of topic {} from {}", merged, page.topic, peer);
+        let cursor = page.cursor?;
+        db::set_history_cursor(&self.db, &page.topic, &peer.to_string(), cursor).ok()?;
+        page.has_more.then(|| HistoryRequest {
+            topic: page.topic.clone(),
+            cursor: Some(cursor),
+            limit: HISTORY_PAGE_SIZE,
+        })
+    }
+
+    fn handle_summary_request(&mut self, peer: PeerId, request: &SummaryRequest) -> SummaryResponse {
+        if !may_read(&self.db, request.topic(), &peer.to_string()) {
+            return request.empty_response();
+        }
+        summary_answer(&self.db, request)
+    }
+
+    /* from the ids of the differing buckets ask for the results I miss, then merge them */
+    fn handle_summary_response(
+        &mut self,
+        peer: PeerId,
+        response: SummaryResponse,
+    ) -> Option<SummaryRequest> {
+        match response {
+            SummaryResponse::Buckets { topic, ids } => {
+                let mine: HashSet<String> = topic_leaves(&self.db, &topic).into_iter().collect();
+                // content I hold withdrawn is not fetched again, the peer learns it from my summary
+                let missing: Vec<String> = ids
+                    .into_iter()
+                    .filter(|leaf| {
+                        if leaf.starts_with(WITHDRAWN_LEAF) {
+                            !mine.contains(leaf)
+                        } else {
+                            db::get_result(&self.db, leaf).is_none()
+                                && !mine.contains(&withdrawn_leaf(leaf))
+                        }
+                    })
+                    .take(HISTORY_PAGE_SIZE)
+                    .collect();
+                (!missing.is_empty()).then_some(SummaryRequest::Results { topic, ids: missing })
+            }
+            SummaryResponse::Results { topic, results } => {
+                for result in &results {
+                    let imported = match result.payload {
+                        ContentMessage::RetractContent { .. } => {
+                            import_retraction(&self.db, &topic, result)
+                        }
+                        _ => import_result(&self.db, &topic, result).map(|_| ()),
+                    };
+                    if let Err(e) = imported {
+                        log::warn!(
+                            "⛔ Discarding result of topic {} repaired from {}: {}",
+                            topic,
+                            peer,
+                            e
+                        );
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::models::db::{TallyMode, TopicPolicy};
+#[cfg(test)]
+use crate::models::messages::Vote;
+
+/* content submitted now by `publisher` on `topic` */
+#[cfg(test)]
+fn content_for_test(publisher: &libp2p::identity::Keypair, topic: &str) -> ContentPayload {
+    ContentPayload {
+        publisher: publisher.public().to_peer_id().to_string(),
+        topic: topic.to_string(),
+        submitted_at: Some(Utc::now()),
+        ..ContentPayload::text("content")
+    }
+}
+
+#[cfg(test)]
+fn vote_leader_request_for_test(keypair: &libp2p::identity::Keypair) -> ContentMessage {
+    let publisher = keypair.public().to_peer_id().to_string();
+    let id_votation = "vote_status/topicA:1:pending:1";
+    let (candidates, interests) =
+        interests_for_test(id_votation, &[candidate_for_test(1), candidate_for_test(2)]);
+    ContentMessage::new_vote_leader_request(
+        id_votation.to_string(),
+        content_for_test(keypair, "topicA"),
+        publisher,
+        candidates,
+        interests,
+        &jury_of_two_for_test(),
+        60,
+        keypair,
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+fn jury_of_two_for_test() -> models::db::TopicPolicy {
+    models::db::TopicPolicy {
+        members_for_consensus: 2,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+fn signed_for_test(message: ContentMessage, keypair: &libp2p::identity::Keypair) -> Vec<u8> {
+    SignedMessage::sign(message, keypair).unwrap().to_bytes().unwrap()
+}
+
+/* candidate `i` of the requests built for tests */
+#[cfg(test)]
+fn candidate_for_test(i: u8) -> libp2p::identity::Keypair {
+    keypair_for_test(200 + i)
+}
+
+/* candidates with the default reputation and their signed answers to the `Interested` message */
+#[cfg(test)]
+fn interests_for_test(
+    id_votation: &str,
+    candidates: &[libp2p::identity::Keypair],
+) -> (Vec<(String, f32)>, Vec<SignedMessage>) {
+    candidates
+        .iter()
+        .map(|keypair| {
+            let answer = ContentMessage::InterestedResponse {
+                id_votation: id_votation.to_string(),
+            };
+            (
+                (keypair.public().to_peer_id().to_string(), DEFAULT_REPUTATION),
+                SignedMessage::sign(answer, keypair).unwrap(),
+            )
+        })
+        .unzip()
+}
+
+#[cfg(test)]
+fn keypair_for_test(seed: u8) -> libp2p::identity::Keypair {
+    libp2p::identity::Keypair::ed25519_from_bytes([seed; 32]).unwrap()
+}
+
+#[cfg(test)]
+fn handler_for_test() -> (tempfile::TempDir, ValidatorHandler) {
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    handler_with_keypair_for_test(keypair)
+}
+
+#[cfg(test)]
+fn handler_with_keypair_for_test(
+    keypair: libp2p::identity::Keypair,
+) -> (tempfile::TempDir, ValidatorHandler) {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let db = Arc::new(db::init_db(tmp_dir.path().to_str().unwrap()).unwrap());
+    (tmp_dir, ValidatorHandler::new(keypair, db))
+}
+
+/* handler in a jury with a second juror, leading the first round or not */
+#[cfg(test)]
+fn jury_for_test(handler_leads: bool) -> (
+    tempfile::TempDir,
+    ValidatorHandler,
+    libp2p::identity::Keypair,
+    libp2p::identity::Keypair,
+) {
+    let leader = keypair_for_test(1);
+    let juror = keypair_for_test(2);
+    let leader_id = leader.public().to_peer_id().to_string();
+    let (candidates, interests) =
+        interests_for_test("vote_status/topicA:1:pending:1", &[leader.clone(), juror.clone()]);
+    let (tmp_dir, mut handler) = handler_with_keypair_for_test(leader);
+
+    // the draw depends on the publisher, the first fixed one giving the wanted leader is used
+    let (publisher, request) = (3..=u8::MAX)
+        .map(keypair_for_test)
+        .find_map(|publisher| {
+            let request = ContentMessage::new_vote_leader_request(
+                "vote_status/topicA:1:pending:1".to_string(),
+                content_for_test(&publisher, "topicA"),
+                publisher.public().to_peer_id().to_string(),
+                candidates.clone(),
+                interests.clone(),
+                &jury_of_two_for_test(),
+                60,
+                &publisher,
+            )
+            .unwrap();
+            let ContentMessage::VoteLeaderRequest { leader_peer_id, .. } = &request else {
+                return None;
+            };
+            ((*leader_peer_id == leader_id) == handler_leads).then_some((publisher, request))
+        })
+        .unwrap();
+    let data = signed_for_test(request, &publisher);
+    handler.handle_message(publisher.public().to_peer_id(), &data, "topicA");
+    assert!(db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:1").is_some());
+    (tmp_dir, handler, juror, publisher)
+}
+
+#[test]
+fn test_vote_leader_request_with_valid_signature_is_stored() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let request = vote_leader_request_for_test(&keypair);
+
+    // the answers of the candidates never reached me, the request carries them
+    let envelope = SignedMessage::sign(request, &keypair).unwrap();
+    let data = envelope.to_bytes().unwrap();
+    handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+
+    let votation = db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:1").unwrap();
+    // the votation expires from the signed request, not from when it reached me
+    assert_eq!(votation.requested_at, Some(envelope.timestamp));
+    let candidates: Vec<String> = (1..=2)
+        .map(|i| candidate_for_test(i).public().to_peer_id().to_string())
+        .collect();
+    assert!(candidates.contains(&votation.leader_id));
+}
+
+#[test]
+fn test_vote_leader_request_with_unchecked_candidates_is_dropped() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let id_votation = "vote_status/topicA:1:pending:1";
+
+    // the answers carried are signed for another votation
+    let candidates = [candidate_for_test(1), candidate_for_test(2)];
+    let (scores, interests) = interests_for_test("vote_status/topicA:2:pending:1", &candidates);
+    let request = ContentMessage::new_vote_leader_request(
+        id_votation.to_string(),
+        content_for_test(&keypair, "topicA"),
+        keypair.public().to_peer_id().to_string(),
+        scores,
+        interests,
+        &jury_of_two_for_test(),
+        60,
+        &keypair,
+    )
+    .unwrap();
+    let data = signed_for_test(request, &keypair);
+    handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+    assert!(db::get_status_vote(&handler.db, id_votation).is_none());
+
+    // the candidates are known with another reputation than the one signed
+    let request = vote_leader_request_for_test(&keypair);
+    let candidate = candidates[0].public().to_peer_id().to_string();
+    db::set_reputation(&handler.db, "topicA", &candidate, 10.0).unwrap();
+    let data = signed_for_test(request, &keypair);
+    handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+    assert!(db::get_status_vote(&handler.db, id_votation).is_none());
+}
+
+#[test]
+fn test_jury_weights_are_the_signed_ones() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let request = vote_leader_request_for_test(&keypair);
+    let ContentMessage::VoteLeaderRequest { weights, .. } = &request else {
+        unreachable!()
+    };
+    let weights = weights.clone();
+
+    // my reputation of a juror moved since the draw, the recount keeps the signed one
+    let candidate = candidate_for_test(1).public().to_peer_id().to_string();
+    db::set_reputation(&handler.db, "topicA", &candidate, 93.0).unwrap();
+    let data = signed_for_test(request, &keypair);
+    handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+
+    let votation = db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:1").unwrap();
+    assert_eq!(votation.weights, weights);
+    assert!(votation.weights.iter().all(|(_, weight)| *weight == 90.0));
+}
+
+#[test]
+fn test_forged_vote_leader_request_is_dropped() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let publisher = libp2p::identity::Keypair::generate_ed25519();
+    let forger = libp2p::identity::Keypair::generate_ed25519();
+
+    // the forger signs with its own key but claims to be the publisher
+    let ContentMessage::VoteLeaderRequest {
+        seed_signature,
+        signature,
+        ..
+    } = vote_leader_request_for_test(&forger)
+    else {
+        unreachable!()
+    };
+    let ContentMessage::VoteLeaderRequest {
+        id_votation,
+        content,
+        publisher_peer_id,
+        voters_peer_id,
+        leader_peer_id,
+        ttl_secs,
+        candidates,
+        interests,
+        weights,
+        ..
+    } = vote_leader_request_for_test(&publisher)
+    else {
+        unreachable!()
+    };
+    let forged = ContentMessage::VoteLeaderRequest {
+        id_votation: id_votation.clone(),
+        content,
+        publisher_peer_id,
+        voters_peer_id,
+        leader_peer_id,
+        ttl_secs,
+        candidates,
+        interests,
+        seed_signature,
+        tally_mode: Default::default(),
+        weights,
+        commit_deadline: None,
+        appeal: None,
+        signature,
+    };
+
+    let data = signed_for_test(forged, &forger);
+    handler.handle_message(forger.public().to_peer_id(), &data, "topicA");
+
+    assert!(db::get_status_vote(&handler.db, &id_votation).is_none());
+}
+
+#[test]
+fn test_tampered_vote_leader_request_is_dropped() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let ContentMessage::VoteLeaderRequest {
+        id_votation,
+        content,
+        publisher_peer_id,
+        mut voters_peer_id,
+        ttl_secs,
+        candidates,
+        interests,
+        seed_signature,
+        weights,
+        signature,
+        ..
+    } = vote_leader_request_for_test(&keypair)
+    else {
+        unreachable!()
+    };
+
+    // the weights are signed, raising one breaks the signature
+    let mut raised = weights.clone();
+    raised[0].1 = 300.0;
+    let tampered = ContentMessage::VoteLeaderRequest {
+        id_votation: id_votation.clone(),
+        content: content.clone(),
+        publisher_peer_id: publisher_peer_id.clone(),
+        voters_peer_id: voters_peer_id.clone(),
+        leader_peer_id: voters_peer_id[0].clone(),
+        ttl_secs,
+        candidates: candidates.clone(),
+        interests: interests.clone(),
+        seed_signature: seed_signature.clone(),
+        tally_mode: Default::default(),
+        weights: raised,
+        commit_deadline: None,
+        appeal: None,
+        signature: signature.clone(),
+    };
+    let data = signed_for_test(tampered, &keypair);
+    handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+    assert!(db::get_status_vote(&handler.db, &id_votation).is_none());
+
+    // someone on the path puts itself as leader of the jury
+    voters_peer_id.push("attacker".to_string());
+    let tampered = ContentMessage::VoteLeaderRequest {
+        id_votation: id_votation.clone(),
+        content,
+        publisher_peer_id,
+        voters_peer_id,
+        leader_peer_id: "attacker".to_string(),
+        ttl_secs,
+        candidates,
+        interests,
+        seed_signature,
+        tally_mode: Default::default(),
+        weights,
+        commit_deadline: None,
+        appeal: None,
+        signature,
+    };
+
+    let data = signed_for_test(tampered, &keypair);
+    handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+
+    assert!(db::get_status_vote(&handler.db, &id_votation).is_none());
+}
+
+#[test]
+fn test_topic_announcement_is_added_to_the_catalog() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let creator = libp2p::identity::Keypair::generate_ed25519();
+    let creator_id = creator.public().to_peer_id().to_string();
+    let announcement = |creator_id: &str| ContentMessage::RegisterTopic {
+        topic: "topicA".to_string(),
+        description: "links about rust".to_string(),
+        creator: creator_id.to_string(),
+        created_at: Some(Utc::now()),
+        policy: TopicPolicy::default(),
+    };
+
+    let forged = signed_for_test(announcement("someone else"), &creator);
+    handler.handle_message(creator.public().to_peer_id(), &forged, "chat-room");
+    assert!(db::get_topic(&handler.db, "topicA").is_none());
+
+    let data = signed_for_test(announcement(&creator_id), &creator);
+    handler.handle_message(creator.public().to_peer_id(), &data, "chat-room");
+    let topic = db::get_topic(&handler.db, "topicA").unwrap();
+    assert_eq!(topic.description, "links about rust");
+    assert_eq!(topic.creator, creator_id);
+    assert!(!topic.subscribed);
+}
+
+#[test]
+fn test_topic_providers_are_stored_without_myself() {
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let (_tmp_dir, mut handler) = handler_with_keypair_for_test(keypair.clone());
+    let provider = libp2p::identity::Keypair::generate_ed25519().public().to_peer_id();
+
+    handler.handle_providers("topicA", &[keypair.public().to_peer_id(), provider]);
+    handler.handle_providers("topicA", &[provider]);
+
+    assert_eq!(db::get_topic_providers(&handler.db, "topicA"), vec![provider.to_string()]);
+    assert!(db::get_topic_providers(&handler.db, "topicB").is_empty());
+    assert!(db::get_providers_lookup(&handler.db, "topicA").is_some());
+
+    // a provider no lookup has found for a day has left the topic
+    let seen_at = Utc::now() - crate::PROVIDER_TTL - Duration::minutes(1);
+    handler
+        .db
+        .insert(format!("providers/topicA/{provider}"), serde_json::to_vec(&seen_at).unwrap())
+        .unwrap();
+    assert!(db::get_topic_providers(&handler.db, "topicA").is_empty());
+}
+
+#[test]
+fn test_tampered_envelope_is_dropped() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let mut envelope = SignedMessage::sign(
+        ContentMessage::Interested {
+            id_votation: "vote_status/topicA:1:pending:1".to_string(),
+            content: content_for_test(&keypair, "topicA"),
+        },
+        &keypair,
+    )
+    .unwrap();
+    envelope.payload = ContentMessage::Interested {
+        id_votation: "vote_status/topicA:1:pending:1".to_string(),
+        content: "other content".to_string().into(),
+    };
+
+    let data = envelope.to_bytes().unwrap();
+    let response = handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+    assert!(response.is_none());
+}
+
+#[test]
+fn test_content_attributed_to_another_peer_is_dropped() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let other = libp2p::identity::Keypair::generate_ed25519();
+    let late = ContentPayload {
+        submitted_at: Some(Utc::now() + Duration::hours(1)),
+        ..content_for_test(&keypair, "topicA")
+    };
+
+    for content in [
+        content_for_test(&other, "topicA"),
+        content_for_test(&keypair, "topicB"),
+        late,
+    ] {
+        let interested = ContentMessage::Interested {
+            id_votation: "vote_status/topicA:1:pending:1".to_string(),
+            content,
+        };
+        let data = signed_for_test(interested, &keypair);
+        assert!(handler.handle_message(keypair.public().to_peer_id(), &data, "topicA").is_none());
+    }
+}
+
+#[test]
+fn test_replayed_message_is_dropped() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let keypair = libp2p::identity::Keypair::generate_ed25519();
+    let data = signed_for_test(
+        ContentMessage::Interested {
+            id_votation: "vote_status/topicA:1:pending:1".to_string(),
+            content: content_for_test(&keypair, "topicA"),
+        },
+        &keypair,
+    );
+
+    let first = handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+    let replayed = handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+    assert!(first.is_some());
+    assert!(replayed.is_none());
+}
+
+#[test]
+fn test_vote_from_outside_the_jury_is_dropped() {
+    let (_tmp_dir, mut handler, _juror, _publisher) = jury_for_test(true);
+    let outsider = libp2p::identity::Keypair::generate_ed25519();
+    let vote = ContentMessage::ResultVote {
+        id_votation: "vote_status/topicA:1:pending:1".to_string(),
+        result: Vote::Yes.into(),
+        round: 1,
+        salt: None,
+    };
+
+    let data = signed_for_test(vote, &outsider);
+    handler.handle_message(outsider.public().to_peer_id(), &data, "topicA");
+
+    assert!(db::get_votes(&handler.db, "vote_status/topicA:1:pending:1", 1).is_empty());
+}
+
+#[test]
+fn test_leader_finalizes_when_all_jurors_voted() {
+    let (_tmp_dir, mut handler, juror, _publisher) = jury_for_test(true);
+    let leader = handler.keypair.clone();
+    let id_votation = "vote_status/topicA:1:pending:1".to_string();
+
+    for keypair in [&leader, &juror] {
+        let vote = ContentMessage::ResultVote {
+            id_votation: id_votation.clone(),
+            result: Vote::Yes.into(),
+            round: 1,
+            salt: None,
+        };
+        let data = signed_for_test(vote, keypair);
+        let response = handler.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+        if keypair.public() != leader.public() {
+            // the finalization is signed by the leader
+            let envelope: SignedMessage = serde_json::from_slice(&response.unwrap()).unwrap();
+            assert!(envelope.verify().is_ok());
+            assert_eq!(envelope.signer, leader.public().to_peer_id().to_string());
+        }
+    }
+
+    let votation = db::get_status_vote(&handler.db, &id_votation).unwrap();
+    assert_eq!(votation.status, "approved");
+    let tally = votation.tally.unwrap();
+    assert_eq!(tally.ballots.len(), 2);
+    assert_eq!(tally.approve_ratio, 1.0);
+}
+
+#[test]
+fn test_result_from_non_leader_is_dropped() {
+    let (_tmp_dir, mut handler, juror, _publisher) = jury_for_test(true);
+    let result = ContentMessage::IncludeNewValidatedContent {
+        id_votation: "vote_status/topicA:1:pending:1".to_string(),
+        content: "content".to_string().into(),
+        approved: true,
+        round: 1,
+        ballots: Vec::new(),
+        threshold_approve: models::db::TopicPolicy::default().threshold_approve,
+        request: None,
+    };
+
+    let data = signed_for_test(result, &juror);
+    handler.handle_message(juror.public().to_peer_id(), &data, "topicA");
+
+    assert!(db::get_contents(&handler.db).is_empty());
+}
+
+
+#[test]
+fn test_silent_leader_is_replaced_in_next_round() {
+    let (_tmp_dir, handler, _juror, _publisher) = jury_for_test(false);
+    let id_votation = "vote_status/topicA:1:pending:1";
+    let my_peer_id = handler.peer_id.to_string();
+    let mut votation = db::get_status_vote(&handler.db, id_votation).unwrap();
+    let silent_leader = votation.leader_id.clone();
+    db::set_reputation(&handler.db, "topicA", &silent_leader, DEFAULT_REPUTATION).unwrap();
+    votation.my_ballot = Some(Vote::Yes.into());
+
+    let (next, resend) = enter_round(&handler.db, &handler.keypair, &votation, 2).unwrap();
+
+    // with two jurors the second round is mine, my ballot is counted locally
+    assert_eq!(next.round, 2);
+    assert_eq!(next.leader_id, my_peer_id);
+    assert!(resend.is_none());
+    assert!(db::exists_vote(&handler.db, id_votation, 2, &my_peer_id).unwrap());
+    assert_eq!(db::get_signed_votes(&handler.db, id_votation, 2).len(), 1);
+    assert_eq!(
+        db::get_reputation(&handler.db, "topicA", &silent_leader),
+        Some(DEFAULT_REPUTATION - models::db::TopicPolicy::default().incr_reputation)
+    );
+}
+
+#[test]
+fn test_one_juror_can_not_move_the_round() {
+    let (_tmp_dir, mut handler, juror, _publisher) = jury_for_test(false);
+    let id_votation = "vote_status/topicA:1:pending:1";
+    let juror_id = juror.public().to_peer_id().to_string();
+    db::set_reputation(&handler.db, "topicA", &juror_id, DEFAULT_REPUTATION).unwrap();
+
+    // the leader of the first round claims it timed out, its ballot is held
+    let vote = ContentMessage::ResultVote {
+        id_votation: id_votation.to_string(),
+        result: Vote::Yes.into(),
+        round: 2,
+        salt: None,
+    };
+    handler.handle_message(juror.public().to_peer_id(), &signed_for_test(vote, &juror), "topicA");
+    let mut votation = db::get_status_vote(&handler.db, id_votation).unwrap();
+    assert_eq!(votation.round, 1);
+    assert_eq!(
+        db::get_reputation(&handler.db, "topicA", &juror_id),
+        Some(DEFAULT_REPUTATION)
+    );
+
+    // once the round timed out here, I lead the next one with the held ballot
+    votation.round_deadline = Some(Utc::now() - Duration::seconds(1));
+    let (next, _) = enter_round(&handler.db, &handler.keypair, &votation, 2).unwrap();
+    assert_eq!(next.leader_id, handler.peer_id.to_string());
+    assert!(db::exists_vote(&handler.db, id_votation, 2, &juror_id).unwrap());
+}
+
+#[test]
+fn test_juror_without_ballot_rotates_a_silent_leader() {
+    let (_tmp_dir, handler, _juror, _publisher) = jury_for_test(false);
+    let id_votation = "vote_status/topicA:1:pending:1";
+    let mut votation = db::get_status_vote(&handler.db, id_votation).unwrap();
+    let leader = votation.leader_id.clone();
+    db::set_reputation(&handler.db, "topicA", &leader, DEFAULT_REPUTATION).unwrap();
+    assert!(votation.my_ballot.is_none());
+    assert!(votation.round_deadline.is_some_and(|deadline| deadline > Utc::now()));
+
+    // the leader never closes the round and I never voted
+    votation.round_deadline = Some(Utc::now() - Duration::seconds(1));
+    let (next, resend) = enter_round(&handler.db, &handler.keypair, &votation, 2).unwrap();
+    assert!(resend.is_none());
+    assert_eq!(next.round, 2);
+    assert_eq!(next.leader_id, handler.peer_id.to_string());
+    assert_eq!(
+        db::get_reputation(&handler.db, "topicA", &leader),
+        Some(DEFAULT_REPUTATION - crate::INCR_REPUTATION)
+    );
+}
+
+#[test]
+fn test_expired_votation_penalizes_only_missing_jurors() {
+    let (_tmp_dir, handler, juror, _publisher) = jury_for_test(true);
+    let id_votation = "vote_status/topicA:1:pending:1";
+    let my_peer_id = handler.peer_id.to_string();
+    let juror_id = juror.public().to_peer_id().to_string();
+    db::set_reputation(&handler.db, "topicA", &juror_id, DEFAULT_REPUTATION).unwrap();
+    let policy = models::db::TopicPolicy {
+        incr_reputation: 10.0,
+        ..Default::default()
+    };
+    db::save_topic_policy(&handler.db, "topicA", &policy).unwrap();
+    let votation = db::get_status_vote(&handler.db, id_votation).unwrap();
+
+    let message = expire_votation(&handler.db, &my_peer_id, &votation).unwrap();
+
+    let Some(ContentMessage::VotationExpired { missing_voters, .. }) = message else {
+        panic!("the leader reports the expiry");
+    };
+    assert_eq!(missing_voters, vec![juror_id.clone()]);
+    assert_eq!(
+        db::get_reputation(&handler.db, "topicA", &juror_id),
+        Some(DEFAULT_REPUTATION - policy.incr_reputation)
+    );
+    assert_eq!(db::get_reputation(&handler.db, "topicA", &my_peer_id), None);
+    let votation = db::get_status_vote(&handler.db, id_votation).unwrap();
+    assert_eq!(votation.status, "expired");
+}
+
+/* moves the votation of `jury_for_test` to commit-reveal, the commit phase ending in `secs` */
+#[cfg(test)]
+fn commit_phase_for_test(handler: &ValidatorHandler, secs: i64) {
+    let mut votation = db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:1").unwrap();
+    votation.commit_deadline = Some(Utc::now() + Duration::seconds(secs));
+    db::save_status_vote(&handler.db, &votation).unwrap();
+}
+
+#[cfg(test)]
+fn commit_for_test(
+    handler: &mut ValidatorHandler,
+    keypair: &libp2p::identity::Keypair,
+    ballot: &Ballot,
+    salt: &str,
+) {
+    let id_votation = "vote_status/topicA:1:pending:1";
+    let peer_id = keypair.public().to_peer_id();
+    let commit = ContentMessage::VoteCommit {
+        id_votation: id_votation.to_string(),
+        commitment: ballot_commitment(id_votation, &peer_id.to_string(), ballot, salt).unwrap(),
+    };
+    handler.handle_message(peer_id, &signed_for_test(commit, keypair), "topicA");
+}
+
+#[cfg(test)]
+fn reveal_for_test(
+    handler: &mut ValidatorHandler,
+    keypair: &libp2p::identity::Keypair,
+    ballot: Ballot,
+    salt: &str,
+) -> Option<Vec<u8>> {
+    let reveal = ContentMessage::ResultVote {
+        id_votation: "vote_status/topicA:1:pending:1".to_string(),
+        result: ballot,
+        round: 1,
+        salt: Some(salt.to_string()),
+    };
+    let peer_id = keypair.public().to_peer_id();
+    handler.handle_message(peer_id, &signed_for_test(reveal, keypair), "topicA")
+}
+
+#[test]
+fn test_reveal_must_match_commitment() {
+    let (_tmp_dir, mut handler, juror, _publisher) = jury_for_test(true);
+    let leader = handler.keypair.clone();
+    let id_votation = "vote_status/topicA:1:pending:1";
+    commit_phase_for_test(&handler, 60);
+
+    let yes = Ballot::from(Vote::Yes);
+    commit_for_test(&mut handler, &leader, &yes, "leader-salt");
+    commit_for_test(&mut handler, &juror, &yes, "juror-salt");
+
+    // a ballot other than the committed one is not counted
+    assert!(reveal_for_test(&mut handler, &juror, Vote::No.into(), "juror-salt").is_none());
+    let juror_id = juror.public().to_peer_id().to_string();
+    assert!(!db::exists_vote(&handler.db, id_votation, 1, &juror_id).unwrap());
+
+    assert!(reveal_for_test(&mut handler, &leader, yes.clone(), "leader-salt").is_none());
+    // every juror committed, the last reveal closes the votation
+    assert!(reveal_for_test(&mut handler, &juror, yes, "juror-salt").is_some());
+    let votation = db::get_status_vote(&handler.db, id_votation).unwrap();
+    assert_eq!(votation.status, "approved");
+}
+
+#[test]
+fn test_late_commitment_is_dropped() {
+    let (_tmp_dir, mut handler, juror, _publisher) = jury_for_test(true);
+    commit_phase_for_test(&handler, -1);
+
+    commit_for_test(&mut handler, &juror, &Vote::Yes.into(), "juror-salt");
+
+    let juror_id = juror.public().to_peer_id().to_string();
+    assert!(db::get_commitment(&handler.db, "vote_status/topicA:1:pending:1", &juror_id).is_none());
+}
+
+#[test]
+fn test_juror_that_never_reveals_leaves_the_votation_open() {
+    let (_tmp_dir, mut handler, juror, _publisher) = jury_for_test(true);
+    let leader = handler.keypair.clone();
+    let id_votation = "vote_status/topicA:1:pending:1";
+    commit_phase_for_test(&handler, 60);
+
+    commit_for_test(&mut handler, &leader, &Vote::Yes.into(), "leader-salt");
+    commit_for_test(&mut handler, &juror, &Vote::No.into(), "juror-salt");
+    commit_phase_for_test(&handler, -1);
+
+    // the juror that committed is still awaited
+    assert!(reveal_for_test(&mut handler, &leader, Vote::Yes.into(), "leader-salt").is_none());
+
+    // the leader can not close without the reveal of the juror voting no
+    let votation = db::get_status_vote(&handler.db, id_votation).unwrap();
+    assert!(close_reveal_window(&handler.db, &votation).unwrap().is_none());
+    assert!(db::get_status_vote(&handler.db, id_votation).unwrap().is_pending());
+}
+
+/* leader of `jury_for_test(true)` closing with two yes, and the other juror to receive the result */
+#[cfg(test)]
+pub(crate) fn leader_result_for_test() -> (
+    Vec<tempfile::TempDir>,
+    ValidatorHandler,
+    libp2p::identity::Keypair,
+    Vec<u8>,
+    libp2p::identity::Keypair,
+) {
+    let (leader_dir, mut leader, juror, publisher) = jury_for_test(true);
+    let id_votation = "vote_status/topicA:1:pending:1";
+    let mut result = None;
+    for keypair in [&leader.keypair.clone(), &juror] {
+        let vote = ContentMessage::ResultVote {
+            id_votation: id_votation.to_string(),
+            result: Vote::Yes.into(),
+            round: 1,
+            salt: None,
+        };
+        let data = signed_for_test(vote, keypair);
+        result = leader.handle_message(keypair.public().to_peer_id(), &data, "topicA");
+    }
+
+    let (juror_dir, mut juror_handler) = handler_with_keypair_for_test(juror);
+    let request = db::get_vote_request(&leader.db, id_votation).unwrap();
+    let data = signed_for_test(request, &publisher);
+    juror_handler.handle_message(publisher.public().to_peer_id(), &data, "topicA");
+    (
+        vec![leader_dir, juror_dir],
+        juror_handler,
+        leader.keypair.clone(),
+        result.unwrap(),
+        publisher,
+    )
+}
+
+#[test]
+fn test_leader_result_is_recounted() {
+    let (_tmp_dirs, mut handler, leader, result, _publisher) = leader_result_for_test();
+
+    handler.handle_message(leader.public().to_peer_id(), &result, "topicA");
+
+    let votation = db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:1").unwrap();
+    assert_eq!(votation.status, "approved");
+    assert_eq!(votation.tally.unwrap().ballots.len(), 2);
+    assert_eq!(db::get_contents(&handler.db).len(), 1);
+}
+
+#[test]
+fn test_leader_result_with_altered_outcome_is_dropped() {
+    let (_tmp_dirs, mut handler, leader, result, _publisher) = leader_result_for_test();
+    let envelope: SignedMessage = serde_json::from_slice(&result).unwrap();
+    let mut altered = envelope.payload;
+    if let ContentMessage::IncludeNewValidatedContent { approved, .. } = &mut altered {
+        *approved = false;
+    }
+
+    let data = signed_for_test(altered, &leader);
+    handler.handle_message(leader.public().to_peer_id(), &data, "topicA");
+
+    let votation = db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:1").unwrap();
+    assert!(votation.is_pending());
+    assert!(db::get_contents(&handler.db).is_empty());
+}
+
+#[test]
+fn test_leader_result_without_a_ballot_is_dropped() {
+    let (_tmp_dirs, mut handler, leader, result, _publisher) = leader_result_for_test();
+    let envelope: SignedMessage = serde_json::from_slice(&result).unwrap();
+    let leader_id = leader.public().to_peer_id().to_string();
+    let mut partial = envelope.payload;
+    if let ContentMessage::IncludeNewValidatedContent { ballots, .. } = &mut partial {
+        ballots.retain(|ballot| ballot.signer == leader_id);
+    }
+
+    let data = signed_for_test(partial, &leader);
+    handler.handle_message(leader.public().to_peer_id(), &data, "topicA");
+
+    let votation = db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:1").unwrap();
+    assert!(votation.is_pending());
+    assert!(db::get_contents(&handler.db).is_empty());
+}
+
+/* the jurors of a result have the reputation the topic asks for */
+#[cfg(test)]
+fn known_jury_for_test(handler: &ValidatorHandler, result: &[u8]) {
+    let envelope: SignedMessage = serde_json::from_slice(result).unwrap();
+    let ContentMessage::IncludeNewValidatedContent {
+        request: Some(request),
+        ..
+    } = envelope.payload
+    else {
+        panic!("result without its request");
+    };
+    let ContentMessage::VoteLeaderRequest { voters_peer_id, .. } = *request else {
+        panic!("not a VoteLeaderRequest");
+    };
+    for peer_id in voters_peer_id {
+        db::set_reputation(&handler.db, "topicA", &peer_id, DEFAULT_REPUTATION).unwrap();
+    }
+}
+
+#[test]
+fn test_result_of_an_unknown_jury_is_not_imported() {
+    let (_tmp_dirs, _juror, _leader, result, _publisher) = leader_result_for_test();
+    let (_tmp_dir, handler) = handler_for_test();
+    let envelope: SignedMessage = serde_json::from_slice(&result).unwrap();
+
+    // its request was never seen here and nobody knows its jurors
+    assert!(import_result(&handler.db, "topicA", &envelope).is_err());
+    assert!(db::get_contents(&handler.db).is_empty());
+
+    known_jury_for_test(&handler, &result);
+    assert!(import_result(&handler.db, "topicA", &envelope).unwrap());
+}
+
+/* appeal of the result of `leader_result_for_test`, opened by `appellant` with a jury of three */
+#[cfg(test)]
+fn appeal_for_test(result: &[u8], appellant: &libp2p::identity::Keypair) -> ContentMessage {
+    let previous: SignedMessage = serde_json::from_slice(result).unwrap();
+    let ContentMessage::IncludeNewValidatedContent { content, .. } = &previous.payload else {
+        panic!("not a result");
+    };
+    let id_votation = db::appeal_key("vote_status/topicA:1:pending:1");
+    let candidates: Vec<_> = (1..=3).map(candidate_for_test).collect();
+    let (candidates, interests) = interests_for_test(&id_votation, &candidates);
+    ContentMessage::new_vote_leader_request(
+        id_votation,
+        content.clone(),
+        appellant.public().to_peer_id().to_string(),
+        candidates,
+        interests,
+        &models::db::TopicPolicy {
+            members_for_consensus: 3,
+            ..Default::default()
+        },
+        60,
+        appellant,
+    )
+    .unwrap()
+    .with_appeal(previous, appellant)
+    .unwrap()
+}
+
+#[test]
+fn test_publisher_appeal_with_larger_jury_is_stored() {
+    let (_tmp_dirs, _juror, _leader, result, publisher) = leader_result_for_test();
+    let (_tmp_dir, mut handler) = handler_for_test();
+    known_jury_for_test(&handler, &result);
+
+    let appeal = appeal_for_test(&result, &publisher);
+    let data = signed_for_test(appeal, &publisher);
+    handler.handle_message(publisher.public().to_peer_id(), &data, "topicA");
+
+    let votation = db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:2").unwrap();
+    assert_eq!(votation.votes_id.len(), 3);
+}
+
+#[test]
+fn test_appeal_from_peer_without_reputation_is_dropped() {
+    let (_tmp_dirs, _juror, _leader, result, _publisher) = leader_result_for_test();
+    let (_tmp_dir, mut handler) = handler_for_test();
+    known_jury_for_test(&handler, &result);
+    let outsider = libp2p::identity::Keypair::generate_ed25519();
+
+    let appeal = appeal_for_test(&result, &outsider);
+    let data = signed_for_test(appeal, &outsider);
+    handler.handle_message(outsider.public().to_peer_id(), &data, "topicA");
+
+    assert!(db::get_status_vote(&handler.db, "vote_status/topicA:1:pending:2").is_none());
+}
+
+#[test]
+fn test_publisher_retraction_withdraws_content() {
+    let (_tmp_dirs, mut handler, leader, result, publisher) = leader_result_for_test();
+    let id_votation = "vote_status/topicA:1:pending:1";
+    handler.handle_message(leader.public().to_peer_id(), &result, "topicA");
+    let request = db::get_vote_request(&handler.db, id_votation).unwrap();
+
+    // nobody but the publisher can retract
+    let outsider = libp2p::identity::Keypair::generate_ed25519();
+    let forged = ContentMessage::RetractContent {
+        id_votation: id_votation.to_string(),
+        request: Box::new(request.clone()),
+    };
+    let data = signed_for_test(forged, &outsider);
+    handler.handle_message(outsider.public().to_peer_id(), &data, "topicA");
+    assert_eq!(db::get_contents(&handler.db)[0].approved, StateContent::Approved);
+
+    let retraction = ContentMessage::RetractContent {
+        id_votation: id_votation.to_string(),
+        request: Box::new(request),
+    };
+    let data = signed_for_test(retraction, &publisher);
+    handler.handle_message(publisher.public().to_peer_id(), &data, "topicA");
+
+    let contents = db::get_contents(&handler.db);
+    assert_eq!(contents[0].approved, StateContent::Retracted);
+    assert!(contents[0].content.text.is_empty());
+
+    // the withdrawn text is not served to late joiners
+    let request = HistoryRequest {
+        topic: "topicA".to_string(),
+        cursor: None,
+        limit: 10,
+    };
+    assert!(history_page(&handler.db, &request).results.is_empty());
+}
+
+#[test]
+fn test_approved_removal_withdraws_flagged_content() {
+    let (_tmp_dir, handler) = handler_for_test();
+    let id_votation = "vote_status/topicA:1:pending:1";
+    db::include_new_validated_content(
+        &handler.db,
+        &DataContent::new(id_votation.to_string(), "content".to_string().into(), true),
+    )
+    .unwrap();
+    let removal = db::removal_key(id_votation);
+    assert_eq!(db::removal_target(&removal), Some(id_votation));
+
+    let content = ContentPayload::text("content");
+    let rejected = tally(&[("a".to_string(), Vote::No.into())], &[], &TallyMode::Simple, 0.5);
+    apply_decision(&handler.db, &removal, &content, &rejected).unwrap();
+    assert_eq!(db::get_contents(&handler.db)[0].approved, StateContent::Approved);
+
+    let approved = tally(&[("a".to_string(), Vote::Yes.into())], &[], &TallyMode::Simple, 0.5);
+    apply_decision(&handler.db, &removal, &content, &approved).unwrap();
+    let contents = db::get_contents(&handler.db);
+    assert_eq!(contents.len(), 1);
+    assert_eq!(contents[0].approved, StateContent::Removed);
+}
+
+#[test]
+fn test_history_is_merged_by_a_late_joiner() {
+    let (_tmp_dirs, mut juror, leader, result, _publisher) = leader_result_for_test();
+    juror.handle_message(leader.public().to_peer_id(), &result, "topicA");
+    let (_tmp_dir, mut late) = handler_for_test();
+    known_jury_for_test(&late, &result);
+    let request = HistoryRequest {
+        topic: "topicA".to_string(),
+        cursor: None,
+        limit: 10,
+    };
+
+    let page = juror.handle_history_request(late.peer_id, &request);
+    assert_eq!(page.results.len(), 1);
+    assert!(!page.has_more);
+    assert!(late.handle_history_page(juror.peer_id, page).is_none());
+    assert_eq!(db::get_contents(&late.db).len(), 1);
+
+    // resuming from the cursor gives nothing already merged
+    let cursor = db::get_history_cursor(&late.db, "topicA", &juror.peer_id.to_string());
+    assert!(cursor.is_some());
+    let page = juror.handle_history_request(late.peer_id, &HistoryRequest { cursor, ..request });
+    assert!(page.results.is_empty());
+    assert_eq!(page.cursor, cursor);
+}
+
+#[test]
+fn test_differing_summary_fetches_the_missing_results() {
+    let (_tmp_dirs, mut juror, leader, result, _publisher) = leader_result_for_test();
+    juror.handle_message(leader.public().to_peer_id(), &result, "topicA");
+    let (_tmp_dir, mut late) = handler_for_test();
+    known_jury_for_test(&late, &result);
+
+    let digest = ContentMessage::TopicDigest {
+        topic: "topicA".to_string(),
+        summary: topic_summary(&juror.db, "topicA"),
+    };
+    let data = signed_for_test(digest, &juror.keypair);
+    late.handle_message(juror.peer_id, &data, "topicA");
+    let mut gaps = db::take_summary_gaps(&late.db);
+    assert_eq!(gaps.len(), 1);
+    assert!(db::take_summary_gaps(&late.db).is_empty());
+
+    let (topic, _, buckets) = gaps.remove(0);
+    let response = juror.handle_summary_request(late.peer_id, &SummaryRequest::Buckets { topic, buckets });
+    let request = late.handle_summary_response(juror.peer_id, response).unwrap();
+    let response = juror.handle_summary_request(late.peer_id, &request);
+    assert!(late.handle_summary_response(juror.peer_id, response).is_none());
+    assert_eq!(topic_summary(&late.db, "topicA"), topic_summary(&juror.db, "topicA"));
+}
+
+#[test]
+fn test_withdrawal_is_repaired_through_the_summary() {
+    let (_tmp_dirs, mut juror, leader, result, publisher) = leader_result_for_test();
+    let id_votation = "vote_status/topicA:1:pending:1";
+    juror.handle_message(leader.public().to_peer_id(), &result, "topicA");
+    let (_tmp_dir, mut late) = handler_for_test();
+    known_jury_for_test(&late, &result);
+    let envelope: SignedMessage = serde_json::from_slice(&result).unwrap();
+    assert!(import_result(&late.db, "topicA", &envelope).unwrap());
+
+    // the retraction only reached the juror
+    let retraction = ContentMessage::RetractContent {
+        id_votation: id_votation.to_string(),
+        request: Box::new(db::get_vote_request(&juror.db, id_votation).unwrap()),
+    };
+    let data = signed_for_test(retraction, &publisher);
+    juror.handle_message(publisher.public().to_peer_id(), &data, "topicA");
+    let buckets = differing_buckets(&topic_summary(&late.db, "topicA"), &topic_summary(&juror.db, "topicA"));
+    assert_eq!(buckets.len(), 1);
+
+    // the juror has nothing to fetch, the late peer gets the retraction instead of the text
+    let response = late.handle_summary_request(
+        juror.peer_id,
+        &SummaryRequest::Buckets {
+            topic: "topicA".to_string(),
+            buckets: buckets.clone(),
+        },
+    );
+    assert!(juror.handle_summary_response(late.peer_id, response).is_none());
+    let response = juror.handle_summary_request(
+        late.peer_id,
+        &SummaryRequest::Buckets {
+            topic: "topicA".to_string(),
+            buckets,
+        },
+    );
+    let request = late.handle_summary_response(juror.peer_id, response).unwrap();
+    let response = juror.handle_summary_request(late.peer_id, &request);
+    assert!(late.handle_summary_response(juror.peer_id, response).is_none());
+
+    assert_eq!(db::get_contents(&late.db)[0].approved, StateContent::Retracted);
+    assert_eq!(topic_summary(&late.db, "topicA"), topic_summary(&juror.db, "topicA"));
+}
+
+#[test]
+fn test_private_topic_is_only_handled_sealed() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let owner = libp2p::identity::Keypair::generate_ed25519();
+    let owner_id = owner.public().to_peer_id().to_string();
+    let me = handler.peer_id.to_string();
+    let mut private = models::db::PrivateTopic {
+        owner: owner_id.clone(),
+        members: vec![owner_id.clone(), me.clone()],
+        epoch: 1,
+    };
+    let topic = Topic::new("secret", "members only");
+    let key = crate::private::new_topic_key();
+    let invitation = crate::private::topic_key_message(&owner, &topic, &private, &key).unwrap();
+    let invitation = signed_for_test(invitation, &owner);
+    handler.handle_message(owner.public().to_peer_id(), &invitation, "chat-room");
+    assert!(!db::get_topic(&handler.db, "secret").unwrap().subscribed);
+
+    let interested = ContentMessage::Interested {
+        content: content_for_test(&owner, "secret"),
+        id_votation: "vote_status/secret:1:pending:1".to_string(),
+    };
+    let plain = signed_for_test(interested.clone(), &owner);
+    assert!(handler.handle_message(owner.public().to_peer_id(), &plain, "secret").is_none());
+
+    let sealed =
+        seal_for_topic(&handler.db, &owner, "secret", signed_for_test(interested, &owner)).unwrap();
+    let response = handler.handle_message(owner.public().to_peer_id(), &sealed, "secret").unwrap();
+    let envelope: SignedMessage = serde_json::from_slice(&response).unwrap();
+    let ContentMessage::Sealed { epoch, sealed, .. } = envelope.payload else {
+        panic!("response is not sealed");
+    };
+    let opened = open_sealed(&handler.db, "secret", epoch, &sealed).unwrap();
+    let opened: SignedMessage = serde_json::from_slice(&opened).unwrap();
+    assert!(matches!(opened.payload, ContentMessage::InterestedResponse { .. }));
+
+    // once removed the new key is not mine, I can not publish on the topic anymore
+    private.members.retain(|member| *member != me);
+    private.epoch = 2;
+    let rotation =
+        crate::private::topic_key_message(&owner, &topic, &private, &crate::private::new_topic_key())
+            .unwrap();
+    let rotation = signed_for_test(rotation, &owner);
+    handler.handle_message(owner.public().to_peer_id(), &rotation, "chat-room");
+    assert_eq!(db::get_private_topic(&handler.db, "secret").unwrap().epoch, 2);
+    assert!(seal_for_topic(&handler.db, &handler.keypair, "secret", b"data".to_vec()).is_err());
+}
+
+#[test]
+fn test_requests_are_refused_until_a_handler_is_registered() {
+    let (_tmp_dir, mut handler) = handler_for_test();
+    let peer = keypair_for_test(1).public().to_peer_id();
+    assert!(handler.handle_request(peer, b"ping").is_none());
+
+    // registered on a clone, as the application does once the node owns the handler
+    handler.clone().set_request_handler(Box::new(|_, data| Some([b"echo: ", data].concat())));
+    assert_eq!(handler.handle_request(peer, b"ping").unwrap(), b"echo: ping");
+}
@@ -0,0 +1,92 @@
+use sha2::{Digest, Sha256};
+
+/* Jury selection for a votation.
+ *
+ * The draw is seeded with the hash of the votation id and a signature of the
+ * publisher over it. Ed25519 signatures are deterministic, so the publisher can
+ * not re-roll the draw, and every juror can recompute it from the request.
+ */
+
+pub fn jury_seed(id_votation: &str, seed_signature: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(id_votation.as_bytes());
+    hasher.update(seed_signature);
+    hasher.finalize().into()
+}
+
+/* uniform value in (0, 1) for a candidate, derived from the seed */
+fn draw(seed: &[u8; 32], peer_id: &str) -> f64 {
+    let mut hasher = Sha256::new();
+    hasher.update(seed);
+    hasher.update(peer_id.as_bytes());
+    let hash = hasher.finalize();
+    let bits = u64::from_be_bytes(hash[..8].try_into().expect("sha256 has 32 bytes")) >> 11;
+    (bits as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+/// Weighted sampling without replacement (Efraimidis-Spirakis) over
+/// `(peer_id, reputation)` candidates. The publisher never sits in its own jury.
+/// The first juror returned is the leader.
+pub fn select_jury(
+    seed: &[u8; 32],
+    candidates: &[(String, f32)],
+    publisher_peer_id: &str,
+    size: usize,
+) -> Vec<String> {
+    let mut keys: Vec<(f64, &str)> = candidates
+        .iter()
+        .filter(|(peer_id, weight)| peer_id != publisher_peer_id && *weight > 0.0)
+        .map(|(peer_id, weight)| (draw(seed, peer_id).ln() / *weight as f64, peer_id.as_str()))
+        .collect();
+    // higher key wins, ties resolved by peer id so everyone gets the same order
+    keys.sort_by(|(a_key, a_peer), (b_key, b_peer)| {
+        b_key.total_cmp(a_key).then_with(|| a_peer.cmp(b_peer))
+    });
+    keys.dedup_by(|(_, a), (_, b)| a == b);
+
+    keys.into_iter()
+        .take(size)
+        .map(|(_, peer_id)| peer_id.to_string())
+        .collect()
+}
+
+#[test]
+fn test_select_jury_is_deterministic() {
+    let candidates: Vec<(String, f32)> = (0..10).map(|i| (format!("peer{i}"), 90.0)).collect();
+    let seed = jury_seed("vote_status/topicA:1:pending:1", b"signature");
+
+    let first = select_jury(&seed, &candidates, "publisher", 5);
+    let mut reversed = candidates.clone();
+    reversed.reverse();
+    let second = select_jury(&seed, &reversed, "publisher", 5);
+
+    assert_eq!(first.len(), 5);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_select_jury_depends_on_seed() {
+    let candidates: Vec<(String, f32)> = (0..20).map(|i| (format!("peer{i}"), 90.0)).collect();
+    let juries: std::collections::HashSet<Vec<String>> = (0..10)
+        .map(|i| {
+            let seed = jury_seed(&format!("votation{i}"), b"signature");
+            select_jury(&seed, &candidates, "publisher", 5)
+        })
+        .collect();
+
+    assert!(juries.len() > 1);
+}
+
+#[test]
+fn test_select_jury_excludes_publisher() {
+    let candidates = vec![
+        ("publisher".to_string(), 100.0),
+        ("peer1".to_string(), 90.0),
+        ("peer2".to_string(), 90.0),
+    ];
+    let seed = jury_seed("votation", b"signature");
+
+    let jury = select_jury(&seed, &candidates, "publisher", 5);
+    assert_eq!(jury.len(), 2);
+    assert!(!jury.contains(&"publisher".to_string()));
+}
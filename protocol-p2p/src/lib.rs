@@ -2,10 +2,16 @@ use chrono::{Duration, TimeDelta};
 pub use protocol::MessageHandler;
 pub use sled::Db;
 
+pub mod archive;
 pub mod client;
+pub mod crypto;
 pub mod handler;
+pub mod jury;
 pub mod models;
+pub mod private;
 pub mod protocol;
+pub mod summary;
+pub mod tally;
 
 pub mod db;
 
@@ -18,4 +24,31 @@ const MIN_REPUTATION_THRESHOLD: f32 = 80.0;
 const INCR_REPUTATION: f32 = 5.0;
 const THRESHOLD_APPROVE: f32 = 0.6;
 
+/* length of the commit phase when the topic hides ballots until every juror committed */
+const COMMIT_SECS: u64 = 30;
+
+/* every appeal of a decision is judged by a larger jury */
+const APPEAL_JURY_INCREMENT: usize = 2;
+const APPEAL_MIN_REPUTATION: f32 = 120.0;
+const MAX_APPEALS: u32 = 2;
+
+/* bounds of the policies announced for a topic, anything outside them is clamped */
+const MAX_MEMBERS_FOR_CONSENSUS: usize = 100;
+const MAX_APPEALS_ALLOWED: u32 = 10;
+const MAX_POLICY_SECS: i64 = 365 * 24 * 3600;
+
+/* how long a lookup of the providers of a topic waits for the DHT */
+const PROVIDERS_WAIT_SECS: u64 = 5;
+/* providers not found again by a lookup for this long have left the topic */
+const PROVIDER_TTL: TimeDelta = Duration::hours(24);
+
+/* results served per page when a late joiner syncs the history of a topic */
+const HISTORY_PAGE_SIZE: usize = 50;
+
+/* how often the summary of the approved content of a topic is gossiped */
+const SUMMARY_SECS: u64 = 60;
+
 const EXPIRY_DURATION_IN_DAYS: TimeDelta = Duration::days(2);
+
+/* signed envelopes older than this are considered replays */
+const MAX_MESSAGE_AGE: TimeDelta = Duration::minutes(10);
@@ -6,14 +6,10 @@ use tokio::sync::{mpsc, Mutex};
 
 use messages_types::ChatCommand;
 use protocol_p2p::client::ValidatorClient;
-use protocol_p2p::models::messages::ContentMessage;
+use protocol_p2p::models::messages::SignedMessage;
 use protocol_p2p::models::messages::Vote;
-
-use crate::db::init_db;
-
-mod db;
-mod models;
-pub mod protocol;
+use protocol_p2p::db;
+use protocol_p2p::db::init_db;
 
 /// A simple mock server that relays messages to all subscribers by topic.
 struct MockPubSubServer {
@@ -56,10 +52,15 @@ impl MockPubSubServer {
                 ChatCommand::Publish(topic, data) => {
                     self.publish(&topic, data).await;
                 }
-                ChatCommand::Subscribe(_) => {
+                ChatCommand::Subscribe(_)
+                | ChatCommand::Unsubscribe(_)
+                | ChatCommand::FindProviders(_) => {
                     log::debug!("Mocking subscribe command, not implemented in mock server.");
                 }
-                ChatCommand::SendOne(_, _) => {
+                ChatCommand::SendOne(_, _)
+                | ChatCommand::SyncHistory(_, _)
+                | ChatCommand::SyncSummary(_, _)
+                | ChatCommand::SendDirect(_, _) => {
                     //
                 }
                 ChatCommand::Quit => {
@@ -124,18 +125,14 @@ async fn main() {
                     t_peer_id.clone(),
                     String::from_utf8_lossy(&message)
                 );
-                if let Ok(_) = serde_json::from_slice::<ContentMessage>(&message) {
+                if serde_json::from_slice::<SignedMessage>(&message).is_ok() {
                     let response = handler
                         .lock()
                         .await
                         .handle_message(t_peer_id, &message, topic);
                     if let Some(response_message) = response {
                         client
-                            .send(
-                                topic.to_string(),
-                                &serde_json::from_slice::<ContentMessage>(&response_message)
-                                    .unwrap(),
-                            )
+                            .publish(topic.to_string(), response_message)
                             .await
                             .expect("Failed to send message");
                     }
@@ -185,7 +182,7 @@ async fn main() {
                 "Client received message: {:?}",
                 String::from_utf8_lossy(&message)
             );
-            if let Ok(_) = serde_json::from_slice::<ContentMessage>(&message) {
+            if serde_json::from_slice::<SignedMessage>(&message).is_ok() {
                 //get back the peer id from clients to resend the message
                 if let Some(peer_id) = created_peer_ids.pop() {
                     cloned_myself
@@ -1,7 +1,18 @@
 use serde::{Deserialize, Serialize};
 
+/* votations start at round one, also used for messages without a round */
+fn first_round() -> u32 {
+    1
+}
+
+/* topics stored before the registry were all joined ones */
+fn subscribed() -> bool {
+    true
+}
+
 pub mod db {
-    use chrono::{DateTime, Utc};
+    use super::messages::{Ballot, Vote};
+    use chrono::{DateTime, Duration, TimeDelta, Utc};
     use serde::{Deserialize, Serialize};
     use std::fmt;
 
@@ -9,6 +20,15 @@ pub mod db {
     pub struct Topic {
         pub name: String,
         pub description: String,
+        #[serde(default)]
+        pub policy: TopicPolicy,
+        /* peer that announced the topic, empty when unknown */
+        #[serde(default)]
+        pub creator: String,
+        #[serde(default)]
+        pub created_at: Option<DateTime<Utc>>,
+        #[serde(default = "super::subscribed")]
+        pub subscribed: bool,
     }
 
     impl Topic {
@@ -16,6 +36,137 @@ pub mod db {
             Topic {
                 name: name.to_string(),
                 description: description.to_string(),
+                policy: TopicPolicy::default(),
+                creator: String::new(),
+                created_at: Some(Utc::now()),
+                subscribed: true,
+            }
+        }
+    }
+
+    /// Members of a private topic, as distributed by its owner with the topic key.
+    /// The content of the topic is encrypted with the key of the latest epoch.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct PrivateTopic {
+        pub owner: String,
+        pub members: Vec<String>,
+        pub epoch: u32,
+    }
+
+    impl PrivateTopic {
+        pub fn is_member(&self, peer_id: &str) -> bool {
+            self.owner == peer_id || self.members.iter().any(|member| member == peer_id)
+        }
+    }
+
+    /// How the leader weighs the ballots of the jurors.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+    pub enum TallyMode {
+        /// One juror, one vote.
+        #[default]
+        Simple,
+        /// Ballots weigh the per-topic reputation of the juror when the jury was drawn.
+        ReputationWeighted,
+    }
+
+    /// Result of counting the ballots, kept in the votation record.
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct Tally {
+        pub mode: TallyMode,
+        pub ballots: Vec<(String, Vote, f32)>,
+        pub yes_weight: f32,
+        pub total_weight: f32,
+        pub approve_ratio: f32,
+        pub approved: bool,
+    }
+
+    /// Consensus parameters of a topic, announced by its creator.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(default)]
+    pub struct TopicPolicy {
+        pub members_for_consensus: usize,
+        pub min_reputation_threshold: f32,
+        pub threshold_approve: f32,
+        pub tally_mode: TallyMode,
+        pub incr_reputation: f32,
+        pub timeout_secs: u64,
+        pub expiry_secs: i64,
+        /// Jurors publish a hash of their ballot first and reveal it once the
+        /// commit phase is over, so nobody can copy the votes already cast.
+        pub commit_reveal: bool,
+        pub commit_secs: u64,
+        /// Peers other than the publisher need this reputation to appeal a decision.
+        pub appeal_min_reputation: f32,
+        pub appeal_jury_increment: usize,
+        pub max_appeals: u32,
+    }
+
+    impl Default for TopicPolicy {
+        fn default() -> Self {
+            Self {
+                members_for_consensus: crate::MEMBERS_FOR_CONSENSUS,
+                min_reputation_threshold: crate::MIN_REPUTATION_THRESHOLD,
+                threshold_approve: crate::THRESHOLD_APPROVE,
+                tally_mode: TallyMode::default(),
+                incr_reputation: crate::INCR_REPUTATION,
+                timeout_secs: crate::TIMEOUT_SECS,
+                expiry_secs: crate::EXPIRY_DURATION_IN_DAYS.num_seconds(),
+                commit_reveal: false,
+                commit_secs: crate::COMMIT_SECS,
+                appeal_min_reputation: crate::APPEAL_MIN_REPUTATION,
+                appeal_jury_increment: crate::APPEAL_JURY_INCREMENT,
+                max_appeals: crate::MAX_APPEALS,
+            }
+        }
+    }
+
+    impl TopicPolicy {
+        pub fn expiry(&self) -> TimeDelta {
+            Duration::seconds(self.expiry_secs)
+        }
+
+        /// Jury size of a votation `level`: level 1 gets `members_for_consensus` and each
+        /// appeal adds `appeal_jury_increment` (2 by default).
+        pub fn jury_size(&self, level: u32) -> usize {
+            self.members_for_consensus.saturating_add(
+                self.appeal_jury_increment
+                    .saturating_mul(level.saturating_sub(1) as usize),
+            )
+        }
+
+        /// The policy with every field in a usable range, policies are announced
+        /// by other peers. Values that are not numbers take the default.
+        pub fn clamped(self) -> Self {
+            let default = Self::default();
+            let finite_or = |value: f32, fallback: f32| {
+                if value.is_finite() { value } else { fallback }
+            };
+            let max_secs = crate::MAX_POLICY_SECS;
+            Self {
+                members_for_consensus: self
+                    .members_for_consensus
+                    .clamp(1, crate::MAX_MEMBERS_FOR_CONSENSUS),
+                min_reputation_threshold: finite_or(
+                    self.min_reputation_threshold,
+                    default.min_reputation_threshold,
+                ),
+                threshold_approve: finite_or(self.threshold_approve, default.threshold_approve)
+                    .clamp(0.0, 1.0),
+                tally_mode: self.tally_mode,
+                incr_reputation: finite_or(self.incr_reputation, default.incr_reputation)
+                    .clamp(0.0, crate::DEFAULT_REPUTATION),
+                timeout_secs: self.timeout_secs.clamp(1, max_secs as u64),
+                expiry_secs: self.expiry_secs.clamp(1, max_secs),
+                commit_reveal: self.commit_reveal,
+                commit_secs: self.commit_secs.clamp(1, max_secs as u64),
+                appeal_min_reputation: finite_or(
+                    self.appeal_min_reputation,
+                    default.appeal_min_reputation,
+                ),
+                appeal_jury_increment: self
+                    .appeal_jury_increment
+                    .min(crate::MAX_MEMBERS_FOR_CONSENSUS),
+                max_appeals: self.max_appeals.min(crate::MAX_APPEALS_ALLOWED),
             }
         }
     }
@@ -24,17 +175,48 @@ pub mod db {
     pub struct Votation {
         pub id_votation: String,
         pub timestamp: DateTime<Utc>,
-        pub content: String,
+        /* timestamp of the signed VoteLeaderRequest, every node expires the votation from it */
+        #[serde(default)]
+        pub requested_at: Option<DateTime<Utc>>,
+        pub content: ContentPayload,
         pub status: String,
         pub leader_id: String,
         pub my_role: String,
         pub votes_id: Vec<(String, Option<f32>)>,
+        /* a new round starts with the next juror as leader when the current one is silent */
+        #[serde(default = "super::first_round")]
+        pub round: u32,
+        #[serde(default)]
+        pub topic: String,
+        #[serde(default)]
+        pub ttl_secs: u64,
+        /* my own vote, re-sent to the leader of every new round */
+        #[serde(default)]
+        pub my_ballot: Option<Ballot>,
+        /* the leader has until then to close the round, counted from the request and again from my vote */
+        #[serde(default)]
+        pub round_deadline: Option<DateTime<Utc>>,
+        /* weights signed in the request, checked once when the jury was accepted */
+        #[serde(default)]
+        pub weights: Vec<(String, f32)>,
+        #[serde(default)]
+        pub tally_mode: TallyMode,
+        #[serde(default)]
+        pub tally: Option<Tally>,
+        /* commit-reveal votations, ballots sent before then are only hashes */
+        #[serde(default)]
+        pub commit_deadline: Option<DateTime<Utc>>,
+        /* salt of my commitment, needed to reveal the ballot */
+        #[serde(default)]
+        pub my_salt: Option<String>,
+        #[serde(default)]
+        pub revealed: bool,
     }
 
     impl Votation {
         pub fn new(
             id_votation: String,
-            content: String,
+            content: ContentPayload,
             status: String,
             leader_id: String,
             my_role: String,
@@ -43,12 +225,45 @@ pub mod db {
             Self {
                 id_votation,
                 timestamp: Utc::now(),
+                requested_at: None,
                 content,
                 status,
                 leader_id,
                 my_role,
                 votes_id,
+                round: super::first_round(),
+                topic: String::new(),
+                ttl_secs: 0,
+                my_ballot: None,
+                round_deadline: None,
+                weights: Vec::new(),
+                tally_mode: TallyMode::default(),
+                tally: None,
+                commit_deadline: None,
+                my_salt: None,
+                revealed: false,
+            }
+        }
+
+        /// Leader of a round, jurors take turns in the order of the drawn jury.
+        pub fn leader_for_round(&self, round: u32) -> Option<&str> {
+            if self.votes_id.is_empty() || round == 0 {
+                return None;
             }
+            let index = (round as usize - 1) % self.votes_id.len();
+            self.votes_id.get(index).map(|(peer_id, _)| peer_id.as_str())
+        }
+
+        pub fn is_pending(&self) -> bool {
+            self.status == "pending"
+        }
+
+        /// Once the commit phase is over, each round has `ttl_secs` to collect
+        /// the reveals. The leader closes with the ones it got after that.
+        pub fn reveal_deadline(&self) -> Option<DateTime<Utc>> {
+            self.commit_deadline.map(|deadline| {
+                deadline + Duration::seconds((self.ttl_secs * self.round as u64) as i64)
+            })
         }
     }
     #[derive(Serialize, Deserialize, Debug)]
@@ -62,17 +277,170 @@ pub mod db {
     pub enum StateContent {
         Approved,
         Rejected,
+        /// Withdrawn by its publisher.
+        Retracted,
+        /// Removed by a votation after being flagged.
+        Removed,
+    }
+
+    /// What a payload carries.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+    pub enum ContentKind {
+        #[default]
+        Text,
+        Link,
+    }
+
+    /// Content submitted for validation, with who submitted it, where and when.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+    #[serde(from = "ContentPayloadRepr")]
+    pub struct ContentPayload {
+        pub kind: ContentKind,
+        pub text: String,
+        pub url: Option<String>,
+        pub title: Option<String>,
+        pub tags: Vec<String>,
+        pub publisher: String,
+        pub topic: String,
+        pub submitted_at: Option<DateTime<Utc>>,
+    }
+
+    impl ContentPayload {
+        pub fn text(text: &str) -> Self {
+            Self {
+                text: text.to_string(),
+                ..Default::default()
+            }
+        }
+
+        pub fn link(url: &str, title: Option<String>, tags: Vec<String>, description: &str) -> Self {
+            Self {
+                kind: ContentKind::Link,
+                text: description.to_string(),
+                url: Some(url.to_string()),
+                title,
+                tags,
+                ..Default::default()
+            }
+        }
+
+        /// What identifies the content when looking for duplicates, the url of a link.
+        pub fn key_source(&self) -> &str {
+            match (&self.kind, &self.url) {
+                (ContentKind::Link, Some(url)) => url,
+                _ => &self.text,
+            }
+        }
+
+        /* keeps who published it and when, drops the content itself */
+        pub fn withdrawn(&self) -> Self {
+            Self {
+                kind: self.kind.clone(),
+                publisher: self.publisher.clone(),
+                topic: self.topic.clone(),
+                submitted_at: self.submitted_at,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl From<&str> for ContentPayload {
+        fn from(text: &str) -> Self {
+            ContentPayload::text(text)
+        }
+    }
+
+    impl From<String> for ContentPayload {
+        fn from(text: String) -> Self {
+            ContentPayload::text(&text)
+        }
+    }
+
+    // content used to be a plain string, both forms are accepted
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ContentPayloadRepr {
+        Plain(String),
+        Structured {
+            #[serde(default)]
+            kind: ContentKind,
+            #[serde(default)]
+            text: String,
+            #[serde(default)]
+            url: Option<String>,
+            #[serde(default)]
+            title: Option<String>,
+            #[serde(default)]
+            tags: Vec<String>,
+            #[serde(default)]
+            publisher: String,
+            #[serde(default)]
+            topic: String,
+            #[serde(default)]
+            submitted_at: Option<DateTime<Utc>>,
+        },
+    }
+
+    impl From<ContentPayloadRepr> for ContentPayload {
+        fn from(repr: ContentPayloadRepr) -> Self {
+            match repr {
+                ContentPayloadRepr::Plain(text) => text.into(),
+                ContentPayloadRepr::Structured {
+                    kind,
+                    text,
+                    url,
+                    title,
+                    tags,
+                    publisher,
+                    topic,
+                    submitted_at,
+                } => ContentPayload {
+                    kind,
+                    text,
+                    url,
+                    title,
+                    tags,
+                    publisher,
+                    topic,
+                    submitted_at,
+                },
+            }
+        }
+    }
+
+    /// Summary of the jury decision kept with the content.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct OutcomeSummary {
+        pub ballots: usize,
+        pub yes_weight: f32,
+        pub total_weight: f32,
+        pub approve_ratio: f32,
+    }
+
+    impl From<&Tally> for OutcomeSummary {
+        fn from(tally: &Tally) -> Self {
+            Self {
+                ballots: tally.ballots.len(),
+                yes_weight: tally.yes_weight,
+                total_weight: tally.total_weight,
+                approve_ratio: tally.approve_ratio,
+            }
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct DataContent {
         pub id_votation: String,
-        pub content: String,
+        pub content: ContentPayload,
         pub approved: StateContent,
+        #[serde(default)]
+        pub decided_at: Option<DateTime<Utc>>,
+        #[serde(default)]
+        pub outcome: Option<OutcomeSummary>,
     }
 
     impl DataContent {
-        pub fn new(id_votation: String, content: String, approved: bool) -> Self {
+        pub fn new(id_votation: String, content: ContentPayload, approved: bool) -> Self {
             Self {
                 id_votation,
                 content,
@@ -81,6 +449,17 @@ pub mod db {
                 } else {
                     StateContent::Rejected
                 },
+                decided_at: None,
+                outcome: None,
+            }
+        }
+
+        /// Content as decided by a jury, with the summary of its tally.
+        pub fn decided(id_votation: String, content: ContentPayload, tally: &Tally) -> Self {
+            Self {
+                decided_at: Some(Utc::now()),
+                outcome: Some(tally.into()),
+                ..Self::new(id_votation, content, tally.approved)
             }
         }
     }
@@ -141,6 +520,21 @@ pub mod db {
             match self {
                 StateContent::Approved => write!(f, "Approved"),
                 StateContent::Rejected => write!(f, "Rejected"),
+                StateContent::Retracted => write!(f, "Retracted"),
+                StateContent::Removed => write!(f, "Removed"),
+            }
+        }
+    }
+
+    // ContentPayload
+    impl fmt::Display for ContentPayload {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match (&self.kind, &self.url) {
+                (ContentKind::Link, Some(url)) => match &self.title {
+                    Some(title) => write!(f, "{} ({})", title, url),
+                    None => write!(f, "{}", url),
+                },
+                _ => write!(f, "{}", self.text),
             }
         }
     }
@@ -158,26 +552,102 @@ pub mod db {
 }
 
 pub mod messages {
+    use anyhow::anyhow;
     use base64::engine::general_purpose;
     use base64::Engine;
+    use crate::{jury, tally};
+    use crate::models::db::{ContentPayload, TallyMode, TopicPolicy};
+    use crate::summary::TopicSummary;
+    use chrono::{DateTime, Utc};
     use libp2p::gossipsub::IdentTopic;
-    use libp2p::identity::Keypair;
+    use libp2p::identity::{Keypair, PublicKey};
+    use libp2p::PeerId;
     use once_cell::sync::Lazy;
     use serde::{Deserialize, Serialize};
+    use sha2::{Digest, Sha256};
+    use std::str::FromStr;
 
     pub static DEFAULT_TOPIC: Lazy<IdentTopic> = Lazy::new(|| IdentTopic::new("chat-room"));
 
-    #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+    #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
     pub enum Vote {
         Yes = 1,
         No = 0,
+        /// Counts toward the quorum but not toward the approval.
+        Abstain = 2,
+    }
+
+    /* justifications travel in every ballot, keep them short */
+    const MAX_JUSTIFICATION_LEN: usize = 280;
+
+    /// A juror ballot, the vote with a 0-1 confidence and an optional justification.
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[serde(from = "BallotRepr")]
+    pub struct Ballot {
+        pub vote: Vote,
+        pub confidence: f32,
+        pub justification: Option<String>,
+    }
+
+    impl Ballot {
+        pub fn new(vote: Vote, confidence: f32, justification: Option<String>) -> Self {
+            let confidence = if confidence.is_nan() {
+                0.0
+            } else {
+                confidence.clamp(0.0, 1.0)
+            };
+            let justification = justification
+                .map(|text| text.chars().take(MAX_JUSTIFICATION_LEN).collect::<String>());
+            Ballot {
+                vote,
+                confidence,
+                justification,
+            }
+        }
+    }
+
+    impl From<Vote> for Ballot {
+        fn from(vote: Vote) -> Self {
+            Ballot::new(vote, 1.0, None)
+        }
+    }
+
+    fn full_confidence() -> f32 {
+        1.0
+    }
+
+    // ballots used to be a bare `Vote`, both forms are accepted
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BallotRepr {
+        Full {
+            vote: Vote,
+            #[serde(default = "full_confidence")]
+            confidence: f32,
+            #[serde(default)]
+            justification: Option<String>,
+        },
+        Bare(Vote),
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
+    impl From<BallotRepr> for Ballot {
+        fn from(repr: BallotRepr) -> Self {
+            match repr {
+                BallotRepr::Full {
+                    vote,
+                    confidence,
+                    justification,
+                } => Ballot::new(vote, confidence, justification),
+                BallotRepr::Bare(vote) => vote.into(),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone)]
     #[serde(tag = "type")] // para serializar como { "type": "RequestVote", ... }
     pub enum ContentMessage {
         Interested {
-            content: String,
+            content: ContentPayload,
             id_votation: String,
         },
         InterestedResponse {
@@ -185,64 +655,432 @@ pub mod messages {
         },
         VoteLeaderRequest {
             id_votation: String,
-            content: String,
+            content: ContentPayload,
             publisher_peer_id: String,
             voters_peer_id: Vec<String>,
             leader_peer_id: String,
             ttl_secs: u64,
+            /* peers and reputations the jury was drawn from */
+            #[serde(default)]
+            candidates: Vec<(String, f32)>,
+            /* InterestedResponse of each candidate, signed by it */
+            #[serde(default)]
+            interests: Vec<SignedMessage>,
+            /* publisher signature over the votation id, seeds the draw */
+            #[serde(default)]
+            seed_signature: String,
+            #[serde(default)]
+            tally_mode: TallyMode,
+            /* reputation of each juror among the candidates, the tally weighs ballots by it */
+            #[serde(default)]
+            weights: Vec<(String, f32)>,
+            /* set when the topic votes with commit-reveal */
+            #[serde(default)]
+            commit_deadline: Option<DateTime<Utc>>,
+            /* result of the previous level, signed by its leader, when this votation is an appeal */
+            #[serde(default)]
+            appeal: Option<Box<SignedMessage>>,
             signature: String,
         },
+        /* hash of a ballot, revealed later with a ResultVote carrying the salt */
+        VoteCommit {
+            id_votation: String,
+            commitment: String,
+        },
         ResultVote {
             id_votation: String,
-            result: Vote,
+            result: Ballot,
+            #[serde(default = "super::first_round")]
+            round: u32,
+            #[serde(default)]
+            salt: Option<String>,
         },
         IncludeNewValidatedContent {
             id_votation: String,
-            content: String,
+            content: ContentPayload,
             approved: bool,
+            #[serde(default = "super::first_round")]
+            round: u32,
+            /* signed ResultVote of every counted juror, anyone can recount them */
+            #[serde(default)]
+            ballots: Vec<SignedMessage>,
+            #[serde(default)]
+            threshold_approve: f32,
+            /* the VoteLeaderRequest signed by the publisher, with the jury and its weights */
+            #[serde(default)]
+            request: Option<Box<ContentMessage>>,
         },
+        /* announcement of a new topic, signed by its creator */
         RegisterTopic {
             topic: String,
+            #[serde(default)]
+            description: String,
+            #[serde(default)]
+            creator: String,
+            #[serde(default)]
+            created_at: Option<DateTime<Utc>>,
+            #[serde(default)]
+            policy: TopicPolicy,
+        },
+        VotationExpired {
+            id_votation: String,
+            round: u32,
+            missing_voters: Vec<String>,
+        },
+        /* the publisher withdraws its content, proven by the request it signed for the first votation */
+        RetractContent {
+            id_votation: String,
+            request: Box<ContentMessage>,
+        },
+        /* summary of the approved content a peer holds for a topic, gossiped periodically */
+        TopicDigest {
+            topic: String,
+            summary: TopicSummary,
         },
+        /* key of a private topic sealed to each member, signed by the owner of the topic */
+        TopicKey {
+            topic: String,
+            epoch: u32,
+            members: Vec<String>,
+            /* member and the key sealed to it, base64 */
+            keys: Vec<(String, String)>,
+            #[serde(default)]
+            description: String,
+            #[serde(default)]
+            policy: TopicPolicy,
+        },
+        /* signed message of a private topic, encrypted with the key of `epoch`, base64 */
+        Sealed {
+            topic: String,
+            epoch: u32,
+            sealed: String,
+        },
+    }
+
+    /// Envelope every `ContentMessage` travels in, signed by the peer emitting it.
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    pub struct SignedMessage {
+        pub signer: String,
+        pub nonce: u64,
+        pub timestamp: DateTime<Utc>,
+        pub payload: ContentMessage,
+        pub signature: String,
+    }
+
+    // canonical bytes covered by the envelope signature
+    fn signed_message_payload(
+        signer: &str,
+        nonce: u64,
+        timestamp: &DateTime<Utc>,
+        payload: &ContentMessage,
+    ) -> anyhow::Result<Vec<u8>> {
+        let temp_msg = serde_json::json!({
+            "signer": signer,
+            "nonce": nonce,
+            "timestamp": timestamp,
+            "payload": payload,
+        });
+        Ok(serde_json::to_vec(&temp_msg)?)
+    }
+
+    impl SignedMessage {
+        pub fn sign(payload: ContentMessage, keypair: &Keypair) -> anyhow::Result<Self> {
+            let signer = keypair.public().to_peer_id().to_string();
+            let nonce = rand::random::<u64>();
+            let timestamp = Utc::now();
+            let msg_bytes = signed_message_payload(&signer, nonce, &timestamp, &payload)?;
+            let signature_bytes = keypair.sign(&msg_bytes)?;
+            Ok(SignedMessage {
+                signer,
+                nonce,
+                timestamp,
+                payload,
+                signature: general_purpose::STANDARD.encode(signature_bytes),
+            })
+        }
+
+        /// Checks the envelope signature against the public key of `signer`.
+        pub fn verify(&self) -> anyhow::Result<()> {
+            let msg_bytes =
+                signed_message_payload(&self.signer, self.nonce, &self.timestamp, &self.payload)?;
+            let signature_bytes = general_purpose::STANDARD.decode(&self.signature)?;
+            let public_key = public_key_from_peer_id(&self.signer)?;
+            if !public_key.verify(&msg_bytes, &signature_bytes) {
+                return Err(anyhow!("Invalid envelope signature from {}", self.signer));
+            }
+            Ok(())
+        }
+
+        pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(serde_json::to_vec(self)?)
+        }
+    }
+
+    /// Asks a peer for the results of a topic it logged after `cursor`.
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    pub struct HistoryRequest {
+        pub topic: String,
+        /* position in the history of the peer asked, None to start from the beginning */
+        pub cursor: Option<u64>,
+        pub limit: usize,
+    }
+
+    /// A page of the approved history of a topic, the results as signed by their leaders.
+    #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+    pub struct HistoryPage {
+        pub topic: String,
+        pub results: Vec<SignedMessage>,
+        /* position of the last result of the page, the next request resumes from it */
+        pub cursor: Option<u64>,
+        pub has_more: bool,
+    }
+
+    /// Walk of the summary of a topic with a peer whose root differs from mine.
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    #[serde(tag = "type")]
+    pub enum SummaryRequest {
+        /* ids of the approved content in the buckets that differ */
+        Buckets { topic: String, buckets: Vec<usize> },
+        /* results of the content I am missing */
+        Results { topic: String, ids: Vec<String> },
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    #[serde(tag = "type")]
+    pub enum SummaryResponse {
+        Buckets { topic: String, ids: Vec<String> },
+        Results { topic: String, results: Vec<SignedMessage> },
+    }
+
+    impl SummaryRequest {
+        pub fn topic(&self) -> &str {
+            match self {
+                SummaryRequest::Buckets { topic, .. } | SummaryRequest::Results { topic, .. } => {
+                    topic
+                }
+            }
+        }
+
+        /// Answer of a peer with nothing to offer.
+        pub fn empty_response(&self) -> SummaryResponse {
+            match self {
+                SummaryRequest::Buckets { topic, .. } => SummaryResponse::Buckets {
+                    topic: topic.clone(),
+                    ids: Vec::new(),
+                },
+                SummaryRequest::Results { topic, .. } => SummaryResponse::Results {
+                    topic: topic.clone(),
+                    results: Vec::new(),
+                },
+            }
+        }
+    }
+
+    /// Recovers the public key inlined in an ed25519 peer id.
+    pub fn public_key_from_peer_id(peer_id: &str) -> anyhow::Result<PublicKey> {
+        let peer_id = PeerId::from_str(peer_id)?;
+        let multihash = peer_id.as_ref();
+        // identity multihash, the key is stored as is
+        if multihash.code() != 0 {
+            return Err(anyhow!("Peer id {peer_id} does not embed its public key"));
+        }
+        Ok(PublicKey::try_decode_protobuf(multihash.digest())?)
+    }
+
+    /// Hash a juror commits to before revealing its ballot. It binds the votation
+    /// and the juror, so a commitment can not be replayed by someone else.
+    pub fn ballot_commitment(
+        id_votation: &str,
+        peer_id: &str,
+        ballot: &Ballot,
+        salt: &str,
+    ) -> anyhow::Result<String> {
+        let temp_msg = serde_json::json!({
+            "type": "VoteCommit",
+            "id_votation": id_votation,
+            "peer_id": peer_id,
+            "ballot": ballot,
+            "salt": salt,
+        });
+        let hash = Sha256::digest(serde_json::to_vec(&temp_msg)?);
+        Ok(general_purpose::STANDARD.encode(hash))
+    }
+
+    /* random salt so a commitment can not be brute forced from the few possible ballots */
+    pub fn new_salt() -> String {
+        general_purpose::STANDARD.encode(rand::random::<[u8; 32]>())
+    }
+
+    // canonical bytes the publisher signs to seed the jury draw
+    fn jury_seed_payload(id_votation: &str, content: &ContentPayload) -> anyhow::Result<Vec<u8>> {
+        let temp_msg = serde_json::json!({
+            "type": "JurySeed",
+            "id_votation": id_votation,
+            "content": content,
+        });
+        Ok(serde_json::to_vec(&temp_msg)?)
+    }
+
+    // canonical bytes signed by the publisher of a VoteLeaderRequest
+    fn vote_leader_request_payload(message: &ContentMessage) -> anyhow::Result<Vec<u8>> {
+        let ContentMessage::VoteLeaderRequest {
+            id_votation,
+            content,
+            publisher_peer_id,
+            voters_peer_id,
+            leader_peer_id,
+            ttl_secs,
+            candidates,
+            interests,
+            seed_signature,
+            tally_mode,
+            weights,
+            commit_deadline,
+            appeal,
+            ..
+        } = message
+        else {
+            return Err(anyhow!("Not a VoteLeaderRequest"));
+        };
+        // Serialize the message without the signature
+        let temp_msg = serde_json::json!({
+            "type": "VoteLeaderRequest",
+            "id_votation": id_votation,
+            "content": content,
+            "publisher_peer_id": publisher_peer_id,
+            "voters": voters_peer_id,
+            "leader": leader_peer_id,
+            "ttl_secs": ttl_secs,
+            "candidates": candidates,
+            "interests": interests,
+            "seed_signature": seed_signature,
+            "tally_mode": tally_mode,
+            "weights": weights,
+            "commit_deadline": commit_deadline,
+            "appeal": appeal,
+        });
+
+        // Convert to canonical string
+        Ok(serde_json::to_vec(&temp_msg)?)
     }
 
     impl ContentMessage {
+        /// Draws the jury among `candidates` following the topic `policy` and
+        /// signs the resulting request. `interests` are the `InterestedResponse`
+        /// the candidates signed, so jurors do not depend on having received them.
         pub fn new_vote_leader_request(
             id_votation: String,
-            content: String,
+            content: ContentPayload,
             publisher_peer_id: String,
-            voters_peer_id: Vec<String>,
-            leader_peer_id: String,
+            candidates: Vec<(String, f32)>,
+            interests: Vec<SignedMessage>,
+            policy: &TopicPolicy,
             ttl_secs: u64,
             keypair: &Keypair,
         ) -> anyhow::Result<Self> {
-            // Serialize the message without the signature
-            let temp_msg = serde_json::json!({
-                "type": "VoteLeaderRequest",
-                "id_votation": id_votation,
-                "content": content,
-                "publisher_peer_id": publisher_peer_id,
-                "voters": voters_peer_id,
-                "leader": leader_peer_id,
-                "ttl_secs": ttl_secs,
-            });
-
-            // Convert to canonical string
-            let msg_bytes = serde_json::to_vec(&temp_msg)?;
-
-            // Sign with the publisher's private key
-            let signature_bytes = keypair.sign(&msg_bytes)?;
-            let signature_b64 = general_purpose::STANDARD.encode(signature_bytes);
+            let seed_signature = keypair.sign(&jury_seed_payload(&id_votation, &content)?)?;
+            let seed = jury::jury_seed(&id_votation, &seed_signature);
+            let voters_peer_id = jury::select_jury(
+                &seed,
+                &candidates,
+                &publisher_peer_id,
+                policy.members_for_consensus,
+            );
+            let leader_peer_id = voters_peer_id
+                .first()
+                .cloned()
+                .ok_or_else(|| anyhow!("No candidates available for votation {id_votation}"))?;
+            let weights = tally::jury_weights(&candidates, &voters_peer_id);
 
-            Ok(ContentMessage::VoteLeaderRequest {
+            let mut message = ContentMessage::VoteLeaderRequest {
                 id_votation,
                 content,
                 publisher_peer_id,
                 voters_peer_id,
                 leader_peer_id,
                 ttl_secs,
-                signature: signature_b64,
-            })
+                candidates,
+                interests,
+                seed_signature: general_purpose::STANDARD.encode(seed_signature),
+                tally_mode: policy.tally_mode.clone(),
+                weights,
+                commit_deadline: policy
+                    .commit_reveal
+                    .then(|| Utc::now() + chrono::Duration::seconds(policy.commit_secs as i64)),
+                appeal: None,
+                signature: String::new(),
+            };
+            message.sign_vote_leader_request(keypair)?;
+            Ok(message)
+        }
+
+        /// Turns a request into the appeal of the `previous` result and signs it again.
+        pub fn with_appeal(mut self, previous: SignedMessage, keypair: &Keypair) -> anyhow::Result<Self> {
+            let ContentMessage::VoteLeaderRequest { appeal, .. } = &mut self else {
+                return Err(anyhow!("Not a VoteLeaderRequest"));
+            };
+            *appeal = Some(Box::new(previous));
+            self.sign_vote_leader_request(keypair)?;
+            Ok(self)
+        }
+
+        // Sign with the publisher's private key
+        fn sign_vote_leader_request(&mut self, keypair: &Keypair) -> anyhow::Result<()> {
+            let signature_bytes = keypair.sign(&vote_leader_request_payload(self)?)?;
+            if let ContentMessage::VoteLeaderRequest { signature, .. } = self {
+                *signature = general_purpose::STANDARD.encode(signature_bytes);
+            }
+            Ok(())
+        }
+
+        /// Checks that a `VoteLeaderRequest` was signed by the publisher it claims
+        /// and that its jury and weights are the ones drawn from the signed candidates.
+        pub fn verify_vote_leader_request(&self) -> anyhow::Result<()> {
+            let ContentMessage::VoteLeaderRequest {
+                id_votation,
+                content,
+                publisher_peer_id,
+                voters_peer_id,
+                leader_peer_id,
+                candidates,
+                seed_signature,
+                weights,
+                signature,
+                ..
+            } = self
+            else {
+                return Err(anyhow!("Not a VoteLeaderRequest"));
+            };
+            let public_key = public_key_from_peer_id(publisher_peer_id)?;
+
+            let signature_bytes = general_purpose::STANDARD.decode(signature)?;
+            if !public_key.verify(&vote_leader_request_payload(self)?, &signature_bytes) {
+                return Err(anyhow!(
+                    "Invalid signature for votation {id_votation} from {publisher_peer_id}"
+                ));
+            }
+
+            let seed_signature = general_purpose::STANDARD.decode(seed_signature)?;
+            if !public_key.verify(&jury_seed_payload(id_votation, content)?, &seed_signature) {
+                return Err(anyhow!("Invalid jury seed for votation {id_votation}"));
+            }
+            let seed = jury::jury_seed(id_votation, &seed_signature);
+            let expected_jury =
+                jury::select_jury(&seed, candidates, publisher_peer_id, voters_peer_id.len());
+            if voters_peer_id.is_empty()
+                || *voters_peer_id != expected_jury
+                || voters_peer_id.first() != Some(leader_peer_id)
+            {
+                return Err(anyhow!(
+                    "Jury for votation {id_votation} does not match the draw from its candidates"
+                ));
+            }
+            if *weights != tally::jury_weights(candidates, voters_peer_id) {
+                return Err(anyhow!(
+                    "Weights for votation {id_votation} are not the reputations of its candidates"
+                ));
+            }
+            Ok(())
         }
     }
 }
@@ -0,0 +1,229 @@
+use crate::crypto;
+use crate::db;
+use crate::models::db::{PrivateTopic, Topic};
+use crate::models::messages::{ContentMessage, SignedMessage};
+use anyhow::anyhow;
+use base64::engine::general_purpose;
+use base64::Engine;
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::ChaCha20Poly1305;
+use libp2p::identity::Keypair;
+use sled::Db;
+
+/* separates the keys sealing topic keys from other uses of the identity keys */
+const TOPIC_KEY_CONTEXT: &str = "p2p-topic-key/1";
+
+// binds a sealed topic key to its topic, epoch and member
+fn key_header(topic: &str, epoch: u32, member: &str) -> Vec<u8> {
+    format!("{topic}:{epoch}:{member}").into_bytes()
+}
+
+fn content_header(topic: &str, epoch: u32) -> Vec<u8> {
+    format!("{topic}:{epoch}").into_bytes()
+}
+
+/// Fresh key of a private topic, a new one is drawn at every epoch.
+pub fn new_topic_key() -> [u8; 32] {
+    ChaCha20Poly1305::generate_key(&mut OsRng).into()
+}
+
+/// Key distribution of a private topic I own, the key is sealed to every member.
+pub fn topic_key_message(
+    keypair: &Keypair,
+    topic: &Topic,
+    private: &PrivateTopic,
+    key: &[u8; 32],
+) -> anyhow::Result<ContentMessage> {
+    let keys = private
+        .members
+        .iter()
+        .map(|member| {
+            let sealing_key = crypto::shared_key(keypair, member, TOPIC_KEY_CONTEXT)?;
+            let sealed = crypto::seal(
+                &sealing_key,
+                key,
+                &key_header(&topic.name, private.epoch, member),
+            )?;
+            Ok((member.clone(), general_purpose::STANDARD.encode(sealed)))
+        })
+        .collect::<anyhow::Result<Vec<(String, String)>>>()?;
+    Ok(ContentMessage::TopicKey {
+        topic: topic.name.clone(),
+        epoch: private.epoch,
+        members: private.members.clone(),
+        keys,
+        description: topic.description.clone(),
+        policy: topic.policy.clone(),
+    })
+}
+
+/// Stores the key of a private topic sent by `owner` if it was sealed to me.
+/// Private topics are never announced, a topic already in my registry can not
+/// be made private by anyone. The first owner seen for a topic is kept, later
+/// keys must come from it with a newer epoch. Returns the membership, None if
+/// I am not a member.
+pub fn accept_topic_key(
+    db: &Db,
+    keypair: &Keypair,
+    owner: &str,
+    message: &ContentMessage,
+) -> anyhow::Result<Option<PrivateTopic>> {
+    let ContentMessage::TopicKey {
+        topic,
+        epoch,
+        members,
+        keys,
+        ..
+    } = message
+    else {
+        return Err(anyhow!("Not a topic key"));
+    };
+    match db::get_private_topic(db, topic) {
+        Some(known) if known.owner != owner => {
+            return Err(anyhow!("Topic {topic} is owned by {}, not {owner}", known.owner));
+        }
+        Some(known) if *epoch < known.epoch => {
+            return Err(anyhow!("Key of epoch {epoch} of topic {topic} is outdated"));
+        }
+        Some(_) => {}
+        None if db::get_topic(db, topic).is_some() => {
+            return Err(anyhow!("Topic {topic} is already known as a public topic"));
+        }
+        None => {}
+    }
+    let me = keypair.public().to_peer_id().to_string();
+    let private = PrivateTopic {
+        owner: owner.to_string(),
+        members: members.clone(),
+        epoch: *epoch,
+    };
+    let Some((_, sealed)) = keys.iter().find(|(member, _)| *member == me) else {
+        // removed from the topic, the keys I had only open the older content
+        if db::get_private_topic(db, topic).is_some() {
+            db::save_private_topic(db, topic, &private)?;
+        }
+        return Ok(None);
+    };
+    let sealing_key = crypto::shared_key(keypair, owner, TOPIC_KEY_CONTEXT)?;
+    let key: [u8; 32] = crypto::open(
+        &sealing_key,
+        &general_purpose::STANDARD.decode(sealed)?,
+        &key_header(topic, *epoch, &me),
+    )?
+    .try_into()
+    .map_err(|_| anyhow!("Key of topic {topic} has a wrong length"))?;
+    db::save_topic_key(db, topic, *epoch, &key)?;
+    db::save_private_topic(db, topic, &private)?;
+    Ok(Some(private))
+}
+
+/// Wraps signed bytes for a private topic in a `Sealed` message encrypted with
+/// the current topic key. Public topics get the bytes unchanged.
+pub fn seal_for_topic(
+    db: &Db,
+    keypair: &Keypair,
+    topic: &str,
+    data: Vec<u8>,
+) -> anyhow::Result<Vec<u8>> {
+    let Some(private) = db::get_private_topic(db, topic) else {
+        return Ok(data);
+    };
+    let key = db::get_topic_key(db, topic, private.epoch)
+        .ok_or_else(|| anyhow!("No key of epoch {} for private topic {topic}", private.epoch))?;
+    let sealed = crypto::seal(&key, &data, &content_header(topic, private.epoch))?;
+    SignedMessage::sign(
+        ContentMessage::Sealed {
+            topic: topic.to_string(),
+            epoch: private.epoch,
+            sealed: general_purpose::STANDARD.encode(sealed),
+        },
+        keypair,
+    )?
+    .to_bytes()
+}
+
+/// Opens a `Sealed` message of a private topic, the signed bytes it carries.
+pub fn open_sealed(db: &Db, topic: &str, epoch: u32, sealed: &str) -> anyhow::Result<Vec<u8>> {
+    let key = db::get_topic_key(db, topic, epoch)
+        .ok_or_else(|| anyhow!("No key of epoch {epoch} for private topic {topic}"))?;
+    crypto::open(
+        &key,
+        &general_purpose::STANDARD.decode(sealed)?,
+        &content_header(topic, epoch),
+    )
+}
+
+/// Only members get the history or the summary of a private topic.
+pub fn may_read(db: &Db, topic: &str, peer_id: &str) -> bool {
+    db::get_private_topic(db, topic).is_none_or(|private| private.is_member(peer_id))
+}
+
+#[test]
+fn test_topic_key_is_only_opened_by_members() {
+    let owner = Keypair::generate_ed25519();
+    let member = Keypair::generate_ed25519();
+    let outsider = Keypair::generate_ed25519();
+    let owner_id = owner.public().to_peer_id().to_string();
+    let member_id = member.public().to_peer_id().to_string();
+    let tmp_dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+    let [member_db, outsider_db] = tmp_dirs
+        .each_ref()
+        .map(|dir| db::init_db(dir.path().to_str().unwrap()).unwrap());
+
+    let key = new_topic_key();
+    let private = PrivateTopic {
+        owner: owner_id.clone(),
+        members: vec![owner_id.clone(), member_id.clone()],
+        epoch: 1,
+    };
+    let message = topic_key_message(&owner, &Topic::new("secret", ""), &private, &key).unwrap();
+    assert_eq!(
+        accept_topic_key(&member_db, &member, &owner_id, &message).unwrap(),
+        Some(private.clone())
+    );
+    assert_eq!(db::get_topic_key(&member_db, "secret", 1), Some(key));
+    assert!(accept_topic_key(&outsider_db, &outsider, &owner_id, &message).unwrap().is_none());
+
+    // sealed bytes are only opened with the key
+    let sealed = seal_for_topic(&member_db, &member, "secret", b"signed".to_vec()).unwrap();
+    let envelope: SignedMessage = serde_json::from_slice(&sealed).unwrap();
+    let ContentMessage::Sealed { epoch, sealed, .. } = envelope.payload else {
+        panic!("not sealed");
+    };
+    assert_eq!(open_sealed(&member_db, "secret", epoch, &sealed).unwrap(), b"signed");
+    assert!(open_sealed(&outsider_db, "secret", epoch, &sealed).is_err());
+    assert_eq!(
+        seal_for_topic(&outsider_db, &outsider, "public", b"plain".to_vec()).unwrap(),
+        b"plain"
+    );
+
+    // another peer can not take the topic over
+    let forged = topic_key_message(&outsider, &Topic::new("secret", ""), &private, &key).unwrap();
+    let outsider_id = outsider.public().to_peer_id().to_string();
+    assert!(accept_topic_key(&member_db, &member, &outsider_id, &forged).is_err());
+}
+
+#[test]
+fn test_public_topic_can_not_be_made_private() {
+    let attacker = Keypair::generate_ed25519();
+    let victim = Keypair::generate_ed25519();
+    let attacker_id = attacker.public().to_peer_id().to_string();
+    let victim_id = victim.public().to_peer_id().to_string();
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let victim_db = db::init_db(tmp_dir.path().to_str().unwrap()).unwrap();
+    db::add_known_topic(&victim_db, &Topic::new("news", "")).unwrap();
+
+    let private = PrivateTopic {
+        owner: attacker_id.clone(),
+        members: vec![attacker_id.clone(), victim_id],
+        epoch: 1,
+    };
+    let key = new_topic_key();
+    let message = topic_key_message(&attacker, &Topic::new("news", ""), &private, &key).unwrap();
+    assert!(accept_topic_key(&victim_db, &victim, &attacker_id, &message).is_err());
+    assert!(db::get_private_topic(&victim_db, "news").is_none());
+    assert_eq!(
+        seal_for_topic(&victim_db, &victim, "news", b"plain".to_vec()).unwrap(),
+        b"plain"
+    );
+}
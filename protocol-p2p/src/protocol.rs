@@ -1,5 +1,46 @@
+use crate::models::messages::{
+    HistoryPage, HistoryRequest, SummaryRequest, SummaryResponse,
+};
 use libp2p::PeerId;
 
 pub trait MessageHandler: Send + 'static {
     fn handle_message(&mut self, peer: PeerId, data: &[u8], topic: &str) -> Option<Vec<u8>>;
+
+    /// Direct request from a peer, the reply travels back as its response.
+    /// Returning `None`, as this default does, answers the peer with an error
+    /// telling that the request is not served.
+    fn handle_request(&mut self, _peer: PeerId, _data: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Peers found in the DHT as providers of a topic, called with no peers
+    /// when a lookup ends without finding any.
+    fn handle_providers(&mut self, _topic: &str, _providers: &[PeerId]) {}
+
+    /// Page of the approved history of a topic asked by a peer.
+    fn handle_history_request(&mut self, _peer: PeerId, request: &HistoryRequest) -> HistoryPage {
+        HistoryPage {
+            topic: request.topic.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Page of history received from a peer, returns the request for the next page if any.
+    fn handle_history_page(&mut self, _peer: PeerId, _page: HistoryPage) -> Option<HistoryRequest> {
+        None
+    }
+
+    /// Step of the walk of a topic summary asked by a peer.
+    fn handle_summary_request(&mut self, _peer: PeerId, request: &SummaryRequest) -> SummaryResponse {
+        request.empty_response()
+    }
+
+    /// Answer to a step of a summary walk, returns the next step if any.
+    fn handle_summary_response(
+        &mut self,
+        _peer: PeerId,
+        _response: SummaryResponse,
+    ) -> Option<SummaryRequest> {
+        None
+    }
 }
@@ -0,0 +1,142 @@
+use crate::db;
+use crate::models::db::StateContent;
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sled::Db;
+
+/* leaves are spread by the first byte of their hash, one subtree per bucket */
+pub const SUMMARY_BUCKETS: usize = 16;
+/* leaves of withdrawn content, named after the content since any of its votations withdrew it */
+pub const WITHDRAWN_LEAF: &str = "withdrawn/";
+
+/// Two level Merkle tree over the content of a topic: the ids of the approved
+/// content and the withdrawn content, the root hashes the buckets, each bucket
+/// hashes its sorted leaves. Peers with the same root hold the same content,
+/// otherwise only the differing buckets are walked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TopicSummary {
+    pub root: String,
+    pub buckets: Vec<String>,
+    pub count: usize,
+}
+
+fn hash_parts<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Leaf of content once it is withdrawn.
+pub fn withdrawn_leaf(id_votation: &str) -> String {
+    format!("{WITHDRAWN_LEAF}{}", db::votation_content_key(id_votation))
+}
+
+/* both leaves of a content fall in the same bucket, a withdrawal only changes that one */
+pub fn bucket_of(leaf: &str) -> usize {
+    let content_key = match leaf.strip_prefix(WITHDRAWN_LEAF) {
+        Some(content_key) => content_key,
+        None => db::votation_content_key(leaf),
+    };
+    Sha256::digest(content_key.as_bytes())[0] as usize % SUMMARY_BUCKETS
+}
+
+/* the leaves of the tree, sorted, rejected content is left out */
+pub fn topic_leaves(db: &Db, topic: &str) -> Vec<String> {
+    let mut leaves: Vec<String> = db::get_topic_contents(db, topic)
+        .into_iter()
+        .filter_map(|content| match content.approved {
+            StateContent::Approved => Some(content.id_votation),
+            StateContent::Retracted | StateContent::Removed => {
+                Some(withdrawn_leaf(&content.id_votation))
+            }
+            StateContent::Rejected => None,
+        })
+        .collect();
+    leaves.sort();
+    leaves
+}
+
+pub fn topic_summary(db: &Db, topic: &str) -> TopicSummary {
+    let ids = topic_leaves(db, topic);
+    let mut leaves: Vec<Vec<&str>> = vec![Vec::new(); SUMMARY_BUCKETS];
+    for id in &ids {
+        leaves[bucket_of(id)].push(id);
+    }
+    let buckets: Vec<String> = leaves.into_iter().map(hash_parts).collect();
+    TopicSummary {
+        root: hash_parts(buckets.iter().map(String::as_str)),
+        buckets,
+        count: ids.len(),
+    }
+}
+
+/// Buckets where `theirs` does not match `mine`, none when the roots are equal.
+pub fn differing_buckets(mine: &TopicSummary, theirs: &TopicSummary) -> Vec<usize> {
+    if mine.root == theirs.root {
+        return Vec::new();
+    }
+    if theirs.buckets.len() != SUMMARY_BUCKETS {
+        return (0..SUMMARY_BUCKETS).collect();
+    }
+    (0..SUMMARY_BUCKETS)
+        .filter(|bucket| mine.buckets[*bucket] != theirs.buckets[*bucket])
+        .collect()
+}
+
+/// Leaves of the given buckets, at most `limit` of them. A larger answer is a
+/// random sample, the next walks of the summary ask for the rest.
+pub fn bucket_ids(db: &Db, topic: &str, buckets: &[usize], limit: usize) -> Vec<String> {
+    let mut ids = topic_leaves(db, topic)
+        .into_iter()
+        .filter(|id| buckets.contains(&bucket_of(id)))
+        .choose_multiple(&mut rand::rng(), limit);
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_summaries_differ_only_in_the_bucket_of_the_missing_content() {
+    use crate::models::db::DataContent;
+
+    let tmp_dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+    let [mine, theirs] = tmp_dirs
+        .each_ref()
+        .map(|dir| db::init_db(dir.path().to_str().unwrap()).unwrap());
+    let approved = |db: &Db, id: &str| {
+        let content = DataContent::new(id.to_string(), "content".to_string().into(), true);
+        db::include_new_validated_content(db, &content).unwrap();
+    };
+    approved(&mine, "vote_status/topicA:1:pending:1");
+    approved(&theirs, "vote_status/topicA:1:pending:1");
+    assert_eq!(topic_summary(&mine, "topicA"), topic_summary(&theirs, "topicA"));
+
+    let missing = "vote_status/topicA:2:pending:1";
+    approved(&theirs, missing);
+    // rejected content is not part of the tree
+    db::include_new_validated_content(
+        &theirs,
+        &DataContent::new("vote_status/topicA:3:pending:1".to_string(), "other".to_string().into(), false),
+    )
+    .unwrap();
+    let buckets = differing_buckets(&topic_summary(&mine, "topicA"), &topic_summary(&theirs, "topicA"));
+    assert_eq!(buckets, vec![bucket_of(missing)]);
+    assert!(bucket_ids(&theirs, "topicA", &buckets, 10).contains(&missing.to_string()));
+    assert_eq!(topic_summary(&theirs, "topicB").count, 0);
+
+    // a withdrawal changes the leaf of the content, in the same bucket
+    db::withdraw_content(&theirs, missing, StateContent::Retracted).unwrap();
+    assert_eq!(bucket_of(&withdrawn_leaf(missing)), bucket_of(missing));
+    let leaves = bucket_ids(&theirs, "topicA", &buckets, 10);
+    assert!(leaves.contains(&withdrawn_leaf(missing)));
+    assert!(!leaves.contains(&missing.to_string()));
+    let all: Vec<usize> = (0..SUMMARY_BUCKETS).collect();
+    assert_eq!(bucket_ids(&theirs, "topicA", &all, 1).len(), 1);
+}
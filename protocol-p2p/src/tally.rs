@@ -0,0 +1,119 @@
+use crate::models::db::{Tally, TallyMode};
+use crate::models::messages::{Ballot, Vote};
+
+/* reputation of the jurors among the candidates they were drawn from, the publisher
+signs it with the request so every node recounts with the same weights */
+pub fn jury_weights(candidates: &[(String, f32)], voters: &[String]) -> Vec<(String, f32)> {
+    voters
+        .iter()
+        .filter_map(|voter| candidates.iter().find(|(peer_id, _)| peer_id == voter))
+        .cloned()
+        .collect()
+}
+
+/// Counts the ballots of a votation. With `TallyMode::ReputationWeighted` each
+/// juror weighs as much as the reputation snapshotted when the jury was drawn.
+/// Ballots are scaled by their confidence and abstentions are left out.
+pub fn tally(
+    votes: &[(String, Ballot)],
+    weights: &[(String, f32)],
+    mode: &TallyMode,
+    threshold_approve: f32,
+) -> Tally {
+    let ballots: Vec<(String, Vote, f32)> = votes
+        .iter()
+        .map(|(peer_id, ballot)| {
+            let weight = match mode {
+                TallyMode::Simple => 1.0,
+                TallyMode::ReputationWeighted => weights
+                    .iter()
+                    .find(|(id, _)| id == peer_id)
+                    .map(|(_, weight)| weight.max(0.0))
+                    .unwrap_or(0.0),
+            };
+            (peer_id.clone(), ballot.vote, weight * ballot.confidence)
+        })
+        .collect();
+
+    let yes_weight: f32 = ballots
+        .iter()
+        .filter(|(_, vote, _)| matches!(vote, Vote::Yes))
+        .map(|(_, _, weight)| weight)
+        .sum();
+    let total_weight: f32 = ballots
+        .iter()
+        .filter(|(_, vote, _)| !matches!(vote, Vote::Abstain))
+        .map(|(_, _, weight)| weight)
+        .sum();
+    let approve_ratio = if total_weight > 0.0 {
+        yes_weight / total_weight
+    } else {
+        0.0
+    };
+
+    Tally {
+        mode: mode.clone(),
+        ballots,
+        yes_weight,
+        total_weight,
+        approve_ratio,
+        approved: approve_ratio >= threshold_approve,
+    }
+}
+
+#[test]
+fn test_simple_tally_counts_heads() {
+    let votes = vec![
+        ("peer1".to_string(), Vote::Yes.into()),
+        ("peer2".to_string(), Vote::No.into()),
+        ("peer3".to_string(), Vote::Yes.into()),
+    ];
+    let result = tally(&votes, &[], &TallyMode::Simple, 0.6);
+    assert_eq!(result.total_weight, 3.0);
+    assert!(result.approved);
+}
+
+#[test]
+fn test_weighted_tally_follows_reputation() {
+    let votes = vec![
+        ("trusted".to_string(), Vote::No.into()),
+        ("new1".to_string(), Vote::Yes.into()),
+        ("new2".to_string(), Vote::Yes.into()),
+    ];
+    let weights = vec![
+        ("trusted".to_string(), 300.0),
+        ("new1".to_string(), 90.0),
+        ("new2".to_string(), 90.0),
+    ];
+    let result = tally(&votes, &weights, &TallyMode::ReputationWeighted, 0.6);
+    assert_eq!(result.yes_weight, 180.0);
+    assert_eq!(result.total_weight, 480.0);
+    assert!(!result.approved);
+}
+
+#[test]
+fn test_abstain_and_confidence() {
+    let votes = vec![
+        ("peer1".to_string(), Ballot::new(Vote::Yes, 0.5, None)),
+        ("peer2".to_string(), Ballot::new(Vote::No, 0.25, None)),
+        ("peer3".to_string(), Vote::Abstain.into()),
+    ];
+    let result = tally(&votes, &[], &TallyMode::Simple, 0.6);
+    assert_eq!(result.yes_weight, 0.5);
+    assert_eq!(result.total_weight, 0.75);
+    assert!(result.approved);
+}
+
+#[test]
+fn test_jury_weights_come_from_the_candidates() {
+    let candidates = vec![
+        ("new".to_string(), 90.0),
+        ("trusted".to_string(), 300.0),
+        ("other".to_string(), 100.0),
+    ];
+    let voters = vec!["trusted".to_string(), "new".to_string()];
+    assert_eq!(
+        jury_weights(&candidates, &voters),
+        vec![("trusted".to_string(), 300.0), ("new".to_string(), 90.0)]
+    );
+}